@@ -4,7 +4,7 @@ use std::{
 };
 
 use criterion::{criterion_group, criterion_main, Criterion};
-use rand::Rng;
+use rand::{seq::SliceRandom, Rng};
 use xsparseset::{SparseSet, VecStorage};
 
 type EntityId = NonZeroUsize;
@@ -95,5 +95,189 @@ fn insert(criterion: &mut Criterion) {
     });
 }
 
-criterion_group!(benches, insert_batch, insert);
+fn insert_sequential(criterion: &mut Criterion) {
+    let count = 100_000;
+
+    criterion.bench_function("InsertSequential:vec_wrapper:insert", |b| {
+        b.iter(|| {
+            let mut sparse_set: SparseSet<EntityId, usize, VecStorage<EntityId>> =
+                SparseSet::default();
+            for x in 1..=count {
+                sparse_set.insert(EntityId::new(x).unwrap(), x);
+            }
+        });
+    });
+    criterion.bench_function("InsertSequential:vec_wrapper:insert_append_hint", |b| {
+        b.iter(|| {
+            let mut sparse_set: SparseSet<EntityId, usize, VecStorage<EntityId>> =
+                SparseSet::default();
+            for x in 1..=count {
+                sparse_set.insert_append_hint(EntityId::new(x).unwrap(), x);
+            }
+        });
+    });
+    criterion.bench_function("InsertSequential:BTreeMap:insert", |b| {
+        b.iter(|| {
+            let mut sparse_set: SparseSet<EntityId, usize, BTreeMap<EntityId, NonZeroUsize>> =
+                SparseSet::default();
+            for x in 1..=count {
+                sparse_set.insert(EntityId::new(x).unwrap(), x);
+            }
+        });
+    });
+    criterion.bench_function("InsertSequential:BTreeMap:insert_append_hint", |b| {
+        b.iter(|| {
+            let mut sparse_set: SparseSet<EntityId, usize, BTreeMap<EntityId, NonZeroUsize>> =
+                SparseSet::default();
+            for x in 1..=count {
+                sparse_set.insert_append_hint(EntityId::new(x).unwrap(), x);
+            }
+        });
+    });
+}
+
+fn insert_batch_ascending(criterion: &mut Criterion) {
+    let count = 1_000_000;
+    let ids: Vec<EntityId> = (1..=count).map(|x| EntityId::new(x).unwrap()).collect();
+    let data: Vec<usize> = (1..=count).collect();
+
+    criterion.bench_function("InsertBatchAscending:vec_wrapper", |b| {
+        b.iter(|| {
+            let mut ids_in = ids.clone();
+            let mut data_in = data.clone();
+            let mut sparse_set: SparseSet<EntityId, usize, VecStorage<EntityId>> =
+                SparseSet::default();
+            sparse_set.insert_batch(&mut ids_in, &mut data_in);
+        })
+    });
+}
+
+fn get(criterion: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut sparse_set: SparseSet<EntityId, u64, VecStorage<EntityId>> = SparseSet::default();
+    for x in 1..100_000 {
+        if rng.gen_bool(0.5) {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u64);
+        }
+    }
+    let queries: Vec<EntityId> = (0..10_000)
+        .map(|_| EntityId::new(rng.gen_range(1..100_000)).unwrap())
+        .collect();
+
+    criterion.bench_function("Get:vec_wrapper:get", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .filter_map(|id| sparse_set.get(*id))
+                .sum::<u64>()
+        })
+    });
+    criterion.bench_function("Get:vec_wrapper:contains", |b| {
+        b.iter(|| queries.iter().filter(|id| sparse_set.contains(**id)).count())
+    });
+
+    // the same entities and queries through the generic path of another storage
+    let mut hash_map_set: SparseSet<EntityId, u64, HashMap<EntityId, NonZeroUsize>> =
+        SparseSet::default();
+    for (id, x) in sparse_set.iter() {
+        hash_map_set.insert(id, *x);
+    }
+    criterion.bench_function("Get:HashMap:get", |b| {
+        b.iter(|| {
+            queries
+                .iter()
+                .filter_map(|id| hash_map_set.get(*id))
+                .sum::<u64>()
+        })
+    });
+    criterion.bench_function("Get:HashMap:contains", |b| {
+        b.iter(|| queries.iter().filter(|id| hash_map_set.contains(**id)).count())
+    });
+}
+
+fn apply_batch(criterion: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut sparse_set: SparseSet<EntityId, u64, VecStorage<EntityId>> = SparseSet::default();
+    for x in 1..100_000 {
+        if rng.gen_bool(0.5) {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u64);
+        }
+    }
+    let ids: Vec<EntityId> = (0..10_000)
+        .map(|_| EntityId::new(rng.gen_range(1..100_000)).unwrap())
+        .collect();
+
+    criterion.bench_function("ApplyBatch:vec_wrapper:get_mut_loop", |b| {
+        b.iter(|| {
+            for id in &ids {
+                if let Some(x) = sparse_set.get_mut(*id) {
+                    *x += 1;
+                }
+            }
+        })
+    });
+    criterion.bench_function("ApplyBatch:vec_wrapper:apply_batch", |b| {
+        b.iter(|| sparse_set.apply_batch(&ids, |_, x| *x += 1))
+    });
+}
+
+fn apply_batch_large(criterion: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut sparse_set: SparseSet<EntityId, u64, VecStorage<EntityId>> = SparseSet::default();
+    for x in 1..4_000_000 {
+        if rng.gen_bool(0.5) {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u64);
+        }
+    }
+    let ids: Vec<EntityId> = (0..100_000)
+        .map(|_| EntityId::new(rng.gen_range(1..4_000_000)).unwrap())
+        .collect();
+
+    criterion.bench_function("ApplyBatchLarge:vec_wrapper:get_mut_loop", |b| {
+        b.iter(|| {
+            for id in &ids {
+                if let Some(x) = sparse_set.get_mut(*id) {
+                    *x += 1;
+                }
+            }
+        })
+    });
+    criterion.bench_function("ApplyBatchLarge:vec_wrapper:apply_batch", |b| {
+        b.iter(|| sparse_set.apply_batch(&ids, |_, x| *x += 1))
+    });
+}
+
+fn iter_by_id_asc(criterion: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+    let mut sparse_set: SparseSet<EntityId, u64, VecStorage<EntityId>> = SparseSet::default();
+    // 1% occupancy, inserted in random order
+    let mut ids: Vec<usize> = (1..1_000_000).filter(|_| rng.gen_bool(0.01)).collect();
+    ids.shuffle(&mut rng);
+    for x in ids {
+        sparse_set.insert(EntityId::new(x).unwrap(), x as u64);
+    }
+
+    criterion.bench_function("IterByIdAsc:vec_wrapper:sort", |b| {
+        b.iter(|| {
+            let mut pairs: Vec<_> = sparse_set.iter().collect();
+            pairs.sort_unstable_by_key(|(id, _)| *id);
+            pairs.iter().map(|(_, x)| **x).sum::<u64>()
+        })
+    });
+    criterion.bench_function("IterByIdAsc:vec_wrapper:iter_by_id_asc", |b| {
+        b.iter(|| sparse_set.iter_by_id_asc().map(|(_, x)| *x).sum::<u64>())
+    });
+}
+
+criterion_group!(
+    benches,
+    get,
+    insert_batch,
+    insert,
+    insert_sequential,
+    insert_batch_ascending,
+    apply_batch,
+    apply_batch_large,
+    iter_by_id_asc
+);
 criterion_main!(benches);
@@ -0,0 +1,37 @@
+use std::num::NonZeroUsize;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+use rand::Rng;
+use xsparseset::SparseSetVec;
+
+type EntityId = NonZeroUsize;
+
+fn union(criterion: &mut Criterion) {
+    let mut rng = rand::thread_rng();
+
+    let count = 1_000_000;
+    let mut a: SparseSetVec<EntityId, u64> = SparseSetVec::default();
+    let mut b: SparseSetVec<EntityId, u64> = SparseSetVec::default();
+    for _ in 0..count {
+        let id = EntityId::new(rng.gen_range(1..2 * count)).unwrap();
+        a.insert(id, rng.gen());
+        let id = EntityId::new(rng.gen_range(1..2 * count)).unwrap();
+        b.insert(id, rng.gen());
+    }
+
+    criterion.bench_function("Union:sequential", |bench| {
+        bench.iter(|| {
+            let mut a = a.clone();
+            a.union_with(&b, |_, x, y| *x = x.wrapping_add(*y));
+        })
+    });
+    criterion.bench_function("Union:parallel", |bench| {
+        bench.iter(|| {
+            let mut a = a.clone();
+            a.par_union_with(&b, |_, x, y| *x = x.wrapping_add(*y));
+        })
+    });
+}
+
+criterion_group!(benches, union);
+criterion_main!(benches);
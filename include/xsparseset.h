@@ -0,0 +1,118 @@
+#ifndef XSPARSESET_H
+#define XSPARSESET_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The handle of a sparse set with `u64` ids and `f32` data
+ * # Details
+ * It is opaque in C, only used by pointers
+ */
+typedef struct XSparseSetU64F32 XSparseSetU64F32;
+
+/**
+ * The dense array of ids
+ */
+typedef struct XSparseSetU64Slice {
+  const uint64_t *ptr;
+  size_t len;
+} XSparseSetU64Slice;
+
+/**
+ * The dense array of data, in the same order as the ids
+ */
+typedef struct XSparseSetF32Slice {
+  float *ptr;
+  size_t len;
+} XSparseSetF32Slice;
+
+/**
+ * Create an empty sparse set
+ * # Returns
+ * Return a handle which must be freed by `xsparseset_u64_f32_destroy`
+ */
+struct XSparseSetU64F32 *xsparseset_u64_f32_create(void);
+
+/**
+ * Free the sparse set, it does nothing if `set` is null
+ * # Safety
+ * `set` must be null or a handle not destroyed yet
+ */
+void xsparseset_u64_f32_destroy(struct XSparseSetU64F32 *set);
+
+/**
+ * Insert the `value` with `id`, replacing the old value if the set has `id`
+ * # Returns
+ * Return true if `id` is new, which is a structural mutation
+ * # Safety
+ * `set` must be a valid handle
+ */
+bool xsparseset_u64_f32_insert(struct XSparseSetU64F32 *set, uint64_t id, float value);
+
+/**
+ * Remove the entity by `id`
+ * # Details
+ * The removed value is written to `out` if `out` is not null
+ * # Returns
+ * Return false if the set doesn't contain `id`
+ * # Safety
+ * `set` must be a valid handle, `out` must be null or writable
+ */
+bool xsparseset_u64_f32_remove(struct XSparseSetU64F32 *set, uint64_t id, float *out);
+
+/**
+ * Check if the set contains `id`
+ * # Safety
+ * `set` must be a valid handle
+ */
+bool xsparseset_u64_f32_contains(const struct XSparseSetU64F32 *set, uint64_t id);
+
+/**
+ * Get the pointer of value by given `id`
+ * # Returns
+ * Return null if the set doesn't contain `id`,
+ * the pointer is valid until the next structural mutation
+ * # Safety
+ * `set` must be a valid handle
+ */
+float *xsparseset_u64_f32_get(struct XSparseSetU64F32 *set, uint64_t id);
+
+/**
+ * Get the count of entities
+ * # Safety
+ * `set` must be a valid handle
+ */
+size_t xsparseset_u64_f32_len(const struct XSparseSetU64F32 *set);
+
+/**
+ * Remove all entities
+ * # Safety
+ * `set` must be a valid handle
+ */
+void xsparseset_u64_f32_clear(struct XSparseSetU64F32 *set);
+
+/**
+ * Get the dense array of ids
+ * # Details
+ * The ids must not be written,
+ * the pointer is valid until the next structural mutation
+ * # Safety
+ * `set` must be a valid handle
+ */
+struct XSparseSetU64Slice xsparseset_u64_f32_ids(const struct XSparseSetU64F32 *set);
+
+/**
+ * Get the dense array of data
+ * # Details
+ * The data can be written in place,
+ * the pointer is valid until the next structural mutation
+ * # Safety
+ * `set` must be a valid handle
+ */
+struct XSparseSetF32Slice xsparseset_u64_f32_data(struct XSparseSetU64F32 *set);
+
+#endif  /* XSPARSESET_H */
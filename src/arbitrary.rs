@@ -0,0 +1,71 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{SparseSet, SparseSetLimits, SparseStorage};
+
+/// The max count of sparse slots of a generated sparse set,
+/// so random ids can't make `VecStorage` allocate huge slots
+const ARBITRARY_MAX_SPARSE_SLOTS: usize = 1 << 16;
+
+impl<'a, E, T, S> Arbitrary<'a> for SparseSet<E, T, S>
+where
+    E: Copy + Arbitrary<'a>,
+    T: Arbitrary<'a>,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    /// Generate a sparse set by inserting random `(id, data)` pairs
+    /// # Details
+    /// Only the public insertion is used, so the invariants always hold.
+    /// Ids needing more than 65536 sparse slots are skipped
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let mut sparse_set = SparseSet::default();
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: None,
+            max_sparse_slots: Some(ARBITRARY_MAX_SPARSE_SLOTS),
+        });
+        for pair in u.arbitrary_iter::<(E, T)>()? {
+            let (id, dat) = pair?;
+            // the ids over the limit are skipped
+            let _ = sparse_set.try_insert(id, dat);
+        }
+        sparse_set.set_limits(SparseSetLimits::default());
+        Ok(sparse_set)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use arbitrary::{Arbitrary, Unstructured};
+    use rand::{thread_rng, Rng};
+
+    use crate::{SparseSetHashMap, SparseSetVec};
+
+    #[test]
+    fn arbitrary_test() {
+        let mut rng = thread_rng();
+        for _ in 0..200 {
+            let len = rng.gen_range(0..4096);
+            let bytes: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+
+            let mut u = Unstructured::new(&bytes);
+            let mut sparse_set = SparseSetVec::<NonZeroUsize, u8>::arbitrary(&mut u).unwrap();
+            assert_eq!(sparse_set.check_invariants(), Ok(()));
+            let ids = sparse_set.ids().to_vec();
+            for id in ids.iter().step_by(2) {
+                sparse_set.swap_remove_by_id(*id);
+            }
+            let mut order = sparse_set.ids().to_vec();
+            order.reverse();
+            sparse_set.sort_by_id_order(&order).unwrap();
+            if !sparse_set.is_empty() {
+                sparse_set.rotate_left(1);
+            }
+            assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+            let mut u = Unstructured::new(&bytes);
+            let sparse_set = SparseSetHashMap::<u64, i32>::arbitrary(&mut u).unwrap();
+            assert_eq!(sparse_set.check_invariants(), Ok(()));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use std::collections::VecDeque;
+
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A sparse set holding at most `max_len` entities,
+/// inserting more evicts the oldest inserted one
+/// # Details
+/// The insertion order is kept in a ring of `(id, sequence)`.
+/// Removed entities leave stale records in the ring,
+/// they are skipped on eviction and compacted when the ring grows twice the length
+#[derive(Debug, Clone)]
+pub struct BoundedSparseSet<E, T, S = VecStorage<E>> {
+    /// The data with the sequence number of its insertion
+    set: SparseSet<E, (T, u64), S>,
+    order: VecDeque<(E, u64)>,
+    next_sequence: u64,
+    max_len: usize,
+}
+
+impl<E, T, S> BoundedSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Create a bounded sparse set
+    /// # Panics
+    /// Panic if `max_len` is 0
+    pub fn new(max_len: usize) -> Self
+    where
+        S: Default,
+    {
+        if max_len == 0 {
+            panic!("max_len must not be 0");
+        }
+        BoundedSparseSet {
+            set: SparseSet::default(),
+            order: VecDeque::new(),
+            next_sequence: 0,
+            max_len,
+        }
+    }
+
+    /// Get the max count of entities
+    pub fn max_len(&self) -> usize {
+        self.max_len
+    }
+
+    /// Insert the `dat` with `id`
+    /// # Details
+    /// Replacing the data of an existing `id` keeps its insertion order
+    /// # Returns
+    /// Return the pair pushed out:
+    /// the old data of `id` if it was in the set,
+    /// or the oldest entity evicted if the set was full,
+    /// otherwise None
+    pub fn insert(&mut self, id: E, dat: T) -> Option<(E, T)> {
+        if let Some((old, _)) = self.set.get_mut(id) {
+            return Some((id, std::mem::replace(old, dat)));
+        }
+        let evicted = if self.len() == self.max_len {
+            self.evict_oldest()
+        } else {
+            None
+        };
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        self.set.insert(id, (dat, sequence));
+        self.order.push_back((id, sequence));
+        evicted
+    }
+
+    /// Remove the oldest inserted entity
+    /// # Returns
+    /// Return None if the set is empty
+    pub fn evict_oldest(&mut self) -> Option<(E, T)> {
+        while let Some((id, sequence)) = self.order.pop_front() {
+            if self
+                .set
+                .get(id)
+                .is_some_and(|(_, current)| *current == sequence)
+            {
+                let (dat, _) = self.set.swap_remove_by_id(id)?;
+                return Some((id, dat));
+            }
+        }
+        None
+    }
+
+    /// Remove the entity
+    /// # Returns
+    /// Return None if the set doesn't contain `id`
+    pub fn remove(&mut self, id: E) -> Option<T> {
+        let (dat, _) = self.set.swap_remove_by_id(id)?;
+        if self.order.len() > 2 * self.len() {
+            let set = &self.set;
+            self.order.retain(|(id, sequence)| {
+                set.get(*id).is_some_and(|(_, current)| current == sequence)
+            });
+        }
+        Some(dat)
+    }
+
+    /// Get the oldest inserted entity
+    pub fn oldest(&self) -> Option<(E, &T)> {
+        self.order
+            .iter()
+            .find_map(|(id, sequence)| match self.set.get(*id) {
+                Some((dat, current)) if current == sequence => Some((*id, dat)),
+                _ => None,
+            })
+    }
+
+    /// Check if the set has id
+    pub fn contains(&self, id: E) -> bool {
+        self.set.contains(id)
+    }
+
+    /// Get the reference of data by given `id`
+    pub fn get(&self, id: E) -> Option<&T> {
+        self.set.get(id).map(|(dat, _)| dat)
+    }
+
+    /// Get the MUTABLE reference of data by given `id`
+    pub fn get_mut(&mut self, id: E) -> Option<&mut T> {
+        self.set.get_mut(id).map(|(dat, _)| dat)
+    }
+
+    /// Get the count of entities
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Remove all entities
+    pub fn clear(&mut self) {
+        self.set.clear();
+        self.order.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::BoundedSparseSet;
+
+    #[test]
+    fn evict_test() {
+        let mut cache: BoundedSparseSet<usize, usize> = BoundedSparseSet::new(3);
+        assert_eq!(cache.insert(10, 1), None);
+        assert_eq!(cache.insert(20, 2), None);
+        assert_eq!(cache.insert(30, 3), None);
+        assert_eq!(cache.oldest(), Some((10, &1)));
+
+        // replacing keeps the order
+        assert_eq!(cache.insert(10, 11), Some((10, 1)));
+        assert_eq!(cache.insert(40, 4), Some((10, 11)));
+        assert_eq!(cache.insert(50, 5), Some((20, 2)));
+        assert_eq!(cache.len(), 3);
+
+        // removed entities are skipped
+        assert_eq!(cache.remove(30), Some(3));
+        assert_eq!(cache.insert(60, 6), None);
+        assert_eq!(cache.insert(30, 33), Some((40, 4)));
+        assert_eq!(cache.insert(70, 7), Some((50, 5)));
+        assert_eq!(cache.insert(80, 8), Some((60, 6)));
+        assert_eq!(cache.insert(90, 9), Some((30, 33)));
+        assert_eq!(cache.get(70), Some(&7));
+        assert!(!cache.contains(10));
+
+        for id in 100..200 {
+            cache.insert(id, id);
+        }
+        assert_eq!(cache.oldest(), Some((197, &197)));
+        assert_eq!(cache.evict_oldest(), Some((197, 197)));
+        assert_eq!(cache.len(), 2);
+        cache.clear();
+        assert_eq!(cache.evict_oldest(), None);
+    }
+}
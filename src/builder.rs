@@ -0,0 +1,357 @@
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// Configure a `SparseSet` and collect `(id, data)` pairs to build it in one pass
+/// # Details
+/// `push` only appends to plain `Vec`s, the sparse storage is untouched
+/// until `build`. It is much faster than calling `insert` one by one
+/// for loading a whole sparse set at once.  
+/// A builder without any setter called builds the same set as `SparseSet::default()`
+#[derive(Debug, Clone)]
+pub struct SparseSetBuilder<E, T, S = VecStorage<E>> {
+    sparse: S,
+    pairs: Vec<(E, T)>,
+    dense_capacity: usize,
+    sparse_hint: usize,
+}
+
+impl<E, T, S> Default for SparseSetBuilder<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        SparseSetBuilder {
+            sparse: S::default(),
+            pairs: Vec::new(),
+            dense_capacity: 0,
+            sparse_hint: 0,
+        }
+    }
+}
+
+impl<E, T, S> SparseSetBuilder<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Create a builder with sparse storage
+    /// # Details
+    /// The storage is cleared, the memory it reserved is kept
+    pub fn with_storage(mut sparse_storage: S) -> Self {
+        sparse_storage.clear();
+        SparseSetBuilder {
+            sparse: sparse_storage,
+            pairs: Vec::new(),
+            dense_capacity: 0,
+            sparse_hint: 0,
+        }
+    }
+
+    /// Replace the sparse storage
+    /// # Details
+    /// The storage is cleared, the memory it reserved is kept
+    pub fn storage<S2>(self, mut sparse_storage: S2) -> SparseSetBuilder<E, T, S2>
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        sparse_storage.clear();
+        SparseSetBuilder {
+            sparse: sparse_storage,
+            pairs: self.pairs,
+            dense_capacity: self.dense_capacity,
+            sparse_hint: self.sparse_hint,
+        }
+    }
+
+    /// Reserve capacity for at least `capacity` entities in the dense arrays
+    pub fn dense_capacity(mut self, capacity: usize) -> Self {
+        self.dense_capacity = capacity;
+        self
+    }
+
+    /// Tell the sparse storage to reserve for about `hint` entity ids
+    /// # Details
+    /// See `SparseStorage::reserve` for the meaning of `hint`
+    pub fn sparse_hint(mut self, hint: usize) -> Self {
+        self.sparse_hint = hint;
+        self
+    }
+
+    /// Get the count of pairs pushed, including duplicated ids
+    pub fn len(&self) -> usize {
+        self.pairs.len()
+    }
+
+    /// Check if nothing was pushed
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+}
+
+impl<E, T, S> SparseSetBuilder<E, T, S>
+where
+    E: Copy + Ord,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Add a pair
+    /// # Details
+    /// If the `id` was pushed before, the last one wins in `build`
+    pub fn push(&mut self, id: E, dat: T) -> &mut Self {
+        self.pairs.push((id, dat));
+        self
+    }
+
+    /// Build the sparse set
+    /// # Details
+    /// The entities are stored in ascending order of id
+    pub fn build(self) -> SparseSet<E, T, S> {
+        let mut pairs = self.pairs;
+        // stable sort keeps the push order of same id
+        pairs.sort_by_key(|(id, _)| *id);
+
+        let mut ids: Vec<E> = Vec::with_capacity(pairs.len());
+        let mut data: Vec<T> = Vec::with_capacity(pairs.len());
+        for (id, dat) in pairs {
+            match (ids.last(), data.last_mut()) {
+                (Some(last_id), Some(last_dat)) if *last_id == id => *last_dat = dat,
+                _ => {
+                    ids.push(id);
+                    data.push(dat);
+                }
+            }
+        }
+
+        let mut sparse = self.sparse;
+        if self.sparse_hint > 0 {
+            sparse.reserve(self.sparse_hint);
+        }
+        let mut sparse_set = SparseSet::with_storage(sparse);
+        sparse_set.reserve(self.dense_capacity.max(ids.len()));
+        sparse_set.insert_batch(&mut ids, &mut data);
+        sparse_set
+    }
+}
+
+impl<E, T, S> Extend<(E, T)> for SparseSetBuilder<E, T, S> {
+    fn extend<I: IntoIterator<Item = (E, T)>>(&mut self, iter: I) {
+        self.pairs.extend(iter);
+    }
+}
+
+/// Collect `(id, data)` pairs in strictly ascending order of id to build a `SparseSet`
+/// # Details
+/// Unlike `SparseSetBuilder`, `push` only compares with the last id
+/// and `build` neither sorts nor deduplicates,
+/// so it is the fastest way to load pairs already sorted by id.
+/// The dense array of the built set is sorted, so `ids().binary_search` works
+#[derive(Debug, Clone)]
+pub struct SortedBuilder<E, T, S = VecStorage<E>> {
+    sparse: S,
+    ids: Vec<E>,
+    data: Vec<T>,
+}
+
+impl<E, T, S> Default for SortedBuilder<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        SortedBuilder::with_storage(S::default())
+    }
+}
+
+impl<E, T, S> SortedBuilder<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Create a builder with sparse storage
+    /// # Details
+    /// The storage is cleared, the memory it reserved is kept
+    pub fn with_storage(mut sparse_storage: S) -> Self {
+        sparse_storage.clear();
+        SortedBuilder {
+            sparse: sparse_storage,
+            ids: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    /// Reserve capacity for at least `additional` more pairs
+    pub fn reserve(&mut self, additional: usize) {
+        self.ids.reserve(additional);
+        self.data.reserve(additional);
+    }
+
+    /// Get the last pushed id
+    pub fn last_id(&self) -> Option<E> {
+        self.ids.last().copied()
+    }
+
+    /// Get the count of pairs pushed
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if nothing was pushed
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+impl<E, T, S> SortedBuilder<E, T, S>
+where
+    E: Copy + Ord,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Append a pair
+    /// # Panics
+    /// Panic if `id` is not greater than the last pushed id
+    pub fn push(&mut self, id: E, dat: T) -> &mut Self {
+        if self.ids.last().is_some_and(|last| *last >= id) {
+            panic!("the ids must be pushed in strictly ascending order");
+        }
+        self.ids.push(id);
+        self.data.push(dat);
+        self
+    }
+
+    /// Build the sparse set, filling the sparse storage in one pass
+    pub fn build(self) -> SparseSet<E, T, S> {
+        let mut ids = self.ids;
+        let mut data = self.data;
+        let mut sparse_set = SparseSet::with_storage(self.sparse);
+        sparse_set.insert_batch(&mut ids, &mut data);
+        sparse_set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, num::NonZeroUsize};
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{
+        SortedBuilder, SparseSet, SparseSetBuilder, SparseSetHashMap, SparseSetVec, SparseStorage,
+        VecStorage,
+    };
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn build_test() {
+        let mut rng = thread_rng();
+        let mut builder: SparseSetBuilder<EntityId, u32> = SparseSetBuilder::default();
+        let mut expected: SparseSetHashMap<EntityId, u32> = SparseSetHashMap::default();
+        for _ in 0..100_000 {
+            let id = EntityId::new(rng.gen_range(1..200_000)).unwrap();
+            let x = rng.gen();
+            builder.push(id, x);
+            expected.insert(id, x);
+        }
+        let sparse_set: SparseSetVec<EntityId, u32> = builder.build();
+
+        assert_eq!(sparse_set.len(), expected.len());
+        assert!(sparse_set.ids().windows(2).all(|w| w[0] < w[1]));
+        for (id, x) in expected.iter() {
+            assert_eq!(sparse_set.get(id), Some(x));
+        }
+    }
+
+    #[test]
+    fn sorted_builder_test() {
+        let mut builder: SortedBuilder<EntityId, usize> = SortedBuilder::default();
+        builder.reserve(100);
+        for x in (3..300).step_by(3) {
+            builder.push(EntityId::new(x).unwrap(), x * 2);
+        }
+        assert_eq!(builder.len(), 99);
+        assert_eq!(builder.last_id(), EntityId::new(297));
+
+        let sparse_set = builder.build();
+        assert_eq!(sparse_set.len(), 99);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert!(sparse_set.ids().windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            sparse_set.ids().binary_search(&EntityId::new(150).unwrap()),
+            Ok(49)
+        );
+        assert!(sparse_set
+            .ids()
+            .binary_search(&EntityId::new(151).unwrap())
+            .is_err());
+        assert_eq!(sparse_set.get(EntityId::new(150).unwrap()), Some(&300));
+
+        let empty: SparseSetHashMap<EntityId, ()> = SortedBuilder::default().build();
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn sorted_builder_out_of_order_test() {
+        let mut builder: SortedBuilder<EntityId, ()> = SortedBuilder::default();
+        builder.push(EntityId::new(5).unwrap(), ());
+        builder.push(EntityId::new(4).unwrap(), ());
+    }
+
+    #[test]
+    fn config_test() {
+        let sparse_set: SparseSetVec<EntityId, u32> = SparseSetBuilder::default().build();
+        let default: SparseSetVec<EntityId, u32> = SparseSet::default();
+        assert_eq!(sparse_set.capacity(), default.capacity());
+        assert_eq!(format!("{:?}", sparse_set), format!("{:?}", default));
+
+        let sparse_set: SparseSetVec<EntityId, u32> = SparseSetBuilder::default()
+            .dense_capacity(100)
+            .sparse_hint(1000)
+            .build();
+        assert!(sparse_set.capacity() >= 100);
+        let (sparse, _, _) = sparse_set.into_raw_parts();
+        assert!(sparse.slot_capacity() >= 1000);
+
+        let mut builder = SparseSetBuilder::<EntityId, u32, VecStorage<EntityId>>::default()
+            .storage(HashMap::new())
+            .dense_capacity(10);
+        builder.push(EntityId::new(3).unwrap(), 3);
+        let sparse_set: SparseSetHashMap<EntityId, u32> = builder.build();
+        assert!(sparse_set.capacity() >= 10);
+        assert_eq!(sparse_set.get(EntityId::new(3).unwrap()), Some(&3));
+
+        // configuring doesn't need ordered ids
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        struct Unordered(u32);
+        let builder = SparseSetBuilder::<Unordered, u32, HashMap<_, _>>::default()
+            .dense_capacity(10)
+            .sparse_hint(10);
+        assert!(builder.is_empty());
+        assert!(SortedBuilder::<Unordered, u32, HashMap<_, _>>::default().is_empty());
+    }
+
+    #[test]
+    fn non_empty_storage_test() {
+        let stale = |x| {
+            let mut storage = VecStorage::default();
+            storage.set_index(EntityId::new(x).unwrap(), NonZeroUsize::new(1));
+            storage
+        };
+        let id = |x| EntityId::new(x).unwrap();
+
+        let mut builder = SparseSetBuilder::with_storage(stale(7));
+        builder.push(id(2), 'b');
+        let sparse_set = builder.build();
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert!(!sparse_set.contains(id(7)));
+
+        let mut builder = SparseSetBuilder::<EntityId, char>::default().storage(stale(7));
+        builder.push(id(2), 'b');
+        assert!(!builder.build().contains(id(7)));
+
+        let mut builder = SortedBuilder::with_storage(stale(7));
+        builder.push(id(2), 'b');
+        let sparse_set = builder.build();
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert!(!sparse_set.contains(id(7)));
+    }
+}
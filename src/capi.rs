@@ -0,0 +1,271 @@
+//! The C API of `SparseSet<u64, f32>`
+//! # Invalidation rules
+//! * A handle is valid from `xsparseset_u64_f32_create` until `xsparseset_u64_f32_destroy`
+//! * A pointer returned by `xsparseset_u64_f32_get`, `xsparseset_u64_f32_ids`
+//!   or `xsparseset_u64_f32_data` is valid until the next structural mutation of the set,
+//!   which is `xsparseset_u64_f32_insert` of a new id, `xsparseset_u64_f32_remove`,
+//!   `xsparseset_u64_f32_clear` or `xsparseset_u64_f32_destroy`
+//! * Replacing the value of an id in the set, or writing through a data pointer,
+//!   is not a structural mutation
+//! * A removal moves the last entity into the hole,
+//!   so the dense index of an entity is only stable between structural mutations
+//! * A handle must not be used by 2 threads at the same time
+//! # Linking
+//! The crate builds as a `staticlib` and a `cdylib` besides the `rlib`,
+//! build it with `--features capi` and include `include/xsparseset.h`
+use std::ptr;
+
+use crate::{SparseSet, SparseSetHashMap};
+
+/// The handle of a sparse set with `u64` ids and `f32` data
+/// # Details
+/// It is opaque in C, only used by pointers
+pub struct XSparseSetU64F32 {
+    set: SparseSetHashMap<u64, f32>,
+}
+
+/// The dense array of ids
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XSparseSetU64Slice {
+    pub ptr: *const u64,
+    pub len: usize,
+}
+
+/// The dense array of data, in the same order as the ids
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct XSparseSetF32Slice {
+    pub ptr: *mut f32,
+    pub len: usize,
+}
+
+/// Create an empty sparse set
+/// # Returns
+/// Return a handle which must be freed by `xsparseset_u64_f32_destroy`
+#[no_mangle]
+pub extern "C" fn xsparseset_u64_f32_create() -> *mut XSparseSetU64F32 {
+    Box::into_raw(Box::new(XSparseSetU64F32 {
+        set: SparseSet::default(),
+    }))
+}
+
+/// Free the sparse set, it does nothing if `set` is null
+/// # Safety
+/// `set` must be null or a handle not destroyed yet
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_destroy(set: *mut XSparseSetU64F32) {
+    if !set.is_null() {
+        drop(Box::from_raw(set));
+    }
+}
+
+/// Insert the `value` with `id`, replacing the old value if the set has `id`
+/// # Returns
+/// Return true if `id` is new, which is a structural mutation
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_insert(
+    set: *mut XSparseSetU64F32,
+    id: u64,
+    value: f32,
+) -> bool {
+    (*set).set.insert(id, value).is_none()
+}
+
+/// Remove the entity by `id`
+/// # Details
+/// The removed value is written to `out` if `out` is not null
+/// # Returns
+/// Return false if the set doesn't contain `id`
+/// # Safety
+/// `set` must be a valid handle, `out` must be null or writable
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_remove(
+    set: *mut XSparseSetU64F32,
+    id: u64,
+    out: *mut f32,
+) -> bool {
+    match (*set).set.swap_remove_by_id(id) {
+        Some(value) => {
+            if !out.is_null() {
+                out.write(value);
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Check if the set contains `id`
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_contains(
+    set: *const XSparseSetU64F32,
+    id: u64,
+) -> bool {
+    (*set).set.contains(id)
+}
+
+/// Get the pointer of value by given `id`
+/// # Returns
+/// Return null if the set doesn't contain `id`,
+/// the pointer is valid until the next structural mutation
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_get(set: *mut XSparseSetU64F32, id: u64) -> *mut f32 {
+    match (*set).set.get_mut(id) {
+        Some(value) => value,
+        None => ptr::null_mut(),
+    }
+}
+
+/// Get the count of entities
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_len(set: *const XSparseSetU64F32) -> usize {
+    (*set).set.len()
+}
+
+/// Remove all entities
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_clear(set: *mut XSparseSetU64F32) {
+    (*set).set.clear();
+}
+
+/// Get the dense array of ids
+/// # Details
+/// The ids must not be written,
+/// the pointer is valid until the next structural mutation
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_ids(
+    set: *const XSparseSetU64F32,
+) -> XSparseSetU64Slice {
+    let ids = (*set).set.ids();
+    XSparseSetU64Slice {
+        ptr: ids.as_ptr(),
+        len: ids.len(),
+    }
+}
+
+/// Get the dense array of data
+/// # Details
+/// The data can be written in place,
+/// the pointer is valid until the next structural mutation
+/// # Safety
+/// `set` must be a valid handle
+#[no_mangle]
+pub unsafe extern "C" fn xsparseset_u64_f32_data(set: *mut XSparseSetU64F32) -> XSparseSetF32Slice {
+    let data = (*set).set.data_mut();
+    XSparseSetF32Slice {
+        ptr: data.as_mut_ptr(),
+        len: data.len(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ptr, slice};
+
+    use super::*;
+
+    #[test]
+    fn capi_test() {
+        unsafe {
+            let set = xsparseset_u64_f32_create();
+            assert_eq!(xsparseset_u64_f32_len(set), 0);
+            assert!(xsparseset_u64_f32_insert(set, 1 << 40, 1.5));
+            assert!(xsparseset_u64_f32_insert(set, 7, 2.5));
+            assert!(xsparseset_u64_f32_insert(set, 9, 3.5));
+            assert!(!xsparseset_u64_f32_insert(set, 7, 4.5));
+            assert_eq!(xsparseset_u64_f32_len(set), 3);
+            assert!(xsparseset_u64_f32_contains(set, 1 << 40));
+            assert!(!xsparseset_u64_f32_contains(set, 8));
+
+            // write through the pointer
+            let value = xsparseset_u64_f32_get(set, 7);
+            assert_eq!(*value, 4.5);
+            *value = 5.5;
+            assert!(xsparseset_u64_f32_get(set, 8).is_null());
+
+            let ids = xsparseset_u64_f32_ids(set);
+            let data = xsparseset_u64_f32_data(set);
+            assert_eq!(slice::from_raw_parts(ids.ptr, ids.len), &[1 << 40, 7, 9]);
+            assert_eq!(slice::from_raw_parts(data.ptr, data.len), &[1.5, 5.5, 3.5]);
+            *data.ptr.add(2) = 6.5;
+
+            let mut out = 0.0;
+            assert!(xsparseset_u64_f32_remove(set, 1 << 40, &mut out));
+            assert_eq!(out, 1.5);
+            assert!(!xsparseset_u64_f32_remove(set, 1 << 40, &mut out));
+            assert!(xsparseset_u64_f32_remove(set, 7, ptr::null_mut()));
+            let ids = xsparseset_u64_f32_ids(set);
+            assert_eq!(slice::from_raw_parts(ids.ptr, ids.len), &[9]);
+            assert_eq!(*xsparseset_u64_f32_get(set, 9), 6.5);
+
+            xsparseset_u64_f32_clear(set);
+            assert_eq!(xsparseset_u64_f32_len(set), 0);
+            xsparseset_u64_f32_destroy(set);
+            xsparseset_u64_f32_destroy(ptr::null_mut());
+        }
+    }
+
+    /// The header generated by `cbindgen`, see `cbindgen.toml`
+    const HEADER: &str = include_str!("../include/xsparseset.h");
+
+    #[test]
+    fn header_declares_every_function_test() {
+        let source = include_str!("capi.rs");
+        let functions: Vec<&str> = source
+            .lines()
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .filter_map(|rest| rest.split('(').next())
+            .collect();
+        assert_eq!(functions.len(), 10);
+        for function in functions {
+            assert!(
+                HEADER.contains(&format!("{}(", function)),
+                "{} is missing in include/xsparseset.h, regenerate it by cbindgen",
+                function
+            );
+        }
+    }
+
+    #[test]
+    fn header_builds_test() {
+        let dir = std::env::temp_dir().join(format!("xsparseset-capi-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("xsparseset.h"), HEADER).unwrap();
+        std::fs::write(
+            dir.join("main.c"),
+            "#include \"xsparseset.h\"\n\
+             int main(void) {\n\
+                 XSparseSetU64F32 *set = xsparseset_u64_f32_create();\n\
+                 xsparseset_u64_f32_insert(set, 1, 1.5f);\n\
+                 XSparseSetF32Slice data = xsparseset_u64_f32_data(set);\n\
+                 xsparseset_u64_f32_destroy(set);\n\
+                 return (int)data.len;\n\
+             }\n",
+        )
+        .unwrap();
+        let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_string());
+        let status = std::process::Command::new(&cc)
+            .args(["-std=c99", "-Wall", "-Werror", "-fsyntax-only"])
+            .arg(dir.join("main.c"))
+            .status();
+        std::fs::remove_dir_all(&dir).unwrap();
+        match status {
+            Ok(status) => assert!(status.success(), "include/xsparseset.h doesn't build"),
+            // no C compiler to check with
+            Err(error) => eprintln!("skip building the header by {}: {}", cc, error),
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::cmp::Ordering;
+
+use crate::{SparseSet, SparseStorage};
+
+/// Two sparse sets are equal if they have the same ids with the same data,
+/// the order of the dense array is ignored
+impl<E, T, S> PartialEq for SparseSet<E, T, S>
+where
+    E: Copy,
+    T: PartialEq,
+    S: SparseStorage<EntityId = E>,
+{
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().all(|(id, dat)| other.get(id) == Some(dat))
+    }
+}
+
+impl<E, T, S> Eq for SparseSet<E, T, S>
+where
+    E: Copy,
+    T: Eq,
+    S: SparseStorage<EntityId = E>,
+{
+}
+
+/// Compare the `(id, data)` pairs sorted by id lexicographically,
+/// the order of the dense array is ignored
+impl<E, T, S> PartialOrd for SparseSet<E, T, S>
+where
+    E: Copy + Ord,
+    T: PartialOrd,
+    S: SparseStorage<EntityId = E>,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        sorted_pairs(self)
+            .into_iter()
+            .partial_cmp(sorted_pairs(other))
+    }
+}
+
+impl<E, T, S> Ord for SparseSet<E, T, S>
+where
+    E: Copy + Ord,
+    T: Ord,
+    S: SparseStorage<EntityId = E>,
+{
+    fn cmp(&self, other: &Self) -> Ordering {
+        sorted_pairs(self).into_iter().cmp(sorted_pairs(other))
+    }
+}
+
+fn sorted_pairs<E, T, S>(sparse_set: &SparseSet<E, T, S>) -> Vec<(E, &T)>
+where
+    E: Copy + Ord,
+    S: SparseStorage<EntityId = E>,
+{
+    let mut pairs: Vec<_> = sparse_set.iter().collect();
+    pairs.sort_unstable_by_key(|(id, _)| *id);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cmp::Ordering, collections::BTreeSet};
+
+    use crate::SparseSetHashMap;
+
+    #[test]
+    fn ord_test() {
+        let mut a: SparseSetHashMap<u32, char> = SparseSetHashMap::default();
+        let mut b: SparseSetHashMap<u32, char> = SparseSetHashMap::default();
+        for (id, c) in (0..10).zip('a'..='j') {
+            a.insert(id, c);
+        }
+        for (id, c) in a.iter().rev() {
+            b.insert(id, *c);
+        }
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+
+        b.insert(5, 'z');
+        assert_ne!(a, b);
+        assert!(a < b);
+
+        b.insert(5, 'f');
+        b.swap_remove_by_id(9);
+        assert!(a > b);
+
+        let sets: BTreeSet<_> = vec![a.clone(), b.clone(), a.clone()].into_iter().collect();
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets.iter().next(), Some(&b));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::{SparseSet, SparseStorage};
+
+/// A recorded command of `SparseSetCommands`
+enum Command<E, T> {
+    Insert(E, T),
+    Remove(E),
+    Modify(E, Box<dyn FnOnce(&mut T)>),
+}
+
+/// A buffer of structural mutations applied to a sparse set later
+/// # Details
+/// It records commands while the sparse set is borrowed,
+/// like during an iteration, then `SparseSet::apply_commands` runs them in order
+pub struct SparseSetCommands<E, T> {
+    commands: Vec<Command<E, T>>,
+}
+
+impl<E, T> Default for SparseSetCommands<E, T> {
+    fn default() -> Self {
+        SparseSetCommands {
+            commands: Vec::new(),
+        }
+    }
+}
+
+impl<E, T> std::fmt::Debug for SparseSetCommands<E, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SparseSetCommands")
+            .field("len", &self.commands.len())
+            .finish()
+    }
+}
+
+impl<E, T> SparseSetCommands<E, T> {
+    /// Record inserting the `dat` with `id`
+    pub fn insert(&mut self, id: E, dat: T) {
+        self.commands.push(Command::Insert(id, dat));
+    }
+
+    /// Record removing the entity by `id`
+    pub fn remove(&mut self, id: E) {
+        self.commands.push(Command::Remove(id));
+    }
+
+    /// Record calling `f` with the MUTABLE data of `id`
+    pub fn modify<F>(&mut self, id: E, f: F)
+    where
+        F: FnOnce(&mut T) + 'static,
+    {
+        self.commands.push(Command::Modify(id, Box::new(f)));
+    }
+
+    /// Get the count of recorded commands
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Check if no command is recorded
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    /// Drop all recorded commands
+    pub fn clear(&mut self) {
+        self.commands.clear();
+    }
+}
+
+/// The result of `SparseSet::apply_commands`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ApplyReport {
+    /// The count of inserts of new ids
+    pub inserted: usize,
+    /// The count of inserts replacing the data of existing ids
+    pub replaced: usize,
+    /// The count of removes of existing ids
+    pub removed: usize,
+    /// The count of modifies of existing ids
+    pub modified: usize,
+    /// The count of removes and modifies whose id was missing,
+    /// they do nothing
+    pub missing: usize,
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Run the commands in the order they were recorded
+    /// # Details
+    /// Every command sees the effects of the earlier ones,
+    /// so a modify after an insert of the same id modifies the inserted data,
+    /// and a modify after a remove finds the id missing
+    /// # Panics
+    /// Panic if an insert grows past the limits set by `set_limits`,
+    /// the earlier commands are applied and the later ones are dropped
+    pub fn apply_commands(&mut self, commands: SparseSetCommands<E, T>) -> ApplyReport {
+        let mut report = ApplyReport::default();
+        for command in commands.commands {
+            match command {
+                Command::Insert(id, dat) => match self.insert(id, dat) {
+                    Some(_) => report.replaced += 1,
+                    None => report.inserted += 1,
+                },
+                Command::Remove(id) => match self.swap_remove_by_id(id) {
+                    Some(_) => report.removed += 1,
+                    None => report.missing += 1,
+                },
+                Command::Modify(id, f) => match self.get_mut(id) {
+                    Some(dat) => {
+                        f(dat);
+                        report.modified += 1;
+                    }
+                    None => report.missing += 1,
+                },
+            }
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ApplyReport, SparseSet, SparseSetCommands, SparseSetVec};
+
+    #[test]
+    fn apply_commands_test() {
+        let mut sparse_set: SparseSetVec<usize, i32> = SparseSet::default();
+        for id in 0..5 {
+            sparse_set.insert(id, 10);
+        }
+
+        let mut commands = SparseSetCommands::default();
+        for (id, x) in sparse_set.iter() {
+            if id % 2 == 0 {
+                commands.remove(id);
+                commands.insert(id + 100, *x);
+            }
+        }
+        // insert then modify the same id
+        commands.insert(50, 1);
+        commands.modify(50, |x| *x += 1);
+        // modify after remove misses
+        commands.remove(3);
+        commands.modify(3, |x| *x = 0);
+        commands.remove(3);
+        commands.insert(1, 11);
+        commands.modify(1, |x| *x *= 2);
+        assert_eq!(commands.len(), 13);
+
+        let report = sparse_set.apply_commands(commands);
+        assert_eq!(
+            report,
+            ApplyReport {
+                inserted: 4,
+                replaced: 1,
+                removed: 4,
+                modified: 2,
+                missing: 2,
+            }
+        );
+        assert_eq!(sparse_set.get(50), Some(&2));
+        assert_eq!(sparse_set.get(1), Some(&22));
+        assert_eq!(sparse_set.get(104), Some(&10));
+        assert!(!sparse_set.contains(3));
+        assert!(!sparse_set.contains(2));
+        assert_eq!(sparse_set.len(), 5);
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    iter::FusedIterator,
+    num::NonZeroUsize,
+    ops::{Bound, Range, RangeBounds},
+    ptr,
+};
+
+use crate::{SparseSet, SparseStorage};
+
+/// Iterator removing `(id, data)` pairs in a range of dense indices
+/// # Details
+/// Created by `SparseSet::drain_range`.
+/// The entities after the range are moved down when it is dropped,
+/// the pairs not yielded are dropped too
+#[derive(Debug)]
+pub struct DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    set: &'a mut SparseSet<E, T, S>,
+    /// The start of the range
+    start: usize,
+    /// The end of the range
+    end: usize,
+    /// The old length of sparse set
+    len: usize,
+    /// The index of the next pair yielded from the front
+    front: usize,
+    /// The index after the next pair yielded from the back
+    back: usize,
+}
+
+impl<'a, E, T, S> DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Read the pair at `index` out of the dense arrays
+    /// # Safety
+    /// `index` must be in `front..back` and read only once
+    unsafe fn read(&self, index: usize) -> (E, T) {
+        (
+            ptr::read(self.set.dense.as_ptr().add(index)),
+            ptr::read(self.set.data.as_ptr().add(index)),
+        )
+    }
+}
+
+impl<'a, E, T, S> Iterator for DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    type Item = (E, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        // Safety
+        // front is in front..back and moved past
+        let pair = unsafe { self.read(self.front) };
+        self.front += 1;
+        Some(pair)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.back - self.front;
+        (len, Some(len))
+    }
+}
+
+impl<'a, E, T, S> DoubleEndedIterator for DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        // Safety
+        // back is in front..back and moved past
+        Some(unsafe { self.read(self.back) })
+    }
+}
+
+impl<'a, E, T, S> ExactSizeIterator for DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+}
+
+impl<'a, E, T, S> FusedIterator for DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+}
+
+impl<'a, E, T, S> Drop for DrainRange<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    fn drop(&mut self) {
+        let tail_len = self.len - self.end;
+        let set = &mut *self.set;
+        // Safety
+        // The pairs in front..back are not yielded,
+        // the tail is in range of the old length and not moved yet
+        unsafe {
+            let rest = ptr::slice_from_raw_parts_mut(
+                set.data.as_mut_ptr().add(self.front),
+                self.back - self.front,
+            );
+            ptr::drop_in_place(rest);
+
+            let dense = set.dense.as_mut_ptr();
+            ptr::copy(dense.add(self.end), dense.add(self.start), tail_len);
+            let data = set.data.as_mut_ptr();
+            ptr::copy(data.add(self.end), data.add(self.start), tail_len);
+            set.dense.set_len(self.start + tail_len);
+            set.data.set_len(self.start + tail_len);
+        }
+        // Safety
+        // start + 1 is not zero
+        let start_index = unsafe { NonZeroUsize::new_unchecked(self.start + 1) };
+        set.sparse
+            .set_indices(&set.dense[self.start..], start_index);
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Remove the entities in the `range` of dense indices
+    /// # Details
+    /// It yields `(id, data)` pairs in dense order,
+    /// then moves the entities after the range down when the iterator is dropped.
+    /// If the iterator is leaked, the entities after the range are lost too
+    /// # Panics
+    /// Panic if the start of range is greater than the end,
+    /// or the end is greater than `self.len()`
+    pub fn drain_range<R>(&mut self, range: R) -> DrainRange<'_, E, T, S>
+    where
+        R: RangeBounds<usize>,
+    {
+        let len = self.len();
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.checked_add(1).expect("range start overflow"),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => end.checked_add(1).expect("range end overflow"),
+            Bound::Excluded(end) => *end,
+            Bound::Unbounded => len,
+        };
+        if start > end {
+            panic!("range start {} is greater than end {}", start, end);
+        }
+        if end > len {
+            panic!("range end {} is out of range for length {}", end, len);
+        }
+
+        // Unmap the range and the tail first, the tail is mapped again on drop.
+        // So the set is still valid with only the head if the iterator is leaked
+        self.sparse.clear_indices(&self.dense[start..]);
+        // Safety
+        // The ids and data after start are owned by the iterator now
+        unsafe {
+            self.dense.set_len(start);
+            self.data.set_len(start);
+        }
+        DrainRange {
+            set: self,
+            start,
+            end,
+            len,
+            front: start,
+            back: end,
+        }
+    }
+
+    /// Remove the entities in the `range` of dense indices
+    /// # Details
+    /// It is `drain_range(range).collect()`,
+    /// so the entities after the range are moved down and keep their order.  
+    /// `remove_range` removes by a range of ids instead
+    /// # Returns
+    /// Return the removed `(id, data)` pairs in dense order
+    /// # Panics
+    /// Panic if the start of range is greater than the end,
+    /// or the end is greater than `self.len()`
+    pub fn remove_index_range(&mut self, range: Range<usize>) -> Vec<(E, T)> {
+        self.drain_range(range).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{num::NonZeroUsize, rc::Rc};
+
+    use crate::SparseSetVec;
+
+    type EntityId = NonZeroUsize;
+
+    fn sparse_set_of(len: usize) -> SparseSetVec<EntityId, Rc<usize>> {
+        let mut sparse_set = SparseSetVec::default();
+        for x in 1..=len {
+            sparse_set.insert(EntityId::new(x).unwrap(), Rc::new(x));
+        }
+        sparse_set
+    }
+
+    fn ids(sparse_set: &SparseSetVec<EntityId, Rc<usize>>) -> Vec<usize> {
+        sparse_set.ids().iter().map(|id| id.get()).collect()
+    }
+
+    #[test]
+    fn drain_middle_test() {
+        let mut sparse_set = sparse_set_of(10);
+        let drained: Vec<_> = sparse_set
+            .drain_range(3..6)
+            .map(|(id, x)| (id.get(), *x))
+            .collect();
+        assert_eq!(drained, vec![(4, 4), (5, 5), (6, 6)]);
+        assert_eq!(ids(&sparse_set), vec![1, 2, 3, 7, 8, 9, 10]);
+        assert_eq!(sparse_set.get_index(EntityId::new(8).unwrap()), Some(4));
+        assert!(!sparse_set.contains(EntityId::new(5).unwrap()));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // from both ends
+        let mut drain = sparse_set.drain_range(1..=4);
+        assert_eq!(drain.len(), 4);
+        assert_eq!(*drain.next_back().unwrap().1, 8);
+        assert_eq!(*drain.next().unwrap().1, 2);
+        drop(drain);
+        assert_eq!(ids(&sparse_set), vec![1, 9, 10]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn drain_full_test() {
+        let mut sparse_set = sparse_set_of(10);
+        assert_eq!(sparse_set.drain_range(..).count(), 10);
+        assert!(sparse_set.is_empty());
+        assert!(!sparse_set.contains(EntityId::new(1).unwrap()));
+        assert_eq!(sparse_set.drain_range(0..0).count(), 0);
+    }
+
+    #[test]
+    fn drain_drop_halfway_test() {
+        let mut sparse_set = sparse_set_of(10);
+        let values: Vec<_> = sparse_set.data().to_vec();
+
+        let mut drain = sparse_set.drain_range(2..8);
+        assert_eq!(*drain.next().unwrap().1, 3);
+        assert_eq!(*drain.next().unwrap().1, 4);
+        drop(drain);
+        assert_eq!(ids(&sparse_set), vec![1, 2, 9, 10]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        // the pairs not yielded are dropped
+        for (x, value) in values.iter().enumerate() {
+            let expected = if (2..8).contains(&x) { 1 } else { 2 };
+            assert_eq!(Rc::strong_count(value), expected);
+        }
+
+        // a leaked iterator loses the tail but keeps the set valid
+        std::mem::forget(sparse_set.drain_range(1..2));
+        assert_eq!(ids(&sparse_set), vec![1]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn remove_index_range_test() {
+        let mut sparse_set = sparse_set_of(8);
+        let removed: Vec<_> = sparse_set
+            .remove_index_range(2..5)
+            .into_iter()
+            .map(|(id, x)| (id.get(), *x))
+            .collect();
+        assert_eq!(removed, vec![(3, 3), (4, 4), (5, 5)]);
+        assert_eq!(ids(&sparse_set), vec![1, 2, 6, 7, 8]);
+        for x in 1..=8 {
+            let id = EntityId::new(x).unwrap();
+            assert_eq!(
+                sparse_set.get(id).map(|x| **x),
+                Some(x).filter(|x| !(3..=5).contains(x))
+            );
+        }
+        assert_eq!(sparse_set.get_index(EntityId::new(6).unwrap()), Some(2));
+        assert!(sparse_set.remove_index_range(1..1).is_empty());
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn drain_out_of_range_test() {
+        let mut sparse_set = sparse_set_of(3);
+        sparse_set.drain_range(1..4);
+    }
+}
@@ -0,0 +1,264 @@
+use crate::{SparseSet, SparseStorage};
+
+/// A view into an id of sparse set, which is either occupied or vacant
+/// # Details
+/// Created by `SparseSet::entry`, which looks up the id once.
+/// Inserting or removing through the entry doesn't look it up again
+#[derive(Debug)]
+pub enum Entry<'a, E, T, S> {
+    /// The sparse set contains the id
+    Occupied(OccupiedEntry<'a, E, T, S>),
+    /// The sparse set doesn't contain the id
+    Vacant(VacantEntry<'a, E, T, S>),
+}
+
+/// An id in sparse set with its dense index
+#[derive(Debug)]
+pub struct OccupiedEntry<'a, E, T, S> {
+    set: &'a mut SparseSet<E, T, S>,
+    id: E,
+    index: usize,
+}
+
+/// An id not in sparse set
+#[derive(Debug)]
+pub struct VacantEntry<'a, E, T, S> {
+    set: &'a mut SparseSet<E, T, S>,
+    id: E,
+}
+
+impl<'a, E, T, S> Entry<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the id of the entry
+    pub fn id(&self) -> E {
+        match self {
+            Entry::Occupied(entry) => entry.id(),
+            Entry::Vacant(entry) => entry.id(),
+        }
+    }
+
+    /// Get the MUTABLE reference of data, inserting `dat` if the entry is vacant
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn or_insert(self, dat: T) -> &'a mut T {
+        self.or_insert_with(|| dat)
+    }
+
+    /// Get the MUTABLE reference of data, inserting the data made by `f` if the entry is vacant
+    /// # Details
+    /// `f` is only called if the entry is vacant
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn or_insert_with<F>(self, f: F) -> &'a mut T
+    where
+        F: FnOnce() -> T,
+    {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(f()),
+        }
+    }
+
+    /// Call `f` with the data if the entry is occupied
+    pub fn and_modify<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut T),
+    {
+        if let Entry::Occupied(entry) = &mut self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, E, T, S> Entry<'a, E, T, S>
+where
+    E: Copy,
+    T: Default,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the MUTABLE reference of data, inserting `T::default()` if the entry is vacant
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn or_default(self) -> &'a mut T {
+        self.or_insert_with(T::default)
+    }
+}
+
+impl<'a, E, T, S> OccupiedEntry<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the id of the entry
+    pub fn id(&self) -> E {
+        self.id
+    }
+
+    /// Get the dense index of the entry
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the reference of data
+    pub fn get(&self) -> &T {
+        // Safety
+        // The index is looked up by `entry` and the set is borrowed since then
+        unsafe { self.set.data.get_unchecked(self.index) }
+    }
+
+    /// Get the MUTABLE reference of data
+    pub fn get_mut(&mut self) -> &mut T {
+        // Safety
+        // The index is looked up by `entry` and the set is borrowed since then
+        unsafe { self.set.data.get_unchecked_mut(self.index) }
+    }
+
+    /// Convert into the MUTABLE reference of data living as long as the sparse set borrow
+    pub fn into_mut(self) -> &'a mut T {
+        // Safety
+        // The index is looked up by `entry` and the set is borrowed since then
+        unsafe { self.set.data.get_unchecked_mut(self.index) }
+    }
+
+    /// Replace the data
+    /// # Returns
+    /// Return the old data
+    pub fn insert(&mut self, dat: T) -> T {
+        std::mem::replace(self.get_mut(), dat)
+    }
+
+    /// Remove the entity like `swap_remove_by_index`
+    /// # Returns
+    /// Return the removed data
+    pub fn remove(self) -> T {
+        self.set
+            .swap_remove_by_index(self.index)
+            .expect("the index of an occupied entry is in range")
+    }
+}
+
+impl<'a, E, T, S> VacantEntry<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the id of the entry
+    pub fn id(&self) -> E {
+        self.id
+    }
+
+    /// Insert `dat` with the id at the end of the dense array
+    /// # Returns
+    /// Return the MUTABLE reference of the stored data
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn insert(self, dat: T) -> &'a mut T {
+        if let Err(error) = self.set.check_limits(1, &[self.id]) {
+            panic!("{}{}", self.set.name_prefix(), error);
+        }
+        self.set.push_unchecked(self.id, dat);
+        self.set
+            .data
+            .last_mut()
+            .expect("the data was pushed just now")
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the entry of `id` for in-place insertion or update
+    /// # Details
+    /// `id` is looked up in sparse storage only once
+    pub fn entry(&mut self, id: E) -> Entry<'_, E, T, S> {
+        match self.sparse.get_index(id) {
+            Some(index) => Entry::Occupied(OccupiedEntry {
+                set: self,
+                id,
+                index: index.get() - 1,
+            }),
+            None => Entry::Vacant(VacantEntry { set: self, id }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, num::NonZeroUsize};
+
+    use crate::{Entry, SparseSet, SparseStorage, VecStorage};
+
+    /// Count the lookups of the inner storage
+    #[derive(Default)]
+    struct CountingStorage {
+        inner: VecStorage<usize>,
+        lookups: Cell<usize>,
+    }
+
+    impl SparseStorage for CountingStorage {
+        type EntityId = usize;
+
+        fn get_index(&self, entity_id: usize) -> Option<NonZeroUsize> {
+            self.lookups.set(self.lookups.get() + 1);
+            self.inner.get_index(entity_id)
+        }
+
+        fn set_index(&mut self, entity_id: usize, index: Option<NonZeroUsize>) {
+            self.inner.set_index(entity_id, index)
+        }
+
+        fn clear(&mut self) {
+            self.inner.clear()
+        }
+    }
+
+    #[test]
+    fn entry_test() {
+        let mut counts: SparseSet<usize, u32, CountingStorage> = SparseSet::default();
+        for word in [3, 1, 3, 3, 2, 1] {
+            *counts.entry(word).or_insert(0) += 1;
+        }
+        assert_eq!(counts.get(3), Some(&3));
+        assert_eq!(counts.get(1), Some(&2));
+        assert_eq!(counts.get(2), Some(&1));
+        assert_eq!(counts.ids(), &[3, 1, 2]);
+
+        // one lookup for each entry, none on insertion
+        let lookups = counts.sparse.lookups.get();
+        counts.entry(7).or_insert_with(|| 70);
+        counts.entry(7).or_insert_with(|| unreachable!());
+        assert_eq!(counts.sparse.lookups.get(), lookups + 2);
+
+        assert_eq!(*counts.entry(8).or_default(), 0);
+        counts.entry(8).and_modify(|x| *x += 5).or_insert(100);
+        assert_eq!(counts.get(8), Some(&5));
+
+        match counts.entry(1) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.id(), 1);
+                assert_eq!(entry.index(), 1);
+                assert_eq!(*entry.get(), 2);
+                *entry.get_mut() += 10;
+                assert_eq!(entry.insert(20), 12);
+                assert_eq!(entry.remove(), 20);
+            }
+            Entry::Vacant(_) => unreachable!(),
+        }
+        assert!(!counts.contains(1));
+        match counts.entry(1) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.id(), 1);
+                *entry.insert(40) += 2;
+            }
+            Entry::Occupied(_) => unreachable!(),
+        }
+        assert_eq!(counts.get(1), Some(&42));
+        assert_eq!(counts.check_invariants(), Ok(()));
+    }
+}
@@ -0,0 +1,116 @@
+use std::fmt::Display;
+
+/// The dense index is not less than the count of entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexOutOfRange {
+    /// The index given
+    pub index: usize,
+    /// The count of entities
+    pub len: usize,
+}
+
+impl Display for IndexOutOfRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "index={} is out of range, len={}", self.index, self.len)
+    }
+}
+
+impl std::error::Error for IndexOutOfRange {}
+
+/// Growing sparse set exceeds its `SparseSetLimits`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LimitExceeded {
+    /// The count of entities would exceed `max_len`
+    MaxLen(usize),
+    /// The count of slots in the sparse storage would exceed `max_sparse_slots`
+    MaxSparseSlots(usize),
+    /// The id can't be mapped by the sparse storage,
+    /// like a 64-bit id in `U64VecStorage` on a 32-bit target,
+    /// or a key whose slot in `KeyedStorage` holds another version
+    AddressSpace,
+}
+
+impl Display for LimitExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LimitExceeded::MaxLen(max) => write!(f, "the count of entities exceeds {}", max),
+            LimitExceeded::MaxSparseSlots(max) => {
+                write!(f, "the count of sparse slots exceeds {}", max)
+            }
+            LimitExceeded::AddressSpace => {
+                write!(f, "the id can't be mapped by the sparse storage")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LimitExceeded {}
+
+/// The id is too large to be converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IdOverflow(pub usize);
+
+impl Display for IdOverflow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "id={} is too large", self.0)
+    }
+}
+
+impl std::error::Error for IdOverflow {}
+
+/// `SparseSet::id_histogram` can't count the ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HistogramError {
+    /// An id can't be converted into `usize`
+    IdOverflow,
+    /// The histogram would need more buckets than the count given
+    TooManyBuckets(usize),
+}
+
+impl Display for HistogramError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistogramError::IdOverflow => write!(f, "an id can't be converted into usize"),
+            HistogramError::TooManyBuckets(max) => {
+                write!(f, "the histogram needs more than {} buckets", max)
+            }
+        }
+    }
+}
+
+impl std::error::Error for HistogramError {}
+
+/// The order given to `SparseSet::sort_by_id_order` is invalid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OrderError<E> {
+    /// The id appears more than once
+    DuplicateId(E),
+}
+
+impl<E> Display for OrderError<E>
+where
+    E: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OrderError::DuplicateId(id) => write!(f, "id={:?} appears more than once", id),
+        }
+    }
+}
+
+impl<E> std::error::Error for OrderError<E> where E: std::fmt::Debug {}
+
+/// The id is not in sparse set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MissingId<E>(pub E);
+
+impl<E> Display for MissingId<E>
+where
+    E: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "id={:?} is not in sparse set", self.0)
+    }
+}
+
+impl<E> std::error::Error for MissingId<E> where E: std::fmt::Debug {}
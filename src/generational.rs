@@ -0,0 +1,194 @@
+use crate::{Iter, SparseSetVec};
+
+/// An entity id with a generation
+/// # Details
+/// The index is recycled after despawning, the generation tells apart
+/// the entities living in the same index at different times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GenerationalId {
+    index: usize,
+    generation: u32,
+}
+
+impl GenerationalId {
+    /// Get the index part
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Get the generation part
+    pub fn generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// A sparse set allocating its own generational ids
+/// # Details
+/// Despawned indices are kept in a free list with their next generation,
+/// spawning pops it first, so indices are recycled in O(1)
+/// and stale ids never see the new entity
+#[derive(Debug, Clone)]
+pub struct GenerationalSparseSet<T> {
+    set: SparseSetVec<usize, T>,
+    /// The generation of the entity living in each index
+    generations: Vec<u32>,
+    /// The retired `(index, next_generation)`
+    free_list: Vec<(usize, u32)>,
+    recycled: usize,
+}
+
+impl<T> Default for GenerationalSparseSet<T> {
+    fn default() -> Self {
+        GenerationalSparseSet {
+            set: SparseSetVec::default(),
+            generations: Vec::new(),
+            free_list: Vec::new(),
+            recycled: 0,
+        }
+    }
+}
+
+impl<T> GenerationalSparseSet<T> {
+    /// Spawn a new entity with `dat`
+    /// # Details
+    /// It reuses the most recently despawned index if there is any
+    pub fn spawn(&mut self, dat: T) -> GenerationalId {
+        let id = if let Some((index, generation)) = self.free_list.pop() {
+            self.recycled += 1;
+            self.generations[index] = generation;
+            GenerationalId { index, generation }
+        } else {
+            self.generations.push(0);
+            GenerationalId {
+                index: self.generations.len() - 1,
+                generation: 0,
+            }
+        };
+        self.set.insert(id.index, dat);
+        id
+    }
+
+    /// Despawn the entity
+    /// # Returns
+    /// Return None if `id` is not alive
+    pub fn despawn(&mut self, id: GenerationalId) -> Option<T> {
+        if !self.contains(id) {
+            return None;
+        }
+        self.free_list
+            .push((id.index, id.generation.wrapping_add(1)));
+        self.set.swap_remove_by_id(id.index)
+    }
+
+    /// Check if the entity is alive
+    pub fn contains(&self, id: GenerationalId) -> bool {
+        self.generations.get(id.index) == Some(&id.generation) && self.set.contains(id.index)
+    }
+
+    /// Get the reference of data by given `id`
+    /// # Returns
+    /// Return None if `id` is not alive
+    pub fn get(&self, id: GenerationalId) -> Option<&T> {
+        if self.generations.get(id.index) != Some(&id.generation) {
+            return None;
+        }
+        self.set.get(id.index)
+    }
+
+    /// Get the MUTABLE reference by data by given `id`
+    /// # Returns
+    /// Return None if `id` is not alive
+    pub fn get_mut(&mut self, id: GenerationalId) -> Option<&mut T> {
+        if self.generations.get(id.index) != Some(&id.generation) {
+            return None;
+        }
+        self.set.get_mut(id.index)
+    }
+
+    /// Get the count of alive entities
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Check if no entity is alive
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Get the count of spawns that reused a despawned index
+    pub fn recycled_count(&self) -> usize {
+        self.recycled
+    }
+
+    /// Get the count of despawned indices waiting for reuse
+    pub fn free_count(&self) -> usize {
+        self.free_list.len()
+    }
+
+    /// Shrink the free list to fit its length
+    pub fn shrink_free_list(&mut self) {
+        self.free_list.shrink_to_fit();
+    }
+
+    /// Get the slice of data
+    pub fn data(&self) -> &[T] {
+        self.set.data()
+    }
+
+    /// Iterate `(index, &data)` pairs of alive entities in dense order
+    pub fn iter(&self) -> Iter<'_, usize, T> {
+        self.set.iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::GenerationalSparseSet;
+
+    #[test]
+    fn recycle_test() {
+        let mut rng = thread_rng();
+        let mut set = GenerationalSparseSet::default();
+
+        let ids: Vec<_> = (0..1000).map(|x| set.spawn(x)).collect();
+        assert_eq!(set.recycled_count(), 0);
+
+        let mut despawned = Vec::new();
+        for id in &ids {
+            if rng.gen_bool(0.5) {
+                assert_eq!(set.despawn(*id), Some(id.index()));
+                assert_eq!(set.despawn(*id), None);
+                despawned.push(*id);
+            }
+        }
+        assert_eq!(set.free_count(), despawned.len());
+
+        let respawned: Vec<_> = (0..despawned.len()).map(|x| set.spawn(x + 1000)).collect();
+        assert_eq!(set.recycled_count(), despawned.len());
+        assert_eq!(set.free_count(), 0);
+        assert_eq!(set.len(), 1000);
+        set.shrink_free_list();
+
+        // every index is reused with the next generation
+        let old: HashSet<_> = despawned.iter().map(|id| id.index()).collect();
+        for id in &respawned {
+            assert!(old.contains(&id.index()));
+            assert_eq!(id.generation(), 1);
+            assert!(set.contains(*id));
+        }
+        // stale ids miss
+        for id in &despawned {
+            assert!(!set.contains(*id));
+            assert_eq!(set.get(*id), None);
+            assert_eq!(set.get_mut(*id), None);
+        }
+
+        let next = set.spawn(0);
+        assert_eq!(next.index(), 1000);
+        assert_eq!(next.generation(), 0);
+    }
+}
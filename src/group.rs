@@ -0,0 +1,360 @@
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// 2 sparse sets that keep their shared ids packed at the front
+/// # Details
+/// The first `len()` entities of both sets are the ids in both sets, in the same order,
+/// so `iter` walks both data arrays in lockstep without lookups.  
+/// It owns both sets, so every insertion and removal goes through it and keeps the packing:
+/// an id joining both sets is swapped to the end of the packed part,
+/// an id leaving one of them is swapped out of it first
+#[derive(Debug, Clone)]
+pub struct OwningGroup<E, A, B, SA = VecStorage<E>, SB = VecStorage<E>> {
+    a: SparseSet<E, A, SA>,
+    b: SparseSet<E, B, SB>,
+    len: usize,
+}
+
+impl<E, A, B, SA, SB> Default for OwningGroup<E, A, B, SA, SB>
+where
+    E: Copy,
+    SA: SparseStorage<EntityId = E> + Default,
+    SB: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        OwningGroup {
+            a: SparseSet::default(),
+            b: SparseSet::default(),
+            len: 0,
+        }
+    }
+}
+
+impl<E, A, B, SA, SB> OwningGroup<E, A, B, SA, SB>
+where
+    E: Copy,
+    SA: SparseStorage<EntityId = E>,
+    SB: SparseStorage<EntityId = E>,
+{
+    /// Own 2 sparse sets, packing their shared ids by `group_by_shared`
+    pub fn new(mut a: SparseSet<E, A, SA>, mut b: SparseSet<E, B, SB>) -> Self {
+        let len = a.group_by_shared(&mut b);
+        OwningGroup { a, b, len }
+    }
+
+    /// Give the sparse sets back, the shared ids are still at the front
+    pub fn into_inner(self) -> (SparseSet<E, A, SA>, SparseSet<E, B, SB>) {
+        (self.a, self.b)
+    }
+
+    /// Get the count of ids in both sets
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Check if no id is in both sets
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the first sparse set
+    pub fn a(&self) -> &SparseSet<E, A, SA> {
+        &self.a
+    }
+
+    /// Get the second sparse set
+    pub fn b(&self) -> &SparseSet<E, B, SB> {
+        &self.b
+    }
+
+    /// Get the MUTABLE reference of data in the first set by given `id`
+    pub fn get_mut_a(&mut self, id: E) -> Option<&mut A> {
+        self.a.get_mut(id)
+    }
+
+    /// Get the MUTABLE reference of data in the second set by given `id`
+    pub fn get_mut_b(&mut self, id: E) -> Option<&mut B> {
+        self.b.get_mut(id)
+    }
+
+    /// Insert the `dat` with `id` into the first set
+    /// # Returns
+    /// Return the old data if the first set has this id
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn insert_a(&mut self, id: E, dat: A) -> Option<A> {
+        let old = self.a.insert(id, dat);
+        if old.is_none() {
+            let index = self.a.len() - 1;
+            if let Some(other_index) = self.b.get_index(id) {
+                self.pack(index, other_index);
+            }
+        }
+        old
+    }
+
+    /// Insert the `dat` with `id` into the second set
+    /// # Returns
+    /// Return the old data if the second set has this id
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn insert_b(&mut self, id: E, dat: B) -> Option<B> {
+        let old = self.b.insert(id, dat);
+        if old.is_none() {
+            let other_index = self.b.len() - 1;
+            if let Some(index) = self.a.get_index(id) {
+                self.pack(index, other_index);
+            }
+        }
+        old
+    }
+
+    /// Remove the entity by `id` from the first set
+    /// # Returns
+    /// Return None if the first set doesn't contain `id`
+    pub fn remove_a(&mut self, id: E) -> Option<A> {
+        let index = self.a.get_index(id)?;
+        let index = self.unpack(index);
+        self.a.swap_remove_by_index(index)
+    }
+
+    /// Remove the entity by `id` from the second set
+    /// # Returns
+    /// Return None if the second set doesn't contain `id`
+    pub fn remove_b(&mut self, id: E) -> Option<B> {
+        let index = self.b.get_index(id)?;
+        let index = self.unpack(index);
+        self.b.swap_remove_by_index(index)
+    }
+
+    /// Iterate `(id, &a, &b)` for the ids in both sets
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = (E, &A, &B)> + DoubleEndedIterator {
+        self.a.dense[..self.len]
+            .iter()
+            .zip(&self.a.data[..self.len])
+            .zip(&self.b.data[..self.len])
+            .map(|((id, a), b)| (*id, a, b))
+    }
+
+    /// Iterate `(id, &mut a, &mut b)` for the ids in both sets
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl ExactSizeIterator<Item = (E, &mut A, &mut B)> + DoubleEndedIterator {
+        self.a.dense[..self.len]
+            .iter()
+            .zip(&mut self.a.data[..self.len])
+            .zip(&mut self.b.data[..self.len])
+            .map(|((id, a), b)| (*id, a, b))
+    }
+
+    /// Move the id just shared at `index` of a and `other_index` of b to the end of the packed part
+    fn pack(&mut self, index: usize, other_index: usize) {
+        // Safety
+        // The id is outside the packed part, so both indices are not less than len
+        unsafe {
+            self.a.swap_by_index_unchecked(index, self.len);
+            self.b.swap_by_index_unchecked(other_index, self.len);
+        }
+        self.len += 1;
+    }
+
+    /// Move the id at dense `index` out of the packed part in both sets
+    /// # Returns
+    /// Return the new dense index of the id, which is the same in both sets if it was packed
+    fn unpack(&mut self, index: usize) -> usize {
+        if index >= self.len {
+            return index;
+        }
+        self.len -= 1;
+        // Safety
+        // index < old len, and the packed part is aligned in both sets
+        unsafe {
+            self.a.swap_by_index_unchecked(index, self.len);
+            self.b.swap_by_index_unchecked(index, self.len);
+        }
+        self.len
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Move the ids shared with `other` to the front of both sparse sets in the same order
+    /// # Details
+    /// After grouping, the first n entities of both sets have the same ids,
+    /// use `shared_prefix_mut` to access them as aligned slices
+    /// # Returns
+    /// Return n, the count of shared ids
+    pub fn group_by_shared<T2, S2>(&mut self, other: &mut SparseSet<E, T2, S2>) -> usize
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        let mut count = 0;
+        for index in 0..self.len() {
+            // Safety
+            // index is less than self.len()
+            let id = unsafe { *self.dense.get_unchecked(index) };
+            if let Some(other_index) = other.get_index(id) {
+                // Safety
+                // count <= index < self.len(),
+                // count <= other_index because the ids before count are grouped already
+                unsafe {
+                    self.swap_by_index_unchecked(index, count);
+                    other.swap_by_index_unchecked(other_index, count);
+                }
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Get the first `n` data of both sparse sets as mutable slices
+    /// # Details
+    /// The slices are aligned by id if both sets were grouped by `group_by_shared`
+    /// and `n` is not greater than the count it returned
+    /// # Panics
+    /// * `n` is greater than the length of any set
+    /// * In debug build, the first `n` ids of both sets are not the same
+    pub fn shared_prefix_mut<'a, T2, S2>(
+        &'a mut self,
+        other: &'a mut SparseSet<E, T2, S2>,
+        n: usize,
+    ) -> (&'a mut [T], &'a mut [T2])
+    where
+        E: PartialEq,
+        S2: SparseStorage<EntityId = E>,
+    {
+        debug_assert!(
+            self.dense[..n] == other.dense[..n],
+            "the first {} ids are not aligned",
+            n
+        );
+        (&mut self.data[..n], &mut other.data[..n])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{OwningGroup, SparseSetHashMap, SparseSetVec, SparseStorage, VecStorage};
+
+    #[test]
+    fn shared_prefix_mut_test() {
+        let mut positions: SparseSetVec<usize, usize> = SparseSetVec::default();
+        let mut velocities: SparseSetHashMap<usize, usize> = SparseSetHashMap::default();
+        for x in 0..100 {
+            positions.insert(x, x);
+        }
+        for x in (0..200).rev().step_by(3) {
+            velocities.insert(x, 1000 + x);
+        }
+
+        let n = positions.group_by_shared(&mut velocities);
+        assert_eq!(n, (0..100).filter(|x| (199 - x) % 3 == 0).count());
+        assert_eq!(positions.ids()[..n], velocities.ids()[..n]);
+
+        let (pos, vel) = positions.shared_prefix_mut(&mut velocities, n);
+        for (p, v) in pos.iter_mut().zip(vel.iter_mut()) {
+            *p += *v;
+            *v = 0;
+        }
+
+        for x in 0..100 {
+            if let Some(v) = velocities.get(x) {
+                assert_eq!(*v, 0);
+                assert_eq!(positions.get(x), Some(&(2 * x + 1000)));
+            } else {
+                assert_eq!(positions.get(x), Some(&x));
+            }
+        }
+        assert_eq!(positions.check_invariants(), Ok(()));
+        assert_eq!(velocities.check_invariants(), Ok(()));
+    }
+
+    fn check_packed<SB>(group: &OwningGroup<usize, u32, u64, VecStorage<usize>, SB>)
+    where
+        SB: SparseStorage<EntityId = usize>,
+    {
+        let (a, b) = (group.a(), group.b());
+        let len = group.len();
+        assert_eq!(a.ids()[..len], b.ids()[..len]);
+        assert!(a.ids()[len..].iter().all(|id| !b.contains(*id)));
+        assert!(b.ids()[len..].iter().all(|id| !a.contains(*id)));
+        assert_eq!(a.check_invariants(), Ok(()));
+        assert_eq!(b.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn owning_group_test() {
+        let mut rng = thread_rng();
+        let mut group: OwningGroup<usize, u32, u64, _, HashMap<_, _>> = OwningGroup::default();
+        let mut naive_a = HashMap::new();
+        let mut naive_b = HashMap::new();
+        for step in 0..5000 {
+            let id = rng.gen_range(0..64);
+            match rng.gen_range(0..4) {
+                0 => {
+                    let x = rng.gen();
+                    assert_eq!(group.insert_a(id, x), naive_a.insert(id, x));
+                }
+                1 => {
+                    let x = rng.gen();
+                    assert_eq!(group.insert_b(id, x), naive_b.insert(id, x));
+                }
+                2 => assert_eq!(group.remove_a(id), naive_a.remove(&id)),
+                _ => assert_eq!(group.remove_b(id), naive_b.remove(&id)),
+            }
+            check_packed(&group);
+
+            let mut joined: Vec<_> = naive_a
+                .iter()
+                .filter_map(|(id, a)| Some((*id, *a, *naive_b.get(id)?)))
+                .collect();
+            joined.sort_unstable();
+            let mut iterated: Vec<_> = group.iter().map(|(id, a, b)| (id, *a, *b)).collect();
+            iterated.sort_unstable();
+            assert_eq!(iterated, joined);
+            assert_eq!(group.len(), joined.len());
+
+            if step % 100 == 0 {
+                for (_, a, b) in group.iter_mut() {
+                    *a = a.wrapping_add(1);
+                    *b = b.wrapping_add(1);
+                }
+                for (id, a, b) in group.iter() {
+                    naive_a.insert(id, *a);
+                    naive_b.insert(id, *b);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn owning_group_new_test() {
+        let mut a: SparseSetVec<usize, u32> = SparseSetVec::default();
+        let mut b: SparseSetVec<usize, u64> = SparseSetVec::default();
+        for x in 0..20 {
+            a.insert(x, x as u32);
+        }
+        for x in (10..30).rev() {
+            b.insert(x, x as u64);
+        }
+        let mut group = OwningGroup::new(a, b);
+        assert_eq!(group.len(), 10);
+        check_packed(&group);
+        assert_eq!(group.remove_b(15), Some(15));
+        assert_eq!(group.len(), 9);
+        *group.get_mut_a(12).unwrap() = 120;
+        assert!(group
+            .iter()
+            .any(|(id, a, b)| id == 12 && *a == 120 && *b == 12));
+        check_packed(&group);
+        let (a, b) = group.into_inner();
+        assert_eq!(a.len(), 20);
+        assert_eq!(b.len(), 19);
+    }
+}
@@ -0,0 +1,225 @@
+use std::convert::TryFrom;
+
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A stable handle to an entity of `HandleSparseSet`
+/// # Details
+/// It resolves in O(1) without probing the sparse storage,
+/// and never resolves to another entity after the entity is removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Handle {
+    slot: u32,
+    generation: u32,
+}
+
+/// A slot of the handle arena
+#[derive(Debug, Clone)]
+struct HandleSlot {
+    /// The dense index of the entity, None if the slot is free
+    index: Option<usize>,
+    generation: u32,
+}
+
+/// A sparse set handing out stable handles
+/// # Details
+/// The handle arena is updated whenever an entity is moved or removed,
+/// so handles stay valid across swap-removals.
+/// Entities without a handle only pay an `Option` in a Vec parallel to dense
+#[derive(Debug, Clone)]
+pub struct HandleSparseSet<E, T, S = VecStorage<E>> {
+    set: SparseSet<E, T, S>,
+    /// The handle slot of each entity, parallel to dense
+    handles: Vec<Option<u32>>,
+    slots: Vec<HandleSlot>,
+    free_slots: Vec<u32>,
+}
+
+impl<E, T, S> Default for HandleSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        HandleSparseSet {
+            set: SparseSet::default(),
+            handles: Vec::new(),
+            slots: Vec::new(),
+            free_slots: Vec::new(),
+        }
+    }
+}
+
+impl<E, T, S> HandleSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Insert the `dat` with `id`
+    /// # Returns
+    /// Return the old data if the set has this id, the handle is kept
+    pub fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        let old = self.set.insert(id, dat);
+        if old.is_none() {
+            self.handles.push(None);
+        }
+        old
+    }
+
+    /// Remove the entity by `id` and invalidate its handle
+    /// # Returns
+    /// Return None if the set doesn't contain `id`
+    pub fn swap_remove_by_id(&mut self, id: E) -> Option<T> {
+        let index = self.set.get_index(id)?;
+        if let Some(slot) = self.handles.swap_remove(index) {
+            let handle_slot = &mut self.slots[slot as usize];
+            handle_slot.index = None;
+            handle_slot.generation = handle_slot.generation.wrapping_add(1);
+            self.free_slots.push(slot);
+        }
+        // the last entity is moved to index
+        if let Some(Some(slot)) = self.handles.get(index) {
+            self.slots[*slot as usize].index = Some(index);
+        }
+        self.set.swap_remove_by_id(id)
+    }
+
+    /// Get the handle of the entity
+    /// # Details
+    /// The same handle is returned until the entity is removed
+    /// # Returns
+    /// Return None if the set doesn't contain `id`
+    /// # Panics
+    /// Panic if more than `u32::MAX` handles are alive
+    pub fn handle(&mut self, id: E) -> Option<Handle> {
+        let index = self.set.get_index(id)?;
+        let slot = match self.handles[index] {
+            Some(slot) => slot,
+            None => {
+                let slot = self.free_slots.pop().unwrap_or_else(|| {
+                    let slot = u32::try_from(self.slots.len())
+                        .expect("the count of handle slots exceeds u32::MAX");
+                    self.slots.push(HandleSlot {
+                        index: None,
+                        generation: 0,
+                    });
+                    slot
+                });
+                self.slots[slot as usize].index = Some(index);
+                self.handles[index] = Some(slot);
+                slot
+            }
+        };
+        Some(Handle {
+            slot,
+            generation: self.slots[slot as usize].generation,
+        })
+    }
+
+    fn resolve_index(&self, handle: Handle) -> Option<usize> {
+        let handle_slot = self.slots.get(handle.slot as usize)?;
+        if handle_slot.generation != handle.generation {
+            return None;
+        }
+        handle_slot.index
+    }
+
+    /// Get `(id, &data)` of the entity by `handle`
+    /// # Returns
+    /// Return None if the entity is removed
+    pub fn resolve(&self, handle: Handle) -> Option<(E, &T)> {
+        let index = self.resolve_index(handle)?;
+        Some((self.set.ids()[index], &self.set.data()[index]))
+    }
+
+    /// Get `(id, &mut data)` of the entity by `handle`
+    /// # Returns
+    /// Return None if the entity is removed
+    pub fn resolve_mut(&mut self, handle: Handle) -> Option<(E, &mut T)> {
+        let index = self.resolve_index(handle)?;
+        let id = self.set.ids()[index];
+        Some((id, &mut self.set.data_mut()[index]))
+    }
+
+    /// Check if the set has id
+    pub fn contains(&self, id: E) -> bool {
+        self.set.contains(id)
+    }
+
+    /// Get the reference of data by given `id`
+    pub fn get(&self, id: E) -> Option<&T> {
+        self.set.get(id)
+    }
+
+    /// Get the MUTABLE reference of data by given `id`
+    pub fn get_mut(&mut self, id: E) -> Option<&mut T> {
+        self.set.get_mut(id)
+    }
+
+    /// Get the count of entities
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Get the inner sparse set
+    pub fn as_sparse_set(&self) -> &SparseSet<E, T, S> {
+        &self.set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::HandleSparseSet;
+
+    #[test]
+    fn churn_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: HandleSparseSet<usize, usize> = HandleSparseSet::default();
+        for id in 0..1000 {
+            sparse_set.insert(id, id * 10);
+        }
+        let mut handles: HashMap<usize, _> = (0..1000)
+            .step_by(7)
+            .map(|id| (id, sparse_set.handle(id).unwrap()))
+            .collect();
+        assert_eq!(sparse_set.handle(7), Some(handles[&7]));
+        assert_eq!(sparse_set.handle(5000), None);
+
+        let mut removed = Vec::new();
+        for round in 0..20_000 {
+            let id = rng.gen_range(0..2000);
+            if rng.gen_bool(0.5) {
+                if sparse_set.swap_remove_by_id(id).is_some() {
+                    removed.extend(handles.remove(&id));
+                }
+            } else {
+                sparse_set.insert(id, id * 10);
+                if id % 7 == 0 && !handles.contains_key(&id) {
+                    handles.insert(id, sparse_set.handle(id).unwrap());
+                }
+            }
+
+            if round % 100 == 0 {
+                for (id, handle) in &handles {
+                    assert_eq!(sparse_set.resolve(*handle), Some((*id, &(id * 10))));
+                }
+                for handle in &removed {
+                    assert_eq!(sparse_set.resolve(*handle), None);
+                }
+            }
+        }
+
+        let (id, handle) = handles.iter().next().map(|(id, h)| (*id, *h)).unwrap();
+        *sparse_set.resolve_mut(handle).unwrap().1 += 1;
+        assert_eq!(sparse_set.get(id), Some(&(id * 10 + 1)));
+        assert_eq!(sparse_set.as_sparse_set().check_invariants(), Ok(()));
+    }
+}
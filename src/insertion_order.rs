@@ -0,0 +1,129 @@
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A sparse set remembering the order entities were first inserted
+/// # Details
+/// Each entity has a sequence number in a Vec parallel to dense,
+/// it is moved together with the entity on swap-removals.
+/// Replacing the data of an existing id keeps its sequence number
+#[derive(Debug, Clone)]
+pub struct InsertionOrderSparseSet<E, T, S = VecStorage<E>> {
+    set: SparseSet<E, T, S>,
+    /// The sequence number of each entity, parallel to dense
+    sequences: Vec<u64>,
+    next_sequence: u64,
+}
+
+impl<E, T, S> Default for InsertionOrderSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        InsertionOrderSparseSet {
+            set: SparseSet::default(),
+            sequences: Vec::new(),
+            next_sequence: 0,
+        }
+    }
+}
+
+impl<E, T, S> InsertionOrderSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Insert the `dat` with `id`
+    /// # Returns
+    /// Return the old data if the set has this id
+    pub fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        let old = self.set.insert(id, dat);
+        if old.is_none() {
+            self.sequences.push(self.next_sequence);
+            self.next_sequence += 1;
+        }
+        old
+    }
+
+    /// Remove the entity by `id`
+    /// # Returns
+    /// Return None if the set doesn't contain `id`
+    pub fn swap_remove_by_id(&mut self, id: E) -> Option<T> {
+        let index = self.set.get_index(id)?;
+        self.sequences.swap_remove(index);
+        self.set.swap_remove_by_id(id)
+    }
+
+    /// Iterate `(id, &data)` pairs in the order they were first inserted
+    /// # Details
+    /// It sorts a temporary Vec of dense indices by sequence number
+    pub fn iter_by_insertion_order(&self) -> impl Iterator<Item = (E, &T)> {
+        let mut indices: Vec<usize> = (0..self.len()).collect();
+        indices.sort_unstable_by_key(|index| self.sequences[*index]);
+        indices
+            .into_iter()
+            .map(move |index| (self.set.ids()[index], &self.set.data()[index]))
+    }
+
+    /// Get the sequence number of the entity, smaller ones were inserted earlier
+    pub fn insertion_sequence(&self, id: E) -> Option<u64> {
+        Some(self.sequences[self.set.get_index(id)?])
+    }
+
+    /// Check if the set has id
+    pub fn contains(&self, id: E) -> bool {
+        self.set.contains(id)
+    }
+
+    /// Get the reference of data by given `id`
+    pub fn get(&self, id: E) -> Option<&T> {
+        self.set.get(id)
+    }
+
+    /// Get the MUTABLE reference of data by given `id`
+    pub fn get_mut(&mut self, id: E) -> Option<&mut T> {
+        self.set.get_mut(id)
+    }
+
+    /// Get the count of entities
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Get the inner sparse set
+    pub fn as_sparse_set(&self) -> &SparseSet<E, T, S> {
+        &self.set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InsertionOrderSparseSet;
+
+    #[test]
+    fn insertion_order_test() {
+        let mut sparse_set: InsertionOrderSparseSet<usize, char> = Default::default();
+        for (id, c) in [(5, 'a'), (1, 'b'), (9, 'c'), (3, 'd'), (7, 'e')] {
+            sparse_set.insert(id, c);
+        }
+
+        // the last entity is swapped into the middle
+        assert_eq!(sparse_set.swap_remove_by_id(9), Some('c'));
+        assert_eq!(sparse_set.as_sparse_set().ids(), &[5, 1, 7, 3]);
+        // replacing keeps the order
+        assert_eq!(sparse_set.insert(5, 'A'), Some('a'));
+        sparse_set.insert(9, 'f');
+
+        let order: Vec<_> = sparse_set.iter_by_insertion_order().collect();
+        assert_eq!(
+            order,
+            vec![(5, &'A'), (1, &'b'), (3, &'d'), (7, &'e'), (9, &'f')]
+        );
+        assert!(sparse_set.insertion_sequence(3) < sparse_set.insertion_sequence(7));
+        assert_eq!(sparse_set.insertion_sequence(100), None);
+    }
+}
@@ -0,0 +1,172 @@
+use std::{
+    collections::{hash_map::RandomState, HashMap},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A sparse set storing every value once, like an interner
+/// # Details
+/// The reverse index maps the hash of a value to the ids with this hash,
+/// the values themselves are only stored in the set, so `T` needn't be `Clone`.
+/// Sets that don't need interning use `SparseSet` and pay nothing
+#[derive(Debug, Clone)]
+pub struct InternSparseSet<E, T, S = VecStorage<E>> {
+    set: SparseSet<E, T, S>,
+    index: HashMap<u64, Vec<E>>,
+    hasher: RandomState,
+}
+
+impl<E, T, S> Default for InternSparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        InternSparseSet {
+            set: SparseSet::default(),
+            index: HashMap::new(),
+            hasher: RandomState::new(),
+        }
+    }
+}
+
+impl<E, T, S> InternSparseSet<E, T, S>
+where
+    E: Copy + Eq,
+    T: Hash + Eq,
+    S: SparseStorage<EntityId = E>,
+{
+    fn hash_of(&self, value: &T) -> u64 {
+        self.hasher.hash_one(value)
+    }
+
+    /// Get the id of the value equal to `value`
+    pub fn id_of(&self, value: &T) -> Option<E> {
+        let ids = self.index.get(&self.hash_of(value))?;
+        ids.iter()
+            .copied()
+            .find(|id| self.set.get(*id) == Some(value))
+    }
+
+    /// Intern the `value`
+    /// # Details
+    /// `make_id` is only called if no equal value is stored,
+    /// it must return an id not in the set
+    /// # Returns
+    /// Return the id and the dense index of the stored value
+    /// # Panics
+    /// Panic if the id returned by `make_id` is in the set
+    pub fn intern_with<F>(&mut self, value: T, make_id: F) -> (E, usize)
+    where
+        F: FnOnce() -> E,
+    {
+        let hash = self.hash_of(&value);
+        if let Some(ids) = self.index.get(&hash) {
+            for id in ids {
+                if let Some(index) = self.set.get_index(*id) {
+                    if self.set.data()[index] == value {
+                        return (*id, index);
+                    }
+                }
+            }
+        }
+        let id = make_id();
+        if self.set.contains(id) {
+            panic!("the id is already in the set");
+        }
+        self.set.insert(id, value);
+        self.index.entry(hash).or_default().push(id);
+        (id, self.set.len() - 1)
+    }
+
+    /// Remove the entity by `id`
+    /// # Returns
+    /// Return None if the set doesn't contain `id`
+    pub fn swap_remove_by_id(&mut self, id: E) -> Option<T> {
+        let value = self.set.swap_remove_by_id(id)?;
+        let hash = self.hash_of(&value);
+        if let Some(ids) = self.index.get_mut(&hash) {
+            ids.retain(|other| *other != id);
+            if ids.is_empty() {
+                self.index.remove(&hash);
+            }
+        }
+        Some(value)
+    }
+
+    /// Check if the set has id
+    pub fn contains(&self, id: E) -> bool {
+        self.set.contains(id)
+    }
+
+    /// Get the reference of value by given `id`
+    pub fn get(&self, id: E) -> Option<&T> {
+        self.set.get(id)
+    }
+
+    /// Get the dense index of the value by given `id`
+    pub fn get_index(&self, id: E) -> Option<usize> {
+        self.set.get_index(id)
+    }
+
+    /// Get the count of values
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Check if the set is empty
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    /// Remove all values
+    pub fn clear(&mut self) {
+        self.set.clear();
+        self.index.clear();
+    }
+
+    /// Get the inner sparse set
+    pub fn as_sparse_set(&self) -> &SparseSet<E, T, S> {
+        &self.set
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::InternSparseSet;
+
+    #[test]
+    fn intern_test() {
+        let mut materials: InternSparseSet<usize, String> = Default::default();
+        let mut next_id = 0;
+        let mut make_id = || {
+            next_id += 1;
+            next_id
+        };
+
+        let (stone, stone_index) = materials.intern_with("stone".to_string(), &mut make_id);
+        let (wood, wood_index) = materials.intern_with("wood".to_string(), &mut make_id);
+        assert_eq!((stone, stone_index), (1, 0));
+        assert_eq!((wood, wood_index), (2, 1));
+        // duplicates don't call make_id
+        for _ in 0..3 {
+            let again = materials.intern_with("stone".to_string(), || unreachable!());
+            assert_eq!(again, (stone, stone_index));
+        }
+        assert_eq!(materials.len(), 2);
+        assert_eq!(materials.id_of(&"wood".to_string()), Some(wood));
+        assert_eq!(materials.id_of(&"iron".to_string()), None);
+
+        // the reverse index is updated on removal
+        assert_eq!(
+            materials.swap_remove_by_id(stone),
+            Some("stone".to_string())
+        );
+        assert_eq!(materials.id_of(&"stone".to_string()), None);
+        assert_eq!(materials.get_index(wood), Some(0));
+        let (stone, stone_index) = materials.intern_with("stone".to_string(), &mut make_id);
+        assert_eq!((stone, stone_index), (3, 1));
+        assert_eq!(materials.as_sparse_set().ids(), &[2, 3]);
+    }
+}
@@ -0,0 +1,116 @@
+use std::iter::FusedIterator;
+
+/// An item from one of 2 sparse sets
+/// # Details
+/// Yielded by `SparseSet::symmetric_difference`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Either<L, R> {
+    /// From the left one, which is `self`
+    Left(L),
+    /// From the right one, which is `other`
+    Right(R),
+}
+
+/// Iterator over `(id, &data)` pairs in dense order
+/// # Details
+/// Created by `SparseSet::iter`.
+/// It is double-ended, so `iter().rev()` walks from the back of the dense array
+#[derive(Debug, Clone)]
+pub struct Iter<'a, E, T> {
+    pub(crate) ids: std::slice::Iter<'a, E>,
+    pub(crate) data: std::slice::Iter<'a, T>,
+}
+
+impl<'a, E, T> Iterator for Iter<'a, E, T>
+where
+    E: Copy,
+{
+    type Item = (E, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((*self.ids.next()?, self.data.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, E, T> DoubleEndedIterator for Iter<'a, E, T>
+where
+    E: Copy,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((*self.ids.next_back()?, self.data.next_back()?))
+    }
+}
+
+impl<'a, E, T> ExactSizeIterator for Iter<'a, E, T> where E: Copy {}
+
+impl<'a, E, T> FusedIterator for Iter<'a, E, T> where E: Copy {}
+
+/// Iterator over `(id, &mut data)` pairs in dense order
+#[derive(Debug)]
+pub struct IterMut<'a, E, T> {
+    pub(crate) ids: std::slice::Iter<'a, E>,
+    pub(crate) data: std::slice::IterMut<'a, T>,
+}
+
+impl<'a, E, T> Iterator for IterMut<'a, E, T>
+where
+    E: Copy,
+{
+    type Item = (E, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((*self.ids.next()?, self.data.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, E, T> DoubleEndedIterator for IterMut<'a, E, T>
+where
+    E: Copy,
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((*self.ids.next_back()?, self.data.next_back()?))
+    }
+}
+
+impl<'a, E, T> ExactSizeIterator for IterMut<'a, E, T> where E: Copy {}
+
+impl<'a, E, T> FusedIterator for IterMut<'a, E, T> where E: Copy {}
+
+/// Iterator moving `(id, data)` pairs out of a sparse set in dense order
+/// # Details
+/// Created by `SparseSet::into_iter`
+#[derive(Debug)]
+pub struct IntoIter<E, T> {
+    pub(crate) ids: std::vec::IntoIter<E>,
+    pub(crate) data: std::vec::IntoIter<T>,
+}
+
+impl<E, T> Iterator for IntoIter<E, T> {
+    type Item = (E, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.ids.next()?, self.data.next()?))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<E, T> DoubleEndedIterator for IntoIter<E, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        Some((self.ids.next_back()?, self.data.next_back()?))
+    }
+}
+
+impl<E, T> ExactSizeIterator for IntoIter<E, T> {}
+
+impl<E, T> FusedIterator for IntoIter<E, T> {}
@@ -0,0 +1,250 @@
+use std::{marker::PhantomData, num::NonZeroUsize};
+
+use crate::{SparseSet, SparseStorage};
+
+/// A key carrying a slot index and a version, like the keys of slot maps
+/// # Details
+/// Keys with the same index but different versions are different entities.
+/// `KeyedStorage` addresses its slots by the index and checks the version,
+/// so a stale key misses instead of aliasing the entity living in the slot now
+pub trait SparseKey: Copy {
+    /// Get the index part, used as the slot in the storage
+    fn to_dense_hint(&self) -> usize;
+
+    /// Get the version part
+    fn version(&self) -> u32;
+}
+
+/// SparseStorage addressed by the index part of `SparseKey`,
+/// checking the version part of keys
+/// # Details
+/// A slot holds one version at a time.
+/// Inserting a key whose slot holds another version is rejected:
+/// `SparseSet::try_insert` returns `LimitExceeded::AddressSpace` and `insert` panics,
+/// so remove the entity of the stale key first.
+/// A key whose index is `usize::MAX` is rejected the same way
+#[derive(Debug, Clone)]
+pub struct KeyedStorage<K> {
+    slots: Vec<Option<(u32, NonZeroUsize)>>,
+    _marker: PhantomData<K>,
+}
+
+impl<K> Default for KeyedStorage<K> {
+    fn default() -> Self {
+        KeyedStorage {
+            slots: Vec::new(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<K> SparseStorage for KeyedStorage<K>
+where
+    K: SparseKey,
+{
+    type EntityId = K;
+
+    fn get_index(&self, entity_id: Self::EntityId) -> Option<NonZeroUsize> {
+        match self.slots.get(entity_id.to_dense_hint()) {
+            Some(Some((version, index))) if *version == entity_id.version() => Some(*index),
+            _ => None,
+        }
+    }
+
+    fn set_index(&mut self, entity_id: Self::EntityId, index: Option<NonZeroUsize>) {
+        let slot = entity_id.to_dense_hint();
+        if let Some(index) = index {
+            if slot >= self.slots.len() {
+                self.slots.resize(slot + 1, None);
+            }
+            self.slots[slot] = Some((entity_id.version(), index));
+        } else if self.get_index(entity_id).is_some() {
+            // a stale key doesn't remove the entity living in the slot
+            self.slots[slot] = None;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    fn reserve(&mut self, hint: usize) {
+        self.slots.reserve(hint.saturating_sub(self.slots.len()));
+    }
+
+    fn slots_after(&self, entity_id: Self::EntityId) -> Option<usize> {
+        Some(
+            self.slots
+                .len()
+                .max(entity_id.to_dense_hint().saturating_add(1)),
+        )
+    }
+
+    fn is_addressable(&self, entity_id: Self::EntityId) -> bool {
+        let slot = entity_id.to_dense_hint();
+        // the slot after `usize::MAX` slots can't be allocated
+        if slot == usize::MAX {
+            return false;
+        }
+        // the slot is vacant or holds this version already
+        match self.slots.get(slot) {
+            Some(Some((version, _))) => *version == entity_id.version(),
+            _ => true,
+        }
+    }
+}
+
+/// SparseSet with `KeyedStorage` as SparseStorage
+pub type SparseSetKeyed<K, T> = SparseSet<K, T, KeyedStorage<K>>;
+
+#[cfg(feature = "slotmap")]
+impl SparseKey for slotmap::KeyData {
+    fn to_dense_hint(&self) -> usize {
+        // the low 32 bits are the index
+        (self.as_ffi() & u64::from(u32::MAX)) as usize
+    }
+
+    fn version(&self) -> u32 {
+        (self.as_ffi() >> 32) as u32
+    }
+}
+
+#[cfg(feature = "slotmap")]
+impl SparseKey for slotmap::DefaultKey {
+    fn to_dense_hint(&self) -> usize {
+        slotmap::Key::data(self).to_dense_hint()
+    }
+
+    fn version(&self) -> u32 {
+        slotmap::Key::data(self).version()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        KeyedStorage, LimitExceeded, SparseKey, SparseSet, SparseSetKeyed, SparseSetLimits,
+        SparseStorage,
+    };
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    struct Key {
+        index: usize,
+        version: u32,
+    }
+
+    impl SparseKey for Key {
+        fn to_dense_hint(&self) -> usize {
+            self.index
+        }
+
+        fn version(&self) -> u32 {
+            self.version
+        }
+    }
+
+    #[test]
+    fn stale_key_test() {
+        let key = |index, version| Key { index, version };
+        let mut sparse_set: SparseSetKeyed<Key, &str> = SparseSet::default();
+        sparse_set.insert(key(3, 1), "v1");
+        sparse_set.insert(key(0, 1), "other");
+        assert_eq!(sparse_set.get(key(3, 1)), Some(&"v1"));
+        assert_eq!(sparse_set.get(key(3, 2)), None);
+
+        assert_eq!(sparse_set.swap_remove_by_id(key(3, 2)), None);
+        assert_eq!(sparse_set.swap_remove_by_id(key(3, 1)), Some("v1"));
+        sparse_set.insert(key(3, 2), "v2");
+
+        assert_eq!(sparse_set.get(key(3, 1)), None);
+        assert!(!sparse_set.contains(key(3, 1)));
+        assert_eq!(sparse_set.swap_remove_by_id(key(3, 1)), None);
+        assert_eq!(sparse_set.get(key(3, 2)), Some(&"v2"));
+        assert_eq!(sparse_set.get(key(0, 1)), Some(&"other"));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn newer_key_before_removal_test() {
+        let key = |index, version| Key { index, version };
+        let mut sparse_set: SparseSetKeyed<Key, &str> = SparseSet::default();
+        sparse_set.insert(key(3, 1), "v1");
+        sparse_set.insert(key(0, 1), "other");
+
+        // the stale entity still holds the slot
+        assert_eq!(
+            sparse_set.try_insert(key(3, 2), "v2"),
+            Err(LimitExceeded::AddressSpace)
+        );
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert_eq!(sparse_set.get(key(3, 1)), Some(&"v1"));
+        assert_eq!(sparse_set.get(key(3, 2)), None);
+        assert_eq!(sparse_set.len(), 2);
+        // replacing the data of the same key is fine
+        assert_eq!(sparse_set.try_insert(key(3, 1), "v1'"), Ok(Some("v1")));
+
+        assert_eq!(sparse_set.swap_remove_by_id(key(3, 1)), Some("v1'"));
+        assert_eq!(sparse_set.try_insert(key(3, 2), "v2"), Ok(None));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn max_index_key_test() {
+        let key = Key {
+            index: usize::MAX,
+            version: 1,
+        };
+        let storage: KeyedStorage<Key> = KeyedStorage::default();
+        assert!(!storage.is_addressable(key));
+        assert_eq!(storage.slots_after(key), Some(usize::MAX));
+
+        let mut sparse_set: SparseSetKeyed<Key, u8> = SparseSet::default();
+        assert_eq!(
+            sparse_set.try_insert(key, 1),
+            Err(LimitExceeded::AddressSpace)
+        );
+        sparse_set.set_limits(SparseSetLimits {
+            max_sparse_slots: Some(100),
+            ..SparseSetLimits::default()
+        });
+        assert_eq!(
+            sparse_set.try_insert(key, 1),
+            Err(LimitExceeded::AddressSpace)
+        );
+        assert!(sparse_set.is_empty());
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn newer_key_insert_panic_test() {
+        let key = |index, version| Key { index, version };
+        let mut sparse_set: SparseSetKeyed<Key, &str> = SparseSet::default();
+        sparse_set.insert(key(3, 1), "v1");
+        sparse_set.insert(key(0, 1), "other");
+        sparse_set.insert(key(3, 2), "v2");
+    }
+
+    #[cfg(feature = "slotmap")]
+    #[test]
+    fn slotmap_test() {
+        use slotmap::{DefaultKey, SlotMap};
+
+        let mut slot_map: SlotMap<DefaultKey, ()> = SlotMap::new();
+        let mut sparse_set: SparseSetKeyed<DefaultKey, u32> = SparseSet::default();
+
+        let v1 = slot_map.insert(());
+        sparse_set.insert(v1, 1);
+        slot_map.remove(v1);
+        sparse_set.swap_remove_by_id(v1);
+
+        // slot map reuses the slot with a newer version
+        let v2 = slot_map.insert(());
+        assert_eq!(v1.to_dense_hint(), v2.to_dense_hint());
+        assert_ne!(v1.version(), v2.version());
+        sparse_set.insert(v2, 2);
+
+        assert_eq!(sparse_set.get(v1), None);
+        assert_eq!(sparse_set.get(v2), Some(&2));
+    }
+}
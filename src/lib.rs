@@ -1,13 +1,88 @@
 //! # XSparseSet
 //! Sparse-set is a data-structure that can get data by dispersed ID and cache-friendly
+#[cfg(feature = "arbitrary")]
+mod arbitrary;
+mod bounded;
+mod builder;
+#[cfg(feature = "capi")]
+mod capi;
+mod cmp;
+mod commands;
+mod drain;
+mod entry;
+mod error;
+mod generational;
+mod group;
+mod handle;
+mod insertion_order;
+mod intern;
+mod keyed;
+mod multi;
+mod partition;
+mod prefetch;
+#[cfg(feature = "roaring")]
+mod roaring;
 mod sparse_storage;
+mod tracked;
+mod view;
+mod iter;
+mod limits;
+mod map_like;
+#[cfg(feature = "fixedbitset")]
+mod mask;
+mod registry;
+mod repair;
+mod resume;
+#[cfg(feature = "rayon")]
+mod par;
+#[cfg(feature = "rand")]
+mod shuffle;
+#[cfg(feature = "std")]
+mod stream;
 
 use std::{
+    borrow::Cow,
     collections::{BTreeMap, HashMap},
+    convert::{TryFrom, TryInto},
+    iter::FromIterator,
+    mem::MaybeUninit,
     num::NonZeroUsize,
+    ops::{Bound, RangeBounds},
 };
 
-pub use sparse_storage::{SparseStorage, VecStorage};
+use prefetch::{prefetch_read, touch_spare_capacity};
+
+pub use bounded::BoundedSparseSet;
+pub use builder::{SortedBuilder, SparseSetBuilder};
+#[cfg(feature = "capi")]
+pub use capi::{XSparseSetF32Slice, XSparseSetU64F32, XSparseSetU64Slice};
+pub use commands::{ApplyReport, SparseSetCommands};
+pub use drain::DrainRange;
+pub use entry::{Entry, OccupiedEntry, VacantEntry};
+pub use error::{
+    HistogramError, IdOverflow, IndexOutOfRange, LimitExceeded, MissingId, OrderError,
+};
+pub use generational::{GenerationalId, GenerationalSparseSet};
+pub use group::OwningGroup;
+pub use handle::{Handle, HandleSparseSet};
+pub use insertion_order::InsertionOrderSparseSet;
+pub use intern::InternSparseSet;
+pub use iter::{Either, IntoIter, Iter, IterMut};
+pub use keyed::{KeyedStorage, SparseKey, SparseSetKeyed};
+pub use limits::SparseSetLimits;
+pub use map_like::MapLike;
+pub use multi::{MultiValues, SparseMultiSet};
+pub use partition::SparsePartitionMut;
+pub use registry::{DynSparseSet, SparseSetRegistry};
+pub use repair::{InvalidSparseSet, InvariantError, RepairReport};
+pub use resume::{ResumableIter, ResumeCursor};
+pub use sparse_storage::{
+    OrderedSparseStorage, SmallVecStorage, SparseStorage, U64VecStorage, VecStorage,
+};
+#[cfg(feature = "std")]
+pub use stream::{FixedCodec, ReadError, StreamEncoding, STREAM_HEADER_LEN};
+pub use tracked::{Mut, Tracked};
+pub use view::{Columns, Entries, PairSlice, SparseSetView};
 
 /// SparseSet with `Vec` as SparseStorage
 pub type SparseSetVec<E, T> = SparseSet<E, T, VecStorage<E>>;
@@ -15,17 +90,25 @@ pub type SparseSetVec<E, T> = SparseSet<E, T, VecStorage<E>>;
 pub type SparseSetHashMap<E, T> = SparseSet<E, T, HashMap<E, NonZeroUsize>>;
 /// SparseSet with `BTreeMap` as SparseStorage
 pub type SparseSetBTreeMap<E, T> = SparseSet<E, T, BTreeMap<E, NonZeroUsize>>;
+/// SparseSet with `SmallVecStorage` as SparseStorage
+pub type SparseSetSmall<E, T, const INLINE: usize> = SparseSet<E, T, SmallVecStorage<E, INLINE>>;
 
 /// The core struct
 /// # Type parameters
 /// * `E` is the type of entity id
 /// * `T` is the type of the data stored in `SparseSet`
 /// * `S` is the type of the sparse storage
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct SparseSet<E, T, S> {
     sparse: S,
     dense: Vec<E>,
     data: Vec<T>,
+    limits: SparseSetLimits,
+    /// Incremented when entities are moved by anything but a swap-removal,
+    /// so `iter_resumable` can tell its position is stale
+    reorder_version: u64,
+    /// The name shown in `{:?}` and panic messages
+    debug_name: Option<Cow<'static, str>>,
 }
 
 impl<E, T, S> Default for SparseSet<E, T, S>
@@ -38,7 +121,28 @@ where
             sparse: S::default(),
             dense: Vec::new(),
             data: Vec::new(),
+            limits: SparseSetLimits::default(),
+            reorder_version: 0,
+            debug_name: None,
+        }
+    }
+}
+
+/// Print `(id, data)` pairs in dense order like a map,
+/// headed by the name set by `set_debug_name` and the type of data
+impl<E, T, S> std::fmt::Debug for SparseSet<E, T, S>
+where
+    E: std::fmt::Debug,
+    T: std::fmt::Debug,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.debug_name {
+            Some(name) => write!(f, "SparseSet {:?} of {} ", name, std::any::type_name::<T>())?,
+            None => write!(f, "SparseSet ")?,
         }
+        f.debug_map()
+            .entries(self.dense.iter().zip(self.data.iter()))
+            .finish()
     }
 }
 
@@ -53,7 +157,76 @@ where
             sparse: sparse_storage,
             dense: Vec::new(),
             data: Vec::new(),
+            limits: SparseSetLimits::default(),
+            reorder_version: 0,
+            debug_name: None,
+        }
+    }
+
+    /// Create sparse set from the sparse storage, the dense array and the data array
+    /// # Safety
+    /// The parts must satisfy the mapping between the sparse and the dense,
+    /// like the parts returned by `into_raw_parts`.  
+    /// Otherwise only `check_invariants`, `repair` and `into_raw_parts` can be called
+    /// before the mapping is fixed
+    pub unsafe fn from_raw_parts(sparse: S, dense: Vec<E>, data: Vec<T>) -> Self {
+        SparseSet {
+            sparse,
+            dense,
+            data,
+            limits: SparseSetLimits::default(),
+            reorder_version: 0,
+            debug_name: None,
+        }
+    }
+
+    /// Decompose the sparse set into the sparse storage, the dense array and the data array
+    pub fn into_raw_parts(self) -> (S, Vec<E>, Vec<T>) {
+        (self.sparse, self.dense, self.data)
+    }
+
+    /// Move all `(id, data)` pairs out in dense order
+    /// # Details
+    /// The pairs are collected in place into the buffer of dense
+    /// if a pair fits in the size of an id (like `T = ()`),
+    /// otherwise one new Vec is allocated
+    pub fn into_pairs(self) -> Vec<(E, T)> {
+        self.dense.into_iter().zip(self.data).collect()
+    }
+
+    /// Move the data out in dense order, the ids are dropped
+    pub fn into_data(self) -> Vec<T> {
+        self.data
+    }
+
+    /// Move the ids out in dense order, the data is dropped
+    pub fn into_ids(self) -> Vec<E> {
+        self.dense
+    }
+
+    /// Map every `(id, data)` pair into a new sparse set with `f(id, data)`
+    /// # Details
+    /// The new ids may be of another type in another storage,
+    /// the pairs keep the dense order
+    /// # Panics
+    /// Panic if `f` returns an id twice
+    pub fn map<E2, U, S2, F>(self, mut f: F) -> SparseSet<E2, U, S2>
+    where
+        E2: Copy,
+        S2: SparseStorage<EntityId = E2> + Default,
+        F: FnMut(E, T) -> (E2, U),
+    {
+        let mut mapped = SparseSet::default();
+        mapped.reserve(self.len());
+        mapped.debug_name = self.debug_name;
+        for (id, dat) in self.dense.into_iter().zip(self.data) {
+            let (id, dat) = f(id, dat);
+            if mapped.contains(id) {
+                panic!("the mapped ids collide");
+            }
+            mapped.push_unchecked(id, dat);
         }
+        mapped
     }
 
     /// Clear the sparse set
@@ -63,40 +236,311 @@ where
         self.data.clear();
     }
 
+    /// Clear the sparse set, moving every `(id, data)` pair into `f` in dense order
+    /// # Details
+    /// The set is always empty after it, and the capacity is kept like `clear`.  
+    /// The sparse storage is cleared before `f` is called,
+    /// so if `f` panics the set is still empty and the pairs left are dropped
+    pub fn clear_in_order<F>(&mut self, mut f: F)
+    where
+        F: FnMut(E, T),
+    {
+        self.sparse.clear();
+        for (id, dat) in self.dense.drain(..).zip(self.data.drain(..)) {
+            f(id, dat);
+        }
+    }
+
+    /// Replace all entities with the `pairs`, reusing the allocations
+    /// # Details
+    /// It is `clear` and then inserting every pair,
+    /// with the dense arrays reserved from the size hint of `pairs`.  
+    /// A repeated id keeps the last data like `insert`.
+    /// The set is empty if `pairs` is empty
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn replace_all<I>(&mut self, pairs: I)
+    where
+        I: IntoIterator<Item = (E, T)>,
+    {
+        self.clear();
+        self.extend(pairs);
+    }
+
+    /// Name the sparse set for diagnostics
+    /// # Details
+    /// The name is shown in `{:?}` and in the panic messages of sparse set
+    pub fn set_debug_name(&mut self, name: impl Into<Cow<'static, str>>) {
+        self.debug_name = Some(name.into());
+    }
+
+    /// Get the name set by `set_debug_name`
+    pub fn debug_name(&self) -> Option<&str> {
+        self.debug_name.as_deref()
+    }
+
+    /// Get the name of the data type for diagnostics
+    /// # Details
+    /// It is shown with the name set by `set_debug_name`
+    pub fn data_type_name(&self) -> &'static str {
+        std::any::type_name::<T>()
+    }
+
+    /// Get the `TypeId` of the data type
+    pub fn data_type_id(&self) -> std::any::TypeId
+    where
+        T: 'static,
+    {
+        std::any::TypeId::of::<T>()
+    }
+
+    /// The prefix of panic messages naming the sparse set
+    fn name_prefix(&self) -> NamePrefix<'_> {
+        NamePrefix(self.debug_name(), self.data_type_name())
+    }
+
     /// Insert the `dat` with `id` into sparse set
     /// # return
     /// It returns Some(T) if sparse set has this id ,
     /// otherwise returns None
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
     pub fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        self.try_insert(id, dat)
+            .unwrap_or_else(|error| panic!("{}{}", self.name_prefix(), error))
+    }
+
+    /// Insert the `dat` with `id` into sparse set
+    /// # return
+    /// It returns Ok(Some(T)) if sparse set has this id ,
+    /// Ok(None) if the id is new,
+    /// or Err if it would grow past the limits set by `set_limits`
+    pub fn try_insert(&mut self, id: E, dat: T) -> Result<Option<T>, LimitExceeded> {
         if let Some(index) = self.sparse.get_index(id) {
             let index: usize = index.get() - 1;
             // Safety
             // The index stored in sparse is always in range
             let data_ref = unsafe { self.data.get_unchecked_mut(index) };
-            Some(std::mem::replace(data_ref, dat))
+            Ok(Some(std::mem::replace(data_ref, dat)))
         } else {
-            let new_index = NonZeroUsize::new(self.dense.len() + 1);
-            self.sparse.set_index(id, new_index);
-            self.dense.push(id);
-            self.data.push(dat);
-            None
+            self.check_limits(1, &[id])?;
+            self.push_unchecked(id, dat);
+            Ok(None)
+        }
+    }
+
+    /// Get the MUTABLE reference of data by given `id`,
+    /// or insert the data made by `f` if sparse set doesn't contain this `id`
+    /// # Details
+    /// `f` is only called if `id` is not in sparse set.
+    /// If `f` returns Err, nothing is inserted and the error is returned
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn get_or_try_insert_with<Err, F>(&mut self, id: E, f: F) -> Result<&mut T, Err>
+    where
+        F: FnOnce() -> Result<T, Err>,
+    {
+        let index = match self.sparse.get_index(id) {
+            Some(index) => index.get() - 1,
+            None => {
+                let dat = f()?;
+                if let Err(error) = self.check_limits(1, &[id]) {
+                    panic!("{}{}", self.name_prefix(), error);
+                }
+                self.push_unchecked(id, dat);
+                self.len() - 1
+            }
+        };
+        // Safety
+        // The index stored in sparse is always in range
+        Ok(unsafe { self.data.get_unchecked_mut(index) })
+    }
+
+    /// Insert the `dat` with `id` and get the MUTABLE reference of the stored data
+    /// # Details
+    /// The old data is dropped if sparse set has this id,
+    /// use `insert` to get it back
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn insert_ref(&mut self, id: E, dat: T) -> &mut T {
+        let index = match self.sparse.get_index(id) {
+            Some(index) => {
+                let index = index.get() - 1;
+                // Safety
+                // The index stored in sparse is always in range
+                *unsafe { self.data.get_unchecked_mut(index) } = dat;
+                index
+            }
+            None => {
+                if let Err(error) = self.check_limits(1, &[id]) {
+                    panic!("{}{}", self.name_prefix(), error);
+                }
+                self.push_unchecked(id, dat);
+                self.len() - 1
+            }
+        };
+        // Safety
+        // The index stored in sparse is always in range
+        unsafe { self.data.get_unchecked_mut(index) }
+    }
+
+    /// Insert the `dat` with `id` which is probably greater than all ids in sparse set
+    /// # Details
+    /// If the storage can tell `id` is after the last id (like `VecStorage`),
+    /// the containment probe is skipped and the data is pushed directly.
+    /// Otherwise it falls back to `insert` silently,
+    /// so it is always correct even if the ids are not increasing
+    /// # return
+    /// It returns Some(T) if sparse set has this id ,
+    /// otherwise returns None
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn insert_append_hint(&mut self, id: E, dat: T) -> Option<T> {
+        if !self.sparse.is_after_last(id) {
+            return self.insert(id, dat);
+        }
+        if let Err(error) = self.check_limits(1, &[id]) {
+            panic!("{}{}", self.name_prefix(), error);
         }
+        self.push_unchecked(id, dat);
+        None
+    }
+
+    /// Push a new entity without checking if `id` is in sparse set
+    fn push_unchecked(&mut self, id: E, dat: T) {
+        let new_index = first_new_index(self.dense.len(), 1);
+        self.sparse.set_index(id, Some(new_index));
+        self.dense.push(id);
+        self.data.push(dat);
     }
 
     /// Insert a lot of data
     /// # Panics
     /// * `ids.len() != data.len()`
+    /// * It grows past the limits set by `set_limits`
     pub fn insert_batch(&mut self, ids: &mut Vec<E>, data: &mut Vec<T>) {
+        if let Err(error) = self.try_insert_batch(ids, data) {
+            panic!("{}{}", self.name_prefix(), error);
+        }
+    }
+
+    /// Insert a lot of data
+    /// # Returns
+    /// Return Err and insert nothing if it would grow past the limits set by `set_limits`
+    /// # Panics
+    /// * `ids.len() != data.len()`
+    pub fn try_insert_batch(
+        &mut self,
+        ids: &mut Vec<E>,
+        data: &mut Vec<T>,
+    ) -> Result<(), LimitExceeded> {
         if ids.len() != data.len() {
             panic!("ids.len() != dat.len()")
         }
-        let start_index = self.data.len() + 1;
-        // # Safety
-        // * the index stored in sparse is start from 1
-        let start_index = unsafe { NonZeroUsize::new_unchecked(start_index) };
-        self.sparse.set_indices(&ids, start_index);
+        self.check_limits(ids.len(), ids)?;
+        self.reserve_for_ids(ids);
+        let start_index = first_new_index(self.data.len(), ids.len());
+        self.sparse.set_indices(ids, start_index);
         self.dense.append(ids);
         self.data.append(data);
+        Ok(())
+    }
+
+    /// Insert every pair like `try_insert`, a repeated id keeps the last data
+    /// # Returns
+    /// Return Err at the first pair that would grow past the limits set by `set_limits`.
+    /// The pairs before it stay inserted, the failing pair is dropped
+    /// and the pairs after it are not consumed
+    pub fn try_extend<I>(&mut self, pairs: I) -> Result<(), LimitExceeded>
+    where
+        I: IntoIterator<Item = (E, T)>,
+    {
+        let pairs = pairs.into_iter();
+        // the size hint can't reserve past `max_len`
+        let additional = match self.limits.max_len {
+            Some(max_len) => pairs.size_hint().0.min(max_len.saturating_sub(self.len())),
+            None => pairs.size_hint().0,
+        };
+        self.reserve(additional);
+        for (id, dat) in pairs {
+            self.try_insert(id, dat)?;
+        }
+        Ok(())
+    }
+
+    /// Register the `ids` and return their uninitialized data slots, to build large data in place
+    /// # Details
+    /// The ids are appended to dense in order,
+    /// the `i`-th slot is the data of `ids[i]`.  
+    /// Call `assume_init_slots` after every slot is written
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    /// # Safety
+    /// * The ids must be unique and not in sparse set
+    /// * Every returned slot must be initialized, then `assume_init_slots` must be called
+    ///   before any other method of sparse set, including drop.
+    ///   Otherwise the ids are mapped to data out of range
+    pub unsafe fn reserve_slots(&mut self, ids: &[E]) -> &mut [MaybeUninit<T>] {
+        if let Err(error) = self.check_limits(ids.len(), ids) {
+            panic!("{}{}", self.name_prefix(), error);
+        }
+        debug_assert!(self.data.len() == self.dense.len());
+        self.reserve_for_ids(ids);
+        let start_index = first_new_index(self.dense.len(), ids.len());
+        self.sparse.set_indices(ids, start_index);
+        self.dense.extend_from_slice(ids);
+        &mut self.data.spare_capacity_mut()[..ids.len()]
+    }
+
+    /// Finish `reserve_slots`, the data in the slots becomes the data of the ids
+    /// # Safety
+    /// The slots returned by the last `reserve_slots` must be all initialized
+    pub unsafe fn assume_init_slots(&mut self) {
+        let len = self.dense.len();
+        self.data.set_len(len);
+    }
+
+    /// Insert a lot of data copied from slices
+    /// # Details
+    /// Unlike `insert_batch`, the ids may be in sparse set already
+    /// or repeated, the data is overwritten by the later one
+    /// # Panics
+    /// * `ids.len() != data.len()`
+    /// * It grows past the limits set by `set_limits`
+    pub fn extend_from_slices(&mut self, ids: &[E], data: &[T])
+    where
+        T: Copy,
+    {
+        if ids.len() != data.len() {
+            panic!("ids.len() != dat.len()")
+        }
+        self.reserve(ids.len());
+        for (id, dat) in ids.iter().zip(data.iter()) {
+            self.insert(*id, *dat);
+        }
+    }
+
+    /// Merge all entities of `other` into this sparse set
+    /// # Details
+    /// * Entities only in `other` are cloned and appended in the dense order of `other`
+    /// * Entities in both sets call `resolver(id, &mut self_data, &other_data)`
+    ///   in the dense order of `other`
+    pub fn union_with<S2, F>(&mut self, other: &SparseSet<E, T, S2>, mut resolver: F)
+    where
+        T: Clone,
+        S2: SparseStorage<EntityId = E>,
+        F: FnMut(E, &mut T, &T),
+    {
+        for (id, other_data) in other.dense.iter().zip(other.data.iter()) {
+            if let Some(index) = self.get_index(*id) {
+                // Safety
+                // The index stored in sparse is always in range
+                let data_ref = unsafe { self.data.get_unchecked_mut(index) };
+                resolver(*id, data_ref, other_data);
+            } else {
+                self.insert(*id, other_data.clone());
+            }
+        }
     }
 
     /// Remove from sparse set
@@ -108,6 +552,118 @@ where
         self.swap_remove_by_index(index)
     }
 
+    /// Remove from sparse set,
+    /// checking whether `id` is at the dense index `hint` before probing the sparse storage
+    /// # Details
+    /// The result is the same as `swap_remove_by_id(id)` whether the hint is right, stale or out of range
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn swap_remove_with_hint(&mut self, id: E, hint: usize) -> Option<T>
+    where
+        E: PartialEq,
+    {
+        let index = self.index_with_hint(id, hint)?;
+        self.swap_remove_by_index(index)
+    }
+
+    /// Get the id that `swap_remove_by_id(id)` would move into the slot of `id`
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`,
+    /// `Some(None)` if `id` is the last one so nothing would be moved
+    pub fn swap_target_of(&self, id: E) -> Option<Option<E>> {
+        let index = self.get_index(id)?;
+        if index + 1 == self.len() {
+            Some(None)
+        } else {
+            self.dense.last().map(|last| Some(*last))
+        }
+    }
+
+    /// Remove all entities that `keep(id, &data)` returns false
+    /// # Details
+    /// It is one pass like `retain_with_drop`,
+    /// the order of the kept entities is not preserved
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(E, &T) -> bool,
+    {
+        self.retain_mut(|id, dat| keep(id, dat))
+    }
+
+    /// Remove all entities that `keep(id, &mut data)` returns false
+    /// # Details
+    /// `keep` may change the data before deciding, the changes of kept data are kept.  
+    /// The entities are visited from the back of the dense array like `retain_with_drop`
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain_mut<F>(&mut self, mut keep: F) -> usize
+    where
+        F: FnMut(E, &mut T) -> bool,
+    {
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let (id, dat) = unsafe {
+                (
+                    *self.dense.get_unchecked(index),
+                    self.data.get_unchecked_mut(index),
+                )
+            };
+            if !keep(id, dat) {
+                self.swap_remove_by_index(index);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Remove all entities that `keep(id, &data)` returns false,
+    /// moving each removed data into `on_remove(id, data)`
+    /// # Details
+    /// The entities are visited from the back of the dense array,
+    /// so every kept one is visited before it may be swapped forward.  
+    /// The order of the kept entities is not preserved
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain_with_drop<F, D>(&mut self, mut keep: F, mut on_remove: D) -> usize
+    where
+        F: FnMut(E, &T) -> bool,
+        D: FnMut(E, T),
+    {
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let (id, dat) = unsafe {
+                (
+                    *self.dense.get_unchecked(index),
+                    self.data.get_unchecked(index),
+                )
+            };
+            if !keep(id, dat) {
+                if let Some(dat) = self.swap_remove_by_index(index) {
+                    on_remove(id, dat);
+                    count += 1;
+                }
+            }
+        }
+        count
+    }
+
+    /// Remove from sparse set by index
+    /// # Returns
+    /// Return Err if index is out of range
+    pub fn try_swap_remove_by_index(&mut self, index: usize) -> Result<T, IndexOutOfRange> {
+        self.check_index(index)?;
+        self.swap_remove_by_index(index).ok_or(IndexOutOfRange {
+            index,
+            len: self.len(),
+        })
+    }
+
     /// Remove from sparse set
     /// # return
     /// It returns Some(T) if index is valid,
@@ -115,14 +671,16 @@ where
     pub fn swap_remove_by_index(&mut self, index: usize) -> Option<T> {
         let id = self.get_id(index)?;
 
-        self.swap_by_index(index, self.len() - 1);
+        // Safety
+        // index is checked by get_id, and the last index is in range too.
+        // Swap-removals keep the reorder version, iter_resumable copes with them
+        unsafe { self.swap_unchecked(index, self.len() - 1) };
 
-        self.sparse.set_index(id,None);
+        self.sparse.set_index(id, None);
         self.dense.pop();
         self.data.pop()
     }
 
-
     /// swap 2 entities in sparse set by entity id
     /// # Details
     /// Do nothing if `id_a` or `id_b` is NOT in sparse set
@@ -147,19 +705,57 @@ where
     /// Panic if index is out of range
     pub fn swap_by_index(&mut self, index_a: usize, index_b: usize) {
         if index_a >= self.len() {
-            panic!("index_a={} is out of range", index_a);
+            panic!("{}index_a={} is out of range", self.name_prefix(), index_a);
         }
         if index_b >= self.len() {
-            panic!("index_b={} is out of range", index_b);
+            panic!("{}index_b={} is out of range", self.name_prefix(), index_b);
         }
 
         unsafe { self.swap_by_index_unchecked(index_a, index_b) }
     }
 
+    /// swap 2 entities in sparse set by index
+    /// # Returns
+    /// Return Err if any index is out of range, nothing is swapped
+    pub fn try_swap_by_index(
+        &mut self,
+        index_a: usize,
+        index_b: usize,
+    ) -> Result<(), IndexOutOfRange> {
+        self.check_index(index_a)?;
+        self.check_index(index_b)?;
+        // Safety
+        // The indices are checked above
+        unsafe { self.swap_by_index_unchecked(index_a, index_b) }
+        Ok(())
+    }
+
+    fn check_index(&self, index: usize) -> Result<(), IndexOutOfRange> {
+        if index < self.len() {
+            Ok(())
+        } else {
+            Err(IndexOutOfRange {
+                index,
+                len: self.len(),
+            })
+        }
+    }
+
     /// swap 2 entities in sparse set by index with out any check
     /// # Safety
     /// Safe only `index_a` and `index_b` is less than `self.len()`
     pub unsafe fn swap_by_index_unchecked(&mut self, index_a: usize, index_b: usize) {
+        if index_a == index_b {
+            return;
+        }
+        self.reorder_version = self.reorder_version.wrapping_add(1);
+        self.swap_unchecked(index_a, index_b);
+    }
+
+    /// Swap 2 entities without changing the reorder version
+    /// # Safety
+    /// Safe only `index_a` and `index_b` is less than `self.len()`
+    unsafe fn swap_unchecked(&mut self, index_a: usize, index_b: usize) {
         if index_a == index_b {
             return;
         }
@@ -171,6 +767,135 @@ where
         self.data.swap(index_a, index_b);
     }
 
+    /// Reserve capacity for at least `additional` more entities in the dense arrays
+    pub fn reserve(&mut self, additional: usize) {
+        self.dense.reserve(additional);
+        self.data.reserve(additional);
+    }
+
+    /// Reserve capacity for inserting the new `ids` on both sides
+    /// # Details
+    /// The dense arrays reserve `ids.len()` more entities.
+    /// Storages made of slots like `VecStorage` reserve the slots up to the greatest id,
+    /// others reserve the count of entities after inserting.
+    /// Ids the storage can't map are skipped,
+    /// and the slots never exceed `max_sparse_slots` of the limits
+    pub fn reserve_for_ids(&mut self, ids: &[E]) {
+        self.reserve(ids.len());
+        let hint = ids
+            .iter()
+            .filter(|id| self.sparse.is_addressable(**id))
+            .filter_map(|id| self.sparse.slots_after(*id))
+            .max()
+            .unwrap_or(self.len() + ids.len());
+        let hint = self
+            .limits
+            .max_sparse_slots
+            .map_or(hint, |max_sparse_slots| hint.min(max_sparse_slots));
+        self.sparse.reserve(hint);
+    }
+
+    /// Fault in the memory reserved by the sparse storage and the dense arrays
+    /// # Details
+    /// Call it after reserving and before a latency-sensitive phase,
+    /// so the first use of the reserved memory doesn't page fault.
+    /// The entities are not changed
+    pub fn warm_up(&mut self) {
+        self.sparse.warm_up();
+        touch_spare_capacity(&mut self.dense);
+        touch_spare_capacity(&mut self.data);
+    }
+
+    /// Get the count of entities can be held without reallocating the dense arrays
+    pub fn capacity(&self) -> usize {
+        self.dense.capacity().min(self.data.capacity())
+    }
+
+    /// Reorder the entities by the ids in `order`
+    /// # Details
+    /// * The ids in `order` come first in the same sequence
+    /// * The ids not in `order` keep their relative order at the end
+    /// * The ids in `order` but not in sparse set are skipped
+    /// # Returns
+    /// Return Err and change nothing if an id in sparse set appears more than once in `order`
+    pub fn sort_by_id_order(&mut self, order: &[E]) -> Result<(), OrderError<E>> {
+        let mut placed = vec![false; self.len()];
+        let mut permutation = Vec::with_capacity(self.len());
+        for id in order {
+            if let Some(index) = self.get_index(*id) {
+                if placed[index] {
+                    return Err(OrderError::DuplicateId(*id));
+                }
+                placed[index] = true;
+                permutation.push(index);
+            }
+        }
+        permutation.extend((0..self.len()).filter(|index| !placed[*index]));
+        self.apply_permutation(&permutation);
+        Ok(())
+    }
+
+    /// Rotate the dense order so the entity at `n` becomes the first
+    /// # Details
+    /// `n` wraps around `len()`.
+    /// Every sparse index is rewritten, so it costs O(n) even for a small `n`
+    pub fn rotate_left(&mut self, n: usize) {
+        if self.is_empty() {
+            return;
+        }
+        let n = n % self.len();
+        self.dense.rotate_left(n);
+        self.data.rotate_left(n);
+        self.reorder_version = self.reorder_version.wrapping_add(1);
+        self.reset_sparse_indices();
+    }
+
+    /// Rotate the dense order so the last `n` entities become the first
+    /// # Details
+    /// `n` wraps around `len()`.
+    /// Every sparse index is rewritten, so it costs O(n) even for a small `n`
+    pub fn rotate_right(&mut self, n: usize) {
+        if self.is_empty() {
+            return;
+        }
+        let n = n % self.len();
+        self.dense.rotate_right(n);
+        self.data.rotate_right(n);
+        self.reorder_version = self.reorder_version.wrapping_add(1);
+        self.reset_sparse_indices();
+    }
+
+    /// Map every id in dense to its index again
+    fn reset_sparse_indices(&mut self) {
+        // Safety
+        // 1 is not zero
+        let start_index = unsafe { NonZeroUsize::new_unchecked(1) };
+        self.sparse.set_indices(&self.dense, start_index);
+    }
+
+    /// Move the entity at `permutation[i]` to `i` for every `i`
+    /// # Details
+    /// `permutation` must contain every index exactly once
+    fn apply_permutation(&mut self, permutation: &[usize]) {
+        debug_assert_eq!(permutation.len(), self.len());
+        let mut visited = vec![false; permutation.len()];
+        for start in 0..permutation.len() {
+            let mut index = start;
+            while !visited[index] {
+                visited[index] = true;
+                let from = permutation[index];
+                if from == start {
+                    break;
+                }
+                self.dense.swap(index, from);
+                self.data.swap(index, from);
+                index = from;
+            }
+        }
+        self.reorder_version = self.reorder_version.wrapping_add(1);
+        self.reset_sparse_indices();
+    }
+
     /// Get the count of entities in sparse set
     pub fn len(&self) -> usize {
         self.dense.len()
@@ -186,6 +911,105 @@ where
         self.sparse.get_index(id).is_some()
     }
 
+    /// Count how many of `ids` are in the sparse set
+    /// # Details
+    /// A repeated id is counted every time it appears
+    pub fn count_present<I>(&self, ids: I) -> usize
+    where
+        I: IntoIterator<Item = E>,
+    {
+        ids.into_iter().filter(|id| self.contains(*id)).count()
+    }
+
+    /// Check if every id in `other` is in this sparse set
+    pub fn is_superset<T2, S2>(&self, other: &SparseSet<E, T2, S2>) -> bool
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        other.len() <= self.len() && other.dense.iter().all(|id| self.contains(*id))
+    }
+
+    /// Check if this sparse set and `other` have exactly the same ids
+    /// # Details
+    /// The data and the dense order are ignored.
+    /// The lengths are compared first, then every id is probed in `other`
+    pub fn same_ids<T2, S2>(&self, other: &SparseSet<E, T2, S2>) -> bool
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        self.len() == other.len() && other.is_superset(self)
+    }
+
+    /// Check if this sparse set and `other` have exactly the same ids
+    /// # Details
+    /// It is the same as `same_ids`.
+    /// The lengths are compared first, then every id of this sparse set is probed in `other`,
+    /// stopping at the first missing one.  
+    /// Use `dense_ids_eq` if the dense order matters too
+    pub fn keys_eq<U, S2>(&self, other: &SparseSet<E, U, S2>) -> bool
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        self.same_ids(other)
+    }
+
+    /// Check if this sparse set and `other` have the same ids in the same dense order
+    /// # Details
+    /// It only compares the dense arrays without probing,
+    /// so the entities are at the same dense index in both sets if it returns true
+    pub fn dense_ids_eq<U, S2>(&self, other: &SparseSet<E, U, S2>) -> bool
+    where
+        E: PartialEq,
+        S2: SparseStorage<EntityId = E>,
+    {
+        self.dense == other.dense
+    }
+
+    /// Check if any id is in both this sparse set and `other`
+    /// # Details
+    /// It iterates the smaller one, probes the larger one and stops at the first shared id
+    pub fn intersects<T2, S2>(&self, other: &SparseSet<E, T2, S2>) -> bool
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        if self.len() <= other.len() {
+            self.dense.iter().any(|id| other.contains(*id))
+        } else {
+            other.dense.iter().any(|id| self.contains(*id))
+        }
+    }
+
+    /// Iterate the entities in exactly one of this sparse set and `other`
+    /// # Details
+    /// The entities only in this sparse set come first as `Either::Left`,
+    /// then the entities only in `other` as `Either::Right`, both in dense order
+    #[allow(clippy::type_complexity)]
+    pub fn symmetric_difference<'a, U, S2>(
+        &'a self,
+        other: &'a SparseSet<E, U, S2>,
+    ) -> impl Iterator<Item = Either<(E, &'a T), (E, &'a U)>> + 'a
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        let left = self
+            .iter()
+            .filter(move |(id, _)| !other.contains(*id))
+            .map(Either::Left);
+        let right = other
+            .iter()
+            .filter(move |(id, _)| !self.contains(*id))
+            .map(Either::Right);
+        left.chain(right)
+    }
+
+    /// Check if every id in this sparse set is in `other`
+    pub fn is_subset<T2, S2>(&self, other: &SparseSet<E, T2, S2>) -> bool
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        other.is_superset(self)
+    }
+
     /// Get the reference of data by given `id`
     /// # Returns
     /// Return None if sparse set doesn't contain this `id`
@@ -206,159 +1030,2931 @@ where
         unsafe { Some(self.data.get_unchecked_mut(index)) }
     }
 
-    /// Get the index of the entity was given by `id` in sparse set
+    /// Get the index of `id` checking the dense index `hint` first
+    fn index_with_hint(&self, id: E, hint: usize) -> Option<usize>
+    where
+        E: PartialEq,
+    {
+        if self.dense.get(hint) == Some(&id) {
+            Some(hint)
+        } else {
+            self.get_index(id)
+        }
+    }
+
+    /// Get the reference of data by given `id`,
+    /// checking whether `id` is at the dense index `hint` before probing the sparse storage
+    /// # Details
+    /// The result is the same as `get(id)` whether the hint is right, stale or out of range
     /// # Returns
     /// Return None if sparse set doesn't contain this `id`
-    pub fn get_index(&self, id: E) -> Option<usize> {
-        self.sparse.get_index(id).map(|x| x.get() - 1)
+    pub fn get_with_hint(&self, id: E, hint: usize) -> Option<&T>
+    where
+        E: PartialEq,
+    {
+        let index = self.index_with_hint(id, hint)?;
+        // Safety
+        // The index is checked by dense or stored in sparse, so it is in range
+        unsafe { Some(self.data.get_unchecked(index)) }
     }
 
-    /// Get the Id from index
-    /// # Return
-    /// Return None if index is not valid
-    pub fn get_id(&self, index: usize) -> Option<E> {
-        self.dense.get(index).copied()
+    /// Call `f(id, &mut data)` for every id in `ids` that is in sparse set
+    /// # Details
+    /// `f` is called once per occurrence, so a repeated id is visited repeatedly.  
+    /// For large sets the sparse slots of the ids some iterations ahead are prefetched
+    /// # Returns
+    /// Return the count of calls to `f`
+    pub fn apply_batch<F>(&mut self, ids: &[E], mut f: F) -> usize
+    where
+        F: FnMut(E, &mut T),
+    {
+        let prefetch = self.len() >= PREFETCH_MIN_LEN;
+        let mut count = 0;
+        for (position, id) in ids.iter().enumerate() {
+            if prefetch {
+                self.prefetch_ahead(ids, position);
+            }
+            if let Some(index) = self.get_index(*id) {
+                // Safety
+                // The index stored in sparse is always in range
+                f(*id, unsafe { self.data.get_unchecked_mut(index) });
+                count += 1;
+            }
+        }
+        count
     }
 
-    /// Get the slice of data
-    pub fn data(&self) -> &[T] {
-        &self.data
-    }
+    /// Call `f(&mut data, &update)` for every `(id, update)` pair of `ids` and `updates`
+    /// whose id is in sparse set
+    /// # Details
+    /// The pairs whose id is not in sparse set are skipped
+    /// # Returns
+    /// Return the count of calls to `f`
+    /// # Panics
+    /// Panic if `ids.len() != updates.len()`
+    pub fn apply_slice_by_ids<U, F>(&mut self, ids: &[E], updates: &[U], mut f: F) -> usize
+    where
+        F: FnMut(&mut T, &U),
+    {
+        if ids.len() != updates.len() {
+            panic!("ids.len() != updates.len()")
+        }
+        let mut count = 0;
+        for (id, update) in ids.iter().zip(updates.iter()) {
+            if let Some(dat) = self.get_mut(*id) {
+                f(dat, update);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Call `f(id, &mut data)` for every id in `ids`
+    /// # Details
+    /// Same as `apply_batch`, but every id must be in sparse set.
+    /// All indices are resolved before calling `f`
+    /// # Returns
+    /// Return Err and call nothing if an id is not in sparse set,
+    /// otherwise return the count of calls to `f`
+    pub fn apply_batch_strict<F>(&mut self, ids: &[E], mut f: F) -> Result<usize, MissingId<E>>
+    where
+        F: FnMut(E, &mut T),
+    {
+        let indices = ids
+            .iter()
+            .map(|id| self.get_index(*id).ok_or(MissingId(*id)))
+            .collect::<Result<Vec<usize>, _>>()?;
+        for (id, index) in ids.iter().zip(indices.iter()) {
+            // Safety
+            // The indices come from sparse, they are always in range
+            f(*id, unsafe { self.data.get_unchecked_mut(*index) });
+        }
+        Ok(indices.len())
+    }
+
+    /// Hint the CPU to load the sparse slot of `id` and its data
+    /// # Details
+    /// It is only a hint, nothing is changed.
+    /// The sparse slot is read to find the data, so call it for the ids some iterations ahead
+    /// after prefetching their slots by `SparseStorage::prefetch`.
+    /// It does nothing on targets without a prefetch instruction
+    pub fn prefetch(&self, id: E) {
+        self.sparse.prefetch(id);
+        if let Some(index) = self.sparse.get_index(id) {
+            prefetch_read(self.data.as_ptr().wrapping_add(index.get() - 1));
+        }
+    }
+
+    /// Prefetch the sparse slot of `ids[position + PREFETCH_DISTANCE]`
+    fn prefetch_ahead(&self, ids: &[E], position: usize) {
+        if let Some(id) = ids.get(position + PREFETCH_DISTANCE) {
+            self.sparse.prefetch(*id);
+        }
+    }
+
+    /// Get the MUTABLE references of data by given dense indices at once
+    /// # Returns
+    /// Return None if any index is out of range or any 2 indices are equal
+    pub fn get_disjoint_mut_by_index<const N: usize>(
+        &mut self,
+        indices: [usize; N],
+    ) -> Option<[&mut T; N]> {
+        for (i, index) in indices.iter().enumerate() {
+            if *index >= self.len() || indices[..i].contains(index) {
+                return None;
+            }
+        }
+        let ptr = self.data.as_mut_ptr();
+        // Safety
+        // All indices are in range and different from each other,
+        // so the references never overlap
+        Some(indices.map(|index| unsafe { &mut *ptr.add(index) }))
+    }
+
+    /// Get the index the entity would have if the data were stably sorted by `key_fn`
+    /// # Details
+    /// Nothing is sorted, it counts the data with smaller keys in O(n)
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn would_be_index_after_sort_by<K, F>(&self, id: E, mut key_fn: F) -> Option<usize>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        let index = self.get_index(id)?;
+        // Safety
+        // The index stored in sparse is always in range
+        let key = key_fn(unsafe { self.data.get_unchecked(index) });
+        let count = self
+            .data
+            .iter()
+            .enumerate()
+            .filter(|(other_index, dat)| match key_fn(dat).cmp(&key) {
+                std::cmp::Ordering::Less => true,
+                // stable sort keeps the order of equal keys
+                std::cmp::Ordering::Equal => *other_index < index,
+                std::cmp::Ordering::Greater => false,
+            })
+            .count();
+        Some(count)
+    }
+
+    /// Get the index of the entity was given by `id` in sparse set
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn get_index(&self, id: E) -> Option<usize> {
+        self.sparse.get_index(id).map(|x| x.get() - 1)
+    }
+
+    /// Copy the ids into a new sparse set without data
+    /// # Details
+    /// The dense order is kept, so the indices are the same as this sparse set
+    pub fn to_id_set(&self) -> SparseSetVec<E, ()>
+    where
+        E: Into<usize>,
+    {
+        let mut ids = self.dense.clone();
+        let mut data = vec![(); ids.len()];
+        let mut id_set = SparseSetVec::default();
+        id_set.insert_batch(&mut ids, &mut data);
+        id_set
+    }
+
+    /// Count the ids in each bucket of `bucket_size` contiguous ids
+    /// # Details
+    /// Bucket `i` counts the ids in `i * bucket_size..(i + 1) * bucket_size`.  
+    /// It helps to choose the storage, clustered ids suit `VecStorage`
+    /// while scattered ids suit a map.
+    /// The ids are scanned once
+    /// # Returns
+    /// Return the counts up to the bucket of the greatest id, it is empty if sparse set is empty.  
+    /// Return Err if it would need more than 2^24 buckets,
+    /// use `id_histogram_sparse` for scattered ids
+    /// # Panics
+    /// Panic if `bucket_size` is 0
+    pub fn id_histogram(&self, bucket_size: usize) -> Result<Vec<usize>, HistogramError>
+    where
+        E: TryInto<usize>,
+    {
+        if bucket_size == 0 {
+            panic!("bucket_size must not be 0");
+        }
+        let mut histogram = Vec::new();
+        for id in &self.dense {
+            let id: usize = (*id).try_into().map_err(|_| HistogramError::IdOverflow)?;
+            let bucket = id / bucket_size;
+            if bucket >= ID_HISTOGRAM_MAX_BUCKETS {
+                return Err(HistogramError::TooManyBuckets(ID_HISTOGRAM_MAX_BUCKETS));
+            }
+            if bucket >= histogram.len() {
+                histogram.resize(bucket + 1, 0);
+            }
+            histogram[bucket] += 1;
+        }
+        Ok(histogram)
+    }
+
+    /// Count the ids in each non-empty bucket of `bucket_size` contiguous ids
+    /// # Details
+    /// Like `id_histogram`, but the empty buckets are left out,
+    /// so it is cheap however large the ids are.
+    /// The ids are scanned once
+    /// # Returns
+    /// Return `(bucket, count)` in ascending order of bucket.  
+    /// Return Err if an id can't be converted into `usize`
+    /// # Panics
+    /// Panic if `bucket_size` is 0
+    pub fn id_histogram_sparse(
+        &self,
+        bucket_size: usize,
+    ) -> Result<Vec<(usize, usize)>, HistogramError>
+    where
+        E: TryInto<usize>,
+    {
+        if bucket_size == 0 {
+            panic!("bucket_size must not be 0");
+        }
+        let mut histogram = BTreeMap::new();
+        for id in &self.dense {
+            let id: usize = (*id).try_into().map_err(|_| HistogramError::IdOverflow)?;
+            *histogram.entry(id / bucket_size).or_insert(0) += 1;
+        }
+        Ok(histogram.into_iter().collect())
+    }
+
+    /// Get the raw value stored in the sparse storage for `id`
+    /// # Details
+    /// The value is the 1-based index, it is only for debugging
+    /// and checking custom `SparseStorage` implementations.  
+    /// Use `get_index` in application code
+    pub fn raw_sparse_index(&self, id: E) -> Option<NonZeroUsize> {
+        self.sparse.get_index(id)
+    }
+
+    /// Get the Id from index
+    /// # Return
+    /// Return None if index is not valid
+    pub fn get_id(&self, index: usize) -> Option<E> {
+        self.dense.get(index).copied()
+    }
+
+    /// Get the reference of data by dense `index`
+    /// # Return
+    /// Return None if index is not valid
+    pub fn get_by_index(&self, index: usize) -> Option<&T> {
+        self.data.get(index)
+    }
+
+    /// Get the MUTABLE reference of data by dense `index`
+    /// # Return
+    /// Return None if index is not valid
+    pub fn get_mut_by_index(&mut self, index: usize) -> Option<&mut T> {
+        self.data.get_mut(index)
+    }
+
+    /// Suggest the smallest id that is not in sparse set
+    /// # Details
+    /// Allocating ids by it keeps `VecStorage` compact.  
+    /// Slots that can't be converted into an id (like 0 for `NonZeroUsize`) are skipped
+    /// # Returns
+    /// Return None if the storage can't answer
+    pub fn suggest_free_id(&self) -> Option<E>
+    where
+        E: TryFrom<usize>,
+    {
+        let mut start = 0;
+        loop {
+            let slot = self.sparse.first_vacant_slot(start)?;
+            match E::try_from(slot) {
+                Ok(id) => return Some(id),
+                Err(_) if slot == 0 => start = 1,
+                Err(_) => return None,
+            }
+        }
+    }
+
+    /// Get the Id from index
+    /// # Returns
+    /// Return Err if index is out of range
+    pub fn try_get_id(&self, index: usize) -> Result<E, IndexOutOfRange> {
+        self.check_index(index)?;
+        // Safety
+        // The index is checked above
+        Ok(unsafe { *self.dense.get_unchecked(index) })
+    }
+
+    /// Append the ids at dense `indices` to `out`
+    /// # Returns
+    /// Return Err and append nothing if an index is out of range
+    pub fn ids_for_indices(
+        &self,
+        indices: &[usize],
+        out: &mut Vec<E>,
+    ) -> Result<(), IndexOutOfRange> {
+        for index in indices {
+            self.check_index(*index)?;
+        }
+        // Safety
+        // The indices are checked above
+        unsafe { self.ids_for_indices_unchecked(indices, out) };
+        Ok(())
+    }
+
+    /// Append the ids at dense `indices` to `out` without bounds checking
+    /// # Safety
+    /// Safe only every index in `indices` is less than `self.len()`
+    pub unsafe fn ids_for_indices_unchecked(&self, indices: &[usize], out: &mut Vec<E>) {
+        out.reserve(indices.len());
+        out.extend(
+            indices
+                .iter()
+                .map(|index| *self.dense.get_unchecked(*index)),
+        );
+    }
+
+    /// Get the slice of data
+    pub fn data(&self) -> &[T] {
+        &self.data
+    }
+
+    /// Get the slice of data
+    pub fn data_mut(&mut self) -> &mut [T] {
+        &mut self.data
+    }
+
+    /// Get the dense index of the entity with the greatest `f(id, &data)`
+    /// # Details
+    /// It returns the first one in dense order if several keys are equal to the greatest,
+    /// unlike `Iterator::max_by_key` returning the last
+    /// # Returns
+    /// Return None if sparse set is empty
+    pub fn position_max_by_key<K, F>(&self, mut f: F) -> Option<usize>
+    where
+        K: Ord,
+        F: FnMut(E, &T) -> K,
+    {
+        self.position_by_key(|key, best| key > best, &mut f)
+    }
+
+    /// Get the dense index of the entity with the least `f(id, &data)`
+    /// # Details
+    /// It returns the first one in dense order if several keys are equal to the least
+    /// # Returns
+    /// Return None if sparse set is empty
+    pub fn position_min_by_key<K, F>(&self, mut f: F) -> Option<usize>
+    where
+        K: Ord,
+        F: FnMut(E, &T) -> K,
+    {
+        self.position_by_key(|key, best| key < best, &mut f)
+    }
+
+    /// Get the entity with the greatest `f(id, &data)`
+    /// # Details
+    /// It returns the first one in dense order if several keys are equal to the greatest
+    /// # Returns
+    /// Return None if sparse set is empty
+    pub fn max_by_key<K, F>(&self, f: F) -> Option<(E, &T)>
+    where
+        K: Ord,
+        F: FnMut(E, &T) -> K,
+    {
+        let index = self.position_max_by_key(f)?;
+        Some((self.dense[index], &self.data[index]))
+    }
+
+    /// Get the entity with the least `f(id, &data)`
+    /// # Details
+    /// It returns the first one in dense order if several keys are equal to the least
+    /// # Returns
+    /// Return None if sparse set is empty
+    pub fn min_by_key<K, F>(&self, f: F) -> Option<(E, &T)>
+    where
+        K: Ord,
+        F: FnMut(E, &T) -> K,
+    {
+        let index = self.position_min_by_key(f)?;
+        Some((self.dense[index], &self.data[index]))
+    }
+
+    /// Get the dense index of the first key that no later key `replaces`
+    fn position_by_key<K, F>(&self, replaces: fn(&K, &K) -> bool, f: &mut F) -> Option<usize>
+    where
+        F: FnMut(E, &T) -> K,
+    {
+        let mut pairs = self.dense.iter().zip(self.data.iter()).enumerate();
+        let (_, (id, dat)) = pairs.next()?;
+        let mut best = (0, f(*id, dat));
+        for (index, (id, dat)) in pairs {
+            let key = f(*id, dat);
+            if replaces(&key, &best.1) {
+                best = (index, key);
+            }
+        }
+        Some(best.0)
+    }
+
+    /// Iterate the data in chunks filling a cache line
+    /// # Details
+    /// Every chunk has `max(1, 64 / size_of::<T>())` elements except the last one.
+    /// The chunks only fill whole cache lines if the data slice starts on a cache line
+    pub fn cache_line_chunks(&self) -> impl Iterator<Item = &[T]> {
+        let chunk_size = (CACHE_LINE_SIZE / std::mem::size_of::<T>().max(1)).max(1);
+        self.data.chunks(chunk_size)
+    }
+
+    /// Get the slice of ID , or the dense array
+    /// # Details
+    /// There is NO any `fn ids_mut(&self)` in this lib.  
+    /// Because the mapping relations between the sparse and the dense is ensured by this lib.  
+    /// Leaking the mutable slice of dense is unsafe and will cause some unexpected error
+    pub fn ids(&self) -> &[E] {
+        &self.dense
+    }
+
+    /// Iterate `(id, &data, Option<&other_data>)` of every entity in this sparse set
+    /// # Details
+    /// `other_data` is the data of the same id in `other`, or None if `other` doesn't have it
+    pub fn iter_with_optional<'a, T2, S2>(
+        &'a self,
+        other: &'a SparseSet<E, T2, S2>,
+    ) -> impl Iterator<Item = (E, &'a T, Option<&'a T2>)> + 'a
+    where
+        S2: SparseStorage<EntityId = E>,
+    {
+        self.iter().map(move |(id, dat)| (id, dat, other.get(id)))
+    }
+
+    /// Iterate `(id, &data)` pairs in ascending order of `f(&data)`
+    /// # Details
+    /// The sparse set is not reordered, a temporary permutation of dense indices is sorted instead.  
+    /// The sort is stable, pairs with equal keys keep the dense order
+    pub fn iter_sorted_by_key<'a, K, F>(&'a self, mut f: F) -> impl Iterator<Item = (E, &'a T)> + 'a
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+    {
+        let mut permutation: Vec<usize> = (0..self.len()).collect();
+        // Safety
+        // The indices in permutation are always in range
+        permutation.sort_by_key(|index| f(unsafe { self.data.get_unchecked(*index) }));
+        permutation.into_iter().map(move |index| unsafe {
+            (
+                *self.dense.get_unchecked(index),
+                self.data.get_unchecked(index),
+            )
+        })
+    }
+
+    /// Get the first dense index whose data `pred` returns false
+    /// # Details
+    /// It binary searches the data, so it is only meaningful
+    /// if `pred` returns true for a prefix of the dense order and false for the rest,
+    /// like when the data is sorted by the key `pred` compares
+    pub fn partition_point_by<F>(&self, pred: F) -> usize
+    where
+        F: FnMut(&T) -> bool,
+    {
+        self.data.partition_point(pred)
+    }
+
+    /// Iterate `(id, &data)` pairs whose `key_fn(&data)` is in `range`
+    /// # Details
+    /// It binary searches the bounds of the run, so it is only meaningful
+    /// if the dense order is sorted by `key_fn`, which is checked in debug builds
+    pub fn range_by_key<K, F, R>(&self, mut key_fn: F, range: R) -> Iter<'_, E, T>
+    where
+        K: Ord,
+        F: FnMut(&T) -> K,
+        R: RangeBounds<K>,
+    {
+        debug_assert!(
+            self.data.is_sorted_by_key(&mut key_fn),
+            "the data is not sorted by the key"
+        );
+        let start = match range.start_bound() {
+            Bound::Included(start) => self.partition_point_by(|dat| key_fn(dat) < *start),
+            Bound::Excluded(start) => self.partition_point_by(|dat| key_fn(dat) <= *start),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(end) => self.partition_point_by(|dat| key_fn(dat) <= *end),
+            Bound::Excluded(end) => self.partition_point_by(|dat| key_fn(dat) < *end),
+            Bound::Unbounded => self.len(),
+        }
+        .max(start);
+        Iter {
+            ids: self.dense[start..end].iter(),
+            data: self.data[start..end].iter(),
+        }
+    }
+
+    /// Split the ids and the data at dense index `mid`
+    /// # Details
+    /// The 2 halves are aligned and disjoint, so they can be handed to 2 threads
+    /// # Panics
+    /// Panic if `mid > self.len()`
+    #[allow(clippy::type_complexity)]
+    pub fn split_data_at_mut(&mut self, mid: usize) -> ((&[E], &mut [T]), (&[E], &mut [T])) {
+        if mid > self.len() {
+            panic!("{}mid={} is out of range", self.name_prefix(), mid);
+        }
+        let (ids_a, ids_b) = self.dense.split_at(mid);
+        let (data_a, data_b) = self.data.split_at_mut(mid);
+        ((ids_a, data_a), (ids_b, data_b))
+    }
+
+    /// Split the ids and the data at dense index `mid`
+    /// # Returns
+    /// Return Err if `mid > self.len()`
+    #[allow(clippy::type_complexity)]
+    pub fn try_split_data_at_mut(
+        &mut self,
+        mid: usize,
+    ) -> Result<((&[E], &mut [T]), (&[E], &mut [T])), IndexOutOfRange> {
+        if mid > self.len() {
+            return Err(IndexOutOfRange {
+                index: mid,
+                len: self.len(),
+            });
+        }
+        Ok(self.split_data_at_mut(mid))
+    }
+
+    /// Iterate `(id, &data)` pairs in dense order
+    /// # Details
+    /// The iterator is double-ended.  
+    /// Use `iter().rev()` to visit from the back of the dense array
+    pub fn iter(&self) -> Iter<'_, E, T> {
+        Iter {
+            ids: self.dense.iter(),
+            data: self.data.iter(),
+        }
+    }
+
+    /// Iterate `(id, &mut data)` pairs in dense order
+    /// # Details
+    /// The iterator is double-ended like `iter`.  
+    /// The ids are copied out, only the data is borrowed mutably,
+    /// so the mapping between sparse and dense can't be changed through it.
+    /// It is also `for (id, data) in &mut sparse_set`
+    pub fn iter_mut(&mut self) -> IterMut<'_, E, T> {
+        IterMut {
+            ids: self.dense.iter(),
+            data: self.data.iter_mut(),
+        }
+    }
+
+    /// Iterate `(id, &external[index])` pairs in dense order,
+    /// for a column kept outside of sparse set and aligned to the dense array
+    /// # Details
+    /// The caller must move the column with the entities,
+    /// like swap-removing it by the same index
+    /// # Panics
+    /// Panic if `external.len() != self.len()`
+    pub fn gather_by_index<'a, U>(
+        &'a self,
+        external: &'a [U],
+    ) -> impl ExactSizeIterator<Item = (E, &'a U)> + DoubleEndedIterator + 'a {
+        if external.len() != self.len() {
+            panic!(
+                "{}external.len()={} != len()={}",
+                self.name_prefix(),
+                external.len(),
+                self.len()
+            );
+        }
+        self.dense.iter().copied().zip(external.iter())
+    }
+}
+
+impl<E, T, S> IntoIterator for SparseSet<E, T, S> {
+    type Item = (E, T);
+    type IntoIter = IntoIter<E, T>;
+
+    /// Move all `(id, data)` pairs out in dense order
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            ids: self.dense.into_iter(),
+            data: self.data.into_iter(),
+        }
+    }
+}
+
+/// Insert every pair like `insert`, a repeated id keeps the last data
+/// # Panics
+/// Panic if it grows past the limits set by `set_limits`
+impl<E, T, S> Extend<(E, T)> for SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    fn extend<I: IntoIterator<Item = (E, T)>>(&mut self, iter: I) {
+        if let Err(error) = self.try_extend(iter) {
+            panic!("{}{}", self.name_prefix(), error);
+        }
+    }
+}
+
+/// Collect pairs like `extend` on an empty sparse set
+impl<E, T, S> FromIterator<(E, T)> for SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn from_iter<I: IntoIterator<Item = (E, T)>>(iter: I) -> Self {
+        let mut sparse_set = SparseSet::default();
+        sparse_set.extend(iter);
+        sparse_set
+    }
+}
+
+impl<'a, E, T, S> IntoIterator for &'a SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    type Item = (E, &'a T);
+    type IntoIter = Iter<'a, E, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, E, T, S> IntoIterator for &'a mut SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    type Item = (E, &'a mut T);
+    type IntoIter = IterMut<'a, E, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Methods walking the slots of `VecStorage` directly
+impl<E, T> SparseSet<E, T, VecStorage<E>>
+where
+    E: Copy + Into<usize>,
+{
+    /// Iterate `(id, &data)` pairs in ascending order of id
+    /// # Details
+    /// It walks the slots of `VecStorage` without allocating,
+    /// so it costs O(slots) instead of sorting the dense array
+    pub fn iter_by_id_asc(&self) -> impl Iterator<Item = (E, &T)> {
+        self.sparse
+            .iter_occupied_ascending()
+            .map(move |(_, index)| {
+                let index = index.get() - 1;
+                // Safety
+                // The index stored in sparse is always in range
+                unsafe {
+                    (
+                        *self.dense.get_unchecked(index),
+                        self.data.get_unchecked(index),
+                    )
+                }
+            })
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + Ord,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Remove all entities whose id is NOT in `range`
+    /// # Details
+    /// Storages keeping ids in order only visit the removed ids,
+    /// others scan the whole dense array
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain_range<R>(&mut self, range: R) -> usize
+    where
+        R: RangeBounds<E>,
+    {
+        if range_is_empty(&range) {
+            let count = self.len();
+            self.clear();
+            return count;
+        }
+        // the complement of a non-empty range is the 2 disjoint ranges below and above it
+        let flip = |bound: Bound<&E>| match bound {
+            Bound::Included(id) => Some(Bound::Excluded(*id)),
+            Bound::Excluded(id) => Some(Bound::Included(*id)),
+            Bound::Unbounded => None,
+        };
+        let below = flip(range.start_bound()).map(|end| (Bound::Unbounded, end));
+        let above = flip(range.end_bound()).map(|start| (start, Bound::Unbounded));
+
+        let mut ids = Vec::new();
+        for side in below.into_iter().chain(above) {
+            match self.sparse.range_ids(side) {
+                Some(mut side_ids) => ids.append(&mut side_ids),
+                None => return self.retain_range_by_scan(range),
+            }
+        }
+        self.remove_ids(&ids)
+    }
+
+    /// Remove all entities whose id is in `range`
+    /// # Details
+    /// Storages keeping ids in order only visit the removed ids,
+    /// others scan the whole dense array
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn remove_range<R>(&mut self, range: R) -> usize
+    where
+        R: RangeBounds<E>,
+    {
+        if range_is_empty(&range) {
+            return 0;
+        }
+        if let Some(ids) = self
+            .sparse
+            .range_ids((range.start_bound(), range.end_bound()))
+        {
+            return self.remove_ids(&ids);
+        }
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let id = unsafe { self.dense.get_unchecked(index) };
+            if range.contains(id) {
+                self.swap_remove_by_index(index);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Iterate `(id, &data)` pairs whose id is in `start..end` in ascending order of id
+    /// # Details
+    /// Storages keeping ids in order only visit the ids in range,
+    /// others scan and sort the whole dense array
+    pub fn range_ids(&self, start: E, end: E) -> impl Iterator<Item = (E, &T)> + '_ {
+        let indices: Vec<(E, usize)> = if start >= end {
+            Vec::new()
+        } else if let Some(ids) = self.sparse.range_ids(start..end) {
+            ids.into_iter()
+                .filter_map(|id| Some((id, self.get_index(id)?)))
+                .collect()
+        } else {
+            let mut indices: Vec<(E, usize)> = self
+                .dense
+                .iter()
+                .enumerate()
+                .filter(|(_, id)| (start..end).contains(*id))
+                .map(|(index, id)| (*id, index))
+                .collect();
+            indices.sort_unstable_by_key(|(id, _)| *id);
+            indices
+        };
+        // Safety
+        // The indices come from sparse or enumerate of dense, they are always in range
+        indices
+            .into_iter()
+            .map(move |(id, index)| (id, unsafe { self.data.get_unchecked(index) }))
+    }
+
+    fn retain_range_by_scan<R>(&mut self, range: R) -> usize
+    where
+        R: RangeBounds<E>,
+    {
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let id = unsafe { self.dense.get_unchecked(index) };
+            if !range.contains(id) {
+                self.swap_remove_by_index(index);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    fn remove_ids(&mut self, ids: &[E]) -> usize {
+        for id in ids {
+            self.swap_remove_by_id(*id);
+        }
+        ids.len()
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + Ord,
+    S: OrderedSparseStorage<EntityId = E>,
+{
+    /// Count the entities whose id is in `range`
+    /// # Returns
+    /// Return 0 if `range` is empty or inverted
+    pub fn count_in_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<E>,
+    {
+        if range_is_empty(&range) {
+            return 0;
+        }
+        self.sparse.count_in_range(range)
+    }
+
+    /// Iterate the ids in `range` in ascending order
+    /// # Details
+    /// It yields nothing if `range` is empty or inverted
+    pub fn ids_in_range<R>(&self, range: R) -> S::RangeIter<'_>
+    where
+        R: RangeBounds<E>,
+    {
+        if range_is_empty(&range) {
+            // an empty range has both bounds,
+            // `start..start` keeps the storage from panicking
+            let start = match range.start_bound() {
+                Bound::Included(start) | Bound::Excluded(start) => *start,
+                Bound::Unbounded => unreachable!(),
+            };
+            return self
+                .sparse
+                .ids_in_range((Bound::Included(start), Bound::Excluded(start)));
+        }
+        self.sparse.ids_in_range(range)
+    }
+}
+
+/// How many ids ahead `apply_batch` prefetches the sparse slot
+const PREFETCH_DISTANCE: usize = 8;
+/// The count of entities from which `apply_batch` prefetches,
+/// smaller sets are likely in cache and prefetching only costs
+const PREFETCH_MIN_LEN: usize = 1 << 16;
+/// The size of a cache line in bytes assumed by `cache_line_chunks`
+const CACHE_LINE_SIZE: usize = 64;
+/// The count of buckets `id_histogram` allocates at most
+const ID_HISTOGRAM_MAX_BUCKETS: usize = 1 << 24;
+
+/// Check if no id is in `range`, including inverted ranges
+/// # Details
+/// Ordered storages like `BTreeMap` panic on such ranges, so they are handled before
+fn range_is_empty<E, R>(range: &R) -> bool
+where
+    E: Ord,
+    R: RangeBounds<E>,
+{
+    match (range.start_bound(), range.end_bound()) {
+        (Bound::Included(start), Bound::Included(end)) => start > end,
+        (Bound::Included(start), Bound::Excluded(end))
+        | (Bound::Excluded(start), Bound::Included(end))
+        | (Bound::Excluded(start), Bound::Excluded(end)) => start >= end,
+        _ => false,
+    }
+}
+
+/// Print `sparse set "name" of T: ` if the sparse set is named, otherwise nothing
+struct NamePrefix<'a>(Option<&'a str>, &'static str);
+
+impl std::fmt::Display for NamePrefix<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Some(name) => write!(f, "sparse set {:?} of {}: ", name, self.1),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Get the index stored in sparse for the first of `additional` entities
+/// pushed after `len` entities
+/// # Panics
+/// Panic if the last index would overflow `usize`
+pub(crate) fn first_new_index(len: usize, additional: usize) -> NonZeroUsize {
+    len.checked_add(additional.max(1))
+        .and_then(|_| NonZeroUsize::new(len + 1))
+        .unwrap_or_else(|| {
+            panic!(
+                "sparse set overflows: len={} + additional={} exceeds usize::MAX",
+                len, additional
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{BTreeMap, BTreeSet, HashMap},
+        num::NonZeroUsize,
+        ops::Bound,
+    };
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{
+        first_new_index, sparse_storage::VecStorage, Either, HistogramError, IndexOutOfRange,
+        InvalidSparseSet, InvariantError, Iter, LimitExceeded, MissingId, OrderError, PairSlice,
+        SparseSet, SparseSetBTreeMap, SparseSetHashMap, SparseSetLimits, SparseSetSmall,
+        SparseSetVec, SparseSetView, SparseStorage,
+    };
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn interface_test() {
+        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
+
+        assert_eq!(sparse_set.len(), 0);
+        assert!(sparse_set.is_empty());
+        assert!(sparse_set.data().is_empty());
+        assert!(sparse_set.ids().is_empty());
+
+        let id = NonZeroUsize::new(124).unwrap();
+
+        assert_eq!(sparse_set.swap_remove_by_id(id), None);
+        assert!(!sparse_set.contains(id));
+        assert_eq!(sparse_set.len(), 0);
+        assert!(sparse_set.is_empty());
+        assert!(sparse_set.data().is_empty());
+        assert!(sparse_set.ids().is_empty());
+
+        // insert
+        assert_eq!(sparse_set.insert(id, 'c'), None);
+
+        assert_eq!(sparse_set.len(), 1);
+        assert!(!sparse_set.is_empty());
+        assert_eq!(sparse_set.get(id).copied(), Some('c'));
+        assert!(sparse_set.contains(id));
+        assert_eq!(sparse_set.data(), &['c']);
+        assert_eq!(sparse_set.ids(), &[id]);
+
+        // insert again to change the value
+        assert_eq!(sparse_set.insert(id, 'b'), Some('c'));
+
+        assert_eq!(sparse_set.len(), 1);
+        assert!(!sparse_set.is_empty());
+        assert_eq!(sparse_set.get(id).copied(), Some('b'));
+        assert!(sparse_set.contains(id));
+        assert_eq!(sparse_set.data(), &['b']);
+        assert_eq!(sparse_set.ids(), &[id]);
+
+        // remove this one
+        assert_eq!(sparse_set.swap_remove_by_id(id), Some('b'));
+
+        assert!(!sparse_set.contains(id));
+        assert_eq!(sparse_set.len(), 0);
+        assert!(sparse_set.is_empty());
+        assert!(sparse_set.data().is_empty());
+        assert!(sparse_set.ids().is_empty());
+
+        // remove twice
+        assert_eq!(sparse_set.swap_remove_by_id(id), None);
+
+        assert!(!sparse_set.contains(id));
+        assert_eq!(sparse_set.len(), 0);
+        assert!(sparse_set.is_empty());
+        assert!(sparse_set.data().is_empty());
+        assert!(sparse_set.ids().is_empty());
+
+        // generate a lot of ids'
+        let mut rng = thread_rng();
+        let count = 100000;
+        // generate unique id
+        let ids = std::iter::from_fn(move || {
+            Some((rng.gen_range(1000..100000), rng.gen_range('a'..='z')))
+        })
+        .map(|(x, c)| (NonZeroUsize::new(x).unwrap(), c))
+        .take(count);
+
+        for (id, c) in ids {
+            sparse_set.insert(id, c);
+            assert!(sparse_set.contains(id));
+            assert_eq!(sparse_set.get(id).copied(), Some(c));
+        }
+    }
+
+    #[test]
+    fn batch_test() {
+        let mut rng = rand::thread_rng();
+        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
+        let mut set = BTreeSet::new();
+
+        let mut ids = Vec::new();
+        let mut data = Vec::new();
+
+        let count = 100_000;
+        for _ in 0..count {
+            'gen_data: loop {
+                let id = rng.gen_range(1..100_000_000);
+                if !set.contains(&id) {
+                    set.insert(id);
+                    let id = EntityId::new(id).unwrap();
+                    let d = rng.gen_range('a'..='z');
+
+                    ids.push(id);
+                    data.push(d);
+                    break 'gen_data;
+                }
+            }
+        }
+
+        let mut ids_in = ids.clone();
+        let mut data_in = data.clone();
+        sparse_set.insert_batch(&mut ids_in, &mut data_in);
+
+        assert_eq!(data.len(), sparse_set.len());
+        assert_eq!(&data, sparse_set.data());
+
+        for (id, data) in ids.iter().zip(data.iter()) {
+            let ch = sparse_set.get(*id);
+            assert!(ch.is_some());
+            assert_eq!(data.clone(), ch.copied().unwrap());
+        }
+    }
+
+    #[test]
+    fn iter_rev_test() {
+        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
+        for (x, c) in (1..=26).zip('a'..='z') {
+            sparse_set.insert(EntityId::new(x).unwrap(), c);
+        }
+
+        let mut forward: Vec<_> = sparse_set.iter().collect();
+        forward.reverse();
+        let backward: Vec<_> = sparse_set.iter().rev().collect();
+        assert_eq!(forward, backward);
+        assert_eq!(sparse_set.iter().len(), 26);
+
+        // removing from the back does not disturb the unvisited elements
+        for index in (0..sparse_set.len()).rev() {
+            if sparse_set.data()[index] < 'n' {
+                sparse_set.swap_remove_by_index(index);
+            }
+        }
+        assert_eq!(sparse_set.len(), 13);
+        assert!(sparse_set.iter().all(|(_, c)| *c >= 'n'));
+    }
+
+    #[test]
+    fn range_test() {
+        let mut sparse_set: SparseSetBTreeMap<u32, char> = SparseSet::default();
+        for (x, c) in (10..20).zip('a'..) {
+            sparse_set.insert(x * 2, c);
+        }
+
+        assert_eq!(sparse_set.count_in_range(..), 10);
+        assert_eq!(sparse_set.count_in_range(20..24), 2);
+        assert_eq!(sparse_set.count_in_range(20..=24), 3);
+        assert_eq!(
+            sparse_set.count_in_range((Bound::Excluded(20), Bound::Included(24))),
+            2
+        );
+        assert_eq!(sparse_set.count_in_range(..=20), 1);
+        assert_eq!(sparse_set.count_in_range(39..), 0);
+        assert_eq!(sparse_set.count_in_range(21..22), 0);
+        assert_eq!(sparse_set.count_in_range(24..24), 0);
+
+        assert_eq!(
+            sparse_set.ids_in_range(33..).collect::<Vec<_>>(),
+            vec![34, 36, 38]
+        );
+        assert_eq!(sparse_set.ids_in_range(..0).next(), None);
+
+        // inverted ranges and equal excluded bounds are empty
+        let inverted = (Bound::Included(30), Bound::Excluded(24));
+        assert_eq!(sparse_set.count_in_range(inverted), 0);
+        assert_eq!(sparse_set.ids_in_range(inverted).next(), None);
+        assert_eq!(
+            sparse_set.count_in_range((Bound::Included(30), Bound::Included(24))),
+            0
+        );
+        let excluded = (Bound::Excluded(24), Bound::Excluded(24));
+        assert_eq!(sparse_set.count_in_range(excluded), 0);
+        assert_eq!(sparse_set.ids_in_range(excluded).next(), None);
+        assert_eq!(
+            sparse_set.count_in_range((Bound::Excluded(30), Bound::Excluded(24))),
+            0
+        );
+    }
+
+    #[test]
+    fn repair_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
+        for (x, c) in (1..=5).zip('a'..) {
+            sparse_set.insert(id(x), c);
+        }
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert!(sparse_set.clone().repair().is_clean());
+
+        // length mismatch
+        let (sparse, dense, mut data) = sparse_set.clone().into_raw_parts();
+        data.push('z');
+        let mut broken = unsafe { SparseSet::from_raw_parts(sparse, dense, data) };
+        assert!(matches!(
+            broken.check_invariants(),
+            Err(InvalidSparseSet {
+                error: InvariantError::LengthMismatch {
+                    dense_len: 5,
+                    data_len: 6
+                },
+                ..
+            })
+        ));
+        let report = broken.repair();
+        assert_eq!(report.truncated, 1);
+        assert_eq!(report.unrecoverable, None);
+        assert_eq!(broken.check_invariants(), Ok(()));
+        assert_eq!(broken.data(), sparse_set.data());
+
+        // duplicated id
+        let (sparse, mut dense, mut data) = sparse_set.clone().into_raw_parts();
+        dense.push(id(2));
+        data.push('y');
+        let mut broken = unsafe { SparseSet::from_raw_parts(sparse, dense, data) };
+        assert!(matches!(
+            broken.check_invariants(),
+            Err(InvalidSparseSet {
+                error: InvariantError::DuplicateId {
+                    index: 5,
+                    other_index: 1
+                },
+                ..
+            })
+        ));
+        let report = broken.repair();
+        assert_eq!(report.duplicates_removed, 1);
+        assert_eq!(report.unrecoverable, None);
+        assert_eq!(broken.check_invariants(), Ok(()));
+        assert_eq!(broken.get(id(2)), Some(&'b'));
+
+        // stale and wrong entries in sparse
+        let (mut sparse, dense, data) = sparse_set.clone().into_raw_parts();
+        sparse.set_index(id(99), NonZeroUsize::new(1));
+        sparse.swap(id(1), id(3));
+        let mut broken = unsafe { SparseSet::from_raw_parts(sparse, dense, data) };
+        assert!(matches!(
+            broken.check_invariants(),
+            Err(InvalidSparseSet {
+                error: InvariantError::IndexMismatch { index: 0, .. },
+                ..
+            })
+        ));
+        let report = broken.repair();
+        assert_eq!(report.remapped, 2);
+        assert_eq!(report.unrecoverable, None);
+        assert_eq!(broken.check_invariants(), Ok(()));
+        assert!(!broken.contains(id(99)));
+        assert_eq!(broken.get(id(1)), Some(&'a'));
+        assert_eq!(broken.get(id(3)), Some(&'c'));
+
+        // the error names the sparse set
+        let (sparse, dense, mut data) = sparse_set.clone().into_raw_parts();
+        data.pop();
+        let mut broken = unsafe { SparseSet::from_raw_parts(sparse, dense, data) };
+        let error = broken.check_invariants().unwrap_err();
+        assert_eq!(error.name, None);
+        assert_eq!(error.to_string(), "dense.len()=5 != data.len()=4");
+        broken.set_debug_name("positions");
+        let error = broken.check_invariants().unwrap_err();
+        assert_eq!(error.name.as_deref(), Some("positions"));
+        assert_eq!(error.data_type, "char");
+        assert_eq!(
+            error.to_string(),
+            "sparse set \"positions\" of char: dense.len()=5 != data.len()=4"
+        );
+        assert_eq!(
+            error.error,
+            InvariantError::LengthMismatch {
+                dense_len: 5,
+                data_len: 4
+            }
+        );
+    }
+
+    #[test]
+    fn retain_range_test() {
+        fn check<S>(mut sparse_set: SparseSet<EntityId, u32, S>)
+        where
+            S: SparseStorage<EntityId = EntityId> + Clone,
+        {
+            let mut rng = thread_rng();
+            let mut model = BTreeMap::new();
+            for _ in 0..1000 {
+                let id = EntityId::new(rng.gen_range(1..2000)).unwrap();
+                let x = rng.gen();
+                sparse_set.insert(id, x);
+                model.insert(id, x);
+            }
+            let id = |x| EntityId::new(x).unwrap();
+
+            let mut retained = sparse_set.clone();
+            let removed = retained.retain_range(id(500)..id(1500));
+            let expected: BTreeMap<_, _> = model
+                .iter()
+                .filter(|(k, _)| (id(500)..id(1500)).contains(*k))
+                .map(|(k, v)| (*k, *v))
+                .collect();
+            assert_eq!(removed, model.len() - expected.len());
+            assert_eq!(retained.len(), expected.len());
+            for (k, v) in &expected {
+                assert_eq!(retained.get(*k), Some(v));
+            }
+
+            let mut retained = sparse_set.clone();
+            retained.retain_range(..=id(100));
+            assert!(retained.ids().iter().all(|x| *x <= id(100)));
+            assert_eq!(retained.len(), model.range(..=id(100)).count());
+
+            let mut removed_set = sparse_set.clone();
+            let removed = removed_set.remove_range(id(500)..=id(1500));
+            assert_eq!(removed, model.range(id(500)..=id(1500)).count());
+            for (k, v) in &model {
+                if (id(500)..=id(1500)).contains(k) {
+                    assert!(!removed_set.contains(*k));
+                } else {
+                    assert_eq!(removed_set.get(*k), Some(v));
+                }
+            }
+
+            assert_eq!(sparse_set.clone().remove_range(..), model.len());
+            assert_eq!(sparse_set.clone().retain_range(..), 0);
+
+            // empty and inverted ranges
+            let empty = (Bound::Excluded(id(4)), Bound::Excluded(id(4)));
+            assert_eq!(sparse_set.clone().remove_range(id(5)..id(3)), 0);
+            assert_eq!(sparse_set.clone().remove_range(empty), 0);
+            assert_eq!(sparse_set.clone().remove_range(id(4)..id(4)), 0);
+            for range in [
+                (Bound::Included(id(500)), Bound::Excluded(id(300))),
+                empty,
+                (Bound::Included(id(4)), Bound::Excluded(id(4))),
+            ] {
+                let mut retained = sparse_set.clone();
+                assert_eq!(retained.retain_range(range), model.len());
+                assert!(retained.is_empty());
+                assert_eq!(retained.check_invariants(), Ok(()));
+            }
+        }
+
+        check(SparseSetVec::default());
+        check(SparseSetHashMap::default());
+        check(SparseSetBTreeMap::default());
+    }
+
+    #[test]
+    fn suggest_free_id_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let hash_set: SparseSetHashMap<EntityId, u32> = SparseSet::default();
+        assert_eq!(hash_set.suggest_free_id(), None);
+        assert_eq!(sparse_set.suggest_free_id(), EntityId::new(1));
+
+        for round in 0..1000 {
+            let id = sparse_set.suggest_free_id().unwrap();
+            sparse_set.insert(id, round);
+        }
+        for round in 0..10_000 {
+            let index = rng.gen_range(0..sparse_set.len());
+            sparse_set.swap_remove_by_index(index);
+            let id = sparse_set.suggest_free_id().unwrap();
+            assert!(!sparse_set.contains(id));
+            sparse_set.insert(id, round);
+
+            // every allocated id is at most len + 1
+            let max_id = sparse_set.ids().iter().max().map_or(0, |x| x.get());
+            assert!(max_id <= sparse_set.len() + 1);
+        }
+    }
+
+    #[test]
+    fn suggest_free_id_hint_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for id in 1..=1000 {
+            sparse_set.insert(EntityId::new(id).unwrap(), 0);
+        }
+        // slot 0 is never written, the hint still follows the filled slots
+        assert_eq!(sparse_set.sparse.first_vacant_hint(), 1001);
+        assert_eq!(sparse_set.suggest_free_id(), EntityId::new(1001));
+
+        sparse_set.swap_remove_by_id(EntityId::new(500).unwrap());
+        assert_eq!(sparse_set.sparse.first_vacant_hint(), 500);
+        assert_eq!(sparse_set.suggest_free_id(), EntityId::new(500));
+        sparse_set.insert(EntityId::new(500).unwrap(), 0);
+        assert_eq!(sparse_set.sparse.first_vacant_hint(), 1001);
+
+        sparse_set.clear();
+        assert_eq!(sparse_set.sparse.first_vacant_hint(), 1);
+        assert_eq!(sparse_set.suggest_free_id(), EntityId::new(1));
+    }
+
+    #[test]
+    fn view_test() {
+        fn sum(view: SparseSetView<'_, EntityId, u32, VecStorage<EntityId>>) -> u32 {
+            view.iter().map(|(_, x)| *x).sum()
+        }
+
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in 1..=10 {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u32);
+        }
+
+        let view = sparse_set.view();
+        let copied = view;
+        assert_eq!(sum(view), 55);
+        assert_eq!(sum(copied), 55);
+        assert_eq!(view.len(), 10);
+        assert!(view.contains(EntityId::new(3).unwrap()));
+        assert_eq!(view.get(EntityId::new(4).unwrap()), Some(&4));
+        assert_eq!(view.get(EntityId::new(11).unwrap()), None);
+        assert_eq!(view.ids(), sparse_set.ids());
+    }
+
+    #[test]
+    fn split_data_at_mut_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        for x in 1..=1000 {
+            sparse_set.insert(EntityId::new(x).unwrap(), 0);
+        }
+
+        let ((ids_a, data_a), (ids_b, data_b)) = sparse_set.split_data_at_mut(300);
+        assert_eq!(ids_a.len(), 300);
+        assert_eq!(data_b.len(), 700);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for (id, x) in ids_a.iter().zip(data_a.iter_mut()) {
+                    *x = id.get() * 2;
+                }
+            });
+            scope.spawn(|| {
+                for (id, x) in ids_b.iter().zip(data_b.iter_mut()) {
+                    *x = id.get() * 3;
+                }
+            });
+        });
+
+        for (index, (id, x)) in sparse_set.iter().enumerate() {
+            let factor = if index < 300 { 2 } else { 3 };
+            assert_eq!(*x, id.get() * factor);
+        }
+    }
+
+    #[test]
+    fn first_new_index_test() {
+        assert_eq!(first_new_index(0, 0).get(), 1);
+        assert_eq!(first_new_index(0, 10).get(), 1);
+        assert_eq!(first_new_index(41, 1).get(), 42);
+        assert_eq!(first_new_index(usize::MAX - 1, 1).get(), usize::MAX);
+        assert!(std::panic::catch_unwind(|| first_new_index(usize::MAX, 1)).is_err());
+        assert!(std::panic::catch_unwind(|| first_new_index(usize::MAX, 0)).is_err());
+        assert!(std::panic::catch_unwind(|| first_new_index(usize::MAX - 5, 6)).is_err());
+    }
+
+    #[test]
+    fn small_test() {
+        let mut sparse_set: SparseSetSmall<usize, usize, 16> = SparseSet::default();
+        for id in (0..16).rev() {
+            sparse_set.insert(id, id * 10);
+        }
+        sparse_set.swap_remove_by_id(5);
+        assert_eq!(sparse_set.len(), 15);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        for id in 16..100 {
+            sparse_set.insert(id, id * 10);
+        }
+        sparse_set.swap_remove_by_id(50);
+        assert_eq!(sparse_set.len(), 98);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        for id in (0..100).filter(|id| *id != 5 && *id != 50) {
+            assert_eq!(sparse_set.get(id), Some(&(id * 10)));
+        }
+        assert!(!sparse_set.contains(5));
+        assert!(!sparse_set.contains(50));
+    }
+
+    #[test]
+    fn apply_batch_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=10 {
+            sparse_set.insert(id(x), 0);
+        }
+
+        // missing and duplicated ids
+        let touched = sparse_set.apply_batch(&[id(2), id(20), id(2), id(5), id(30)], |id, x| {
+            *x += id.get() as u32
+        });
+        assert_eq!(touched, 3);
+        assert_eq!(sparse_set.get(id(2)), Some(&4));
+        assert_eq!(sparse_set.get(id(5)), Some(&5));
+        assert_eq!(sparse_set.data().iter().sum::<u32>(), 9);
+
+        assert_eq!(
+            sparse_set.apply_batch_strict(&[id(1), id(20), id(3)], |_, x| *x += 1),
+            Err(MissingId(id(20)))
+        );
+        assert_eq!(sparse_set.data().iter().sum::<u32>(), 9);
+        assert_eq!(
+            sparse_set.apply_batch_strict(&[id(1), id(1), id(3)], |_, x| *x += 1),
+            Ok(3)
+        );
+        assert_eq!(sparse_set.get(id(1)), Some(&2));
+        assert_eq!(sparse_set.get(id(3)), Some(&1));
+        assert_eq!(sparse_set.apply_batch(&[], |_, x| *x += 1), 0);
+    }
+
+    #[test]
+    fn range_ids_test() {
+        let mut rng = thread_rng();
+        let mut btree: SparseSetBTreeMap<u16, u32> = SparseSet::default();
+        let mut vec: SparseSetVec<u16, u32> = SparseSet::default();
+        for _ in 0..1000 {
+            let id = rng.gen_range(0..5000);
+            let x = rng.gen();
+            btree.insert(id, x);
+            vec.insert(id, x);
+        }
+
+        for (start, end) in [(0, 5000), (100, 2000), (2500, 2501), (3000, 1000), (7, 7)] {
+            let mut expected: Vec<_> = btree
+                .iter()
+                .filter(|(id, _)| (start..end).contains(id))
+                .collect();
+            expected.sort();
+            let ranged: Vec<_> = btree.range_ids(start, end).collect();
+            assert_eq!(ranged, expected);
+            let ranged: Vec<_> = vec.range_ids(start, end).collect();
+            assert_eq!(ranged, expected);
+        }
+    }
+
+    #[test]
+    fn rotate_test() {
+        let mut sparse_set: SparseSetHashMap<u32, u32> = SparseSet::default();
+        sparse_set.rotate_left(3);
+        for x in 0..5 {
+            sparse_set.insert(x, x * 10);
+        }
+
+        sparse_set.rotate_left(2);
+        assert_eq!(sparse_set.ids(), &[2, 3, 4, 0, 1]);
+        assert_eq!(sparse_set.data(), &[20, 30, 40, 0, 10]);
+        assert_eq!(sparse_set.get_index(0), Some(3));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        sparse_set.rotate_right(2);
+        assert_eq!(sparse_set.ids(), &[0, 1, 2, 3, 4]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // wrapped
+        sparse_set.rotate_right(11);
+        assert_eq!(sparse_set.ids(), &[4, 0, 1, 2, 3]);
+        sparse_set.rotate_left(10);
+        assert_eq!(sparse_set.ids(), &[4, 0, 1, 2, 3]);
+        assert_eq!(sparse_set.get_index(4), Some(0));
+        assert!(sparse_set.iter().all(|(id, x)| *x == id * 10));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn into_pairs_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, u64> = SparseSet::default();
+        for _ in 0..1000 {
+            let id = EntityId::new(rng.gen_range(1..2000)).unwrap();
+            sparse_set.insert(id, rng.gen());
+        }
+        sparse_set.swap_remove_by_index(10);
+
+        let expected: Vec<_> = sparse_set.iter().map(|(id, x)| (id, *x)).collect();
+        let pairs = sparse_set.into_pairs();
+        assert_eq!(pairs, expected);
+
+        let mut id_set: SparseSetVec<EntityId, ()> = SparseSet::default();
+        id_set.insert(EntityId::new(3).unwrap(), ());
+        id_set.insert(EntityId::new(1).unwrap(), ());
+        assert_eq!(
+            id_set.into_pairs(),
+            vec![
+                (EntityId::new(3).unwrap(), ()),
+                (EntityId::new(1).unwrap(), ())
+            ]
+        );
+    }
+
+    #[test]
+    fn intersects_test() {
+        let mut a: SparseSetVec<EntityId, char> = SparseSet::default();
+        let mut b: SparseSetHashMap<EntityId, u32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        assert!(!a.intersects(&b));
+        for x in 1..=100 {
+            a.insert(id(x), 'a');
+        }
+        assert!(!a.intersects(&b));
+
+        // disjoint
+        for x in 101..=110 {
+            b.insert(id(x), x as u32);
+        }
+        assert!(!a.intersects(&b));
+        assert!(!b.intersects(&a));
+
+        // overlapping
+        b.insert(id(50), 50);
+        assert!(a.intersects(&b));
+        assert!(b.intersects(&a));
+        assert!(a.intersects(&a));
+    }
+
+    #[test]
+    fn symmetric_difference_test() {
+        let mut a: SparseSetVec<EntityId, char> = SparseSet::default();
+        let mut b: SparseSetHashMap<EntityId, u32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=6 {
+            a.insert(id(x), 'a');
+        }
+
+        // overlapping
+        for x in 4..=8 {
+            b.insert(id(x), x as u32);
+        }
+        let difference: Vec<_> = a.symmetric_difference(&b).collect();
+        assert_eq!(
+            difference,
+            vec![
+                Either::Left((id(1), &'a')),
+                Either::Left((id(2), &'a')),
+                Either::Left((id(3), &'a')),
+                Either::Right((id(7), &7)),
+                Either::Right((id(8), &8)),
+            ]
+        );
+
+        // identical
+        assert_eq!(a.symmetric_difference(&a.clone()).count(), 0);
+
+        // disjoint
+        let mut c: SparseSetVec<EntityId, ()> = SparseSet::default();
+        c.insert(id(100), ());
+        let difference: Vec<_> = a.symmetric_difference(&c).collect();
+        assert_eq!(difference.len(), 7);
+        assert_eq!(difference[6], Either::Right((id(100), &())));
+        assert!(difference[..6]
+            .iter()
+            .all(|item| matches!(item, Either::Left((_, 'a')))));
+    }
+
+    #[test]
+    fn ids_for_indices_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, ()> = SparseSet::default();
+        for _ in 0..1000 {
+            sparse_set.insert(EntityId::new(rng.gen_range(1..2000)).unwrap(), ());
+        }
+        let indices: Vec<usize> = (0..500)
+            .map(|_| rng.gen_range(0..sparse_set.len()))
+            .collect();
+
+        let mut ids = vec![EntityId::new(1).unwrap()];
+        sparse_set.ids_for_indices(&indices, &mut ids).unwrap();
+        assert_eq!(ids.len(), 501);
+        for (index, id) in indices.iter().zip(&ids[1..]) {
+            assert_eq!(sparse_set.get_id(*index), Some(*id));
+        }
+
+        let len = sparse_set.len();
+        assert_eq!(
+            sparse_set.ids_for_indices(&[0, len], &mut ids),
+            Err(IndexOutOfRange { index: len, len })
+        );
+        assert_eq!(ids.len(), 501);
+    }
+
+    #[test]
+    fn prefetch_test() {
+        let mut sparse_set: SparseSetVec<usize, u64> = SparseSet::default();
+        for id in (0..200_000).step_by(2) {
+            sparse_set.insert(id, id as u64);
+        }
+        let before = sparse_set.clone();
+        for id in [0, 1, 2, 199_999, 1_000_000, usize::MAX - 1] {
+            sparse_set.prefetch(id);
+        }
+        assert_eq!(sparse_set.ids(), before.ids());
+        assert_eq!(sparse_set.data(), before.data());
+
+        // large enough to prefetch
+        let mut rng = thread_rng();
+        let ids: Vec<usize> = (0..10_000).map(|_| rng.gen_range(0..300_000)).collect();
+        let mut expected = sparse_set.clone();
+        let mut count = 0;
+        for id in &ids {
+            if let Some(x) = expected.get_mut(*id) {
+                *x += 1;
+                count += 1;
+            }
+        }
+        assert_eq!(sparse_set.apply_batch(&ids, |_, x| *x += 1), count);
+        assert_eq!(sparse_set.data(), expected.data());
+    }
+
+    #[test]
+    fn apply_slice_by_ids_test() {
+        let mut sparse_set: SparseSetVec<EntityId, i32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=5 {
+            sparse_set.insert(id(x), 100);
+        }
+
+        let ids = [id(2), id(9), id(4), id(2), id(7)];
+        let deltas = [-10, 5, 20, -1, 3];
+        let applied = sparse_set.apply_slice_by_ids(&ids, &deltas, |x, delta| *x += *delta);
+        assert_eq!(applied, 3);
+        assert_eq!(sparse_set.data(), &[100, 89, 100, 120, 100]);
+        assert!(!sparse_set.contains(id(9)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn apply_slice_by_ids_len_test() {
+        let mut sparse_set: SparseSetVec<EntityId, i32> = SparseSet::default();
+        sparse_set.apply_slice_by_ids(&[EntityId::new(1).unwrap()], &[1, 2], |x, delta| {
+            *x += *delta
+        });
+    }
+
+    #[test]
+    fn swap_target_of_test() {
+        let mut sparse_set: SparseSetVec<EntityId, char> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for (x, c) in [(3, 'a'), (1, 'b'), (7, 'c')] {
+            sparse_set.insert(id(x), c);
+        }
+
+        assert_eq!(sparse_set.swap_target_of(id(5)), None);
+        assert_eq!(sparse_set.swap_target_of(id(7)), Some(None));
+        assert_eq!(sparse_set.swap_target_of(id(3)), Some(Some(id(7))));
+        // planning doesn't change the set
+        assert_eq!(sparse_set.ids(), &[id(3), id(1), id(7)]);
+
+        sparse_set.swap_remove_by_id(id(3));
+        assert_eq!(sparse_set.get_index(id(7)), Some(0));
+    }
+
+    #[test]
+    fn cache_line_chunks_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in 1..=100 {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u32);
+        }
+        let chunks: Vec<_> = sparse_set.cache_line_chunks().collect();
+        assert_eq!(chunks.len(), 7);
+        assert!(chunks[..6].iter().all(|chunk| chunk.len() == 16));
+        assert_eq!(chunks.concat(), sparse_set.data());
+
+        let mut big: SparseSetVec<EntityId, [u8; 100]> = SparseSet::default();
+        big.insert(EntityId::new(1).unwrap(), [0; 100]);
+        big.insert(EntityId::new(2).unwrap(), [1; 100]);
+        assert!(big.cache_line_chunks().all(|chunk| chunk.len() == 1));
+
+        let mut zst: SparseSetVec<EntityId, ()> = SparseSet::default();
+        zst.insert(EntityId::new(1).unwrap(), ());
+        assert_eq!(zst.cache_line_chunks().count(), 1);
+    }
+
+    #[test]
+    fn with_hint_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=10 {
+            sparse_set.insert(id(x), x * 10);
+        }
+
+        // right hints
+        assert_eq!(sparse_set.get_with_hint(id(4), 3), Some(&40));
+        // stale hints at other live entities
+        assert_eq!(sparse_set.get_with_hint(id(4), 7), Some(&40));
+        // out of range hints
+        assert_eq!(sparse_set.get_with_hint(id(4), 100), Some(&40));
+        assert_eq!(sparse_set.get_with_hint(id(11), 2), None);
+        assert_eq!(sparse_set.get_with_hint(id(11), 100), None);
+
+        assert_eq!(sparse_set.swap_remove_with_hint(id(2), 1), Some(20));
+        // id 10 is moved to index 1, so its old index is stale
+        assert_eq!(sparse_set.swap_remove_with_hint(id(10), 9), Some(100));
+        assert_eq!(sparse_set.swap_remove_with_hint(id(5), 50), Some(50));
+        assert_eq!(sparse_set.swap_remove_with_hint(id(5), 3), None);
+        assert_eq!(
+            sparse_set.ids(),
+            &[id(1), id(9), id(3), id(4), id(8), id(6), id(7)]
+        );
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn insert_ref_test() {
+        let mut sparse_set: SparseSetVec<EntityId, Vec<u32>> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        sparse_set.insert_ref(id(3), vec![1]).push(2);
+        sparse_set.insert_ref(id(5), Vec::new()).extend([7, 8]);
+        // overwriting drops the old data
+        let dat = sparse_set.insert_ref(id(3), vec![10]);
+        dat.push(11);
+        dat[0] += 1;
+        assert_eq!(sparse_set.get(id(3)), Some(&vec![11, 11]));
+        assert_eq!(sparse_set.get(id(5)), Some(&vec![7, 8]));
+        assert_eq!(sparse_set.len(), 2);
+    }
+
+    #[test]
+    fn iter_by_id_asc_test() {
+        use rand::Rng;
+
+        let mut rng = rand::thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        for _ in 0..500 {
+            let x = rng.gen_range(1..10_000);
+            sparse_set.insert(EntityId::new(x).unwrap(), x * 2);
+        }
+        for x in (1..10_000).step_by(7) {
+            sparse_set.swap_remove_by_id(EntityId::new(x).unwrap());
+        }
+
+        let mut expected: Vec<_> = sparse_set.iter().collect();
+        expected.sort_by_key(|(id, _)| *id);
+        let pairs: Vec<_> = sparse_set.iter_by_id_asc().collect();
+        assert_eq!(pairs, expected);
+
+        let slots: Vec<_> = sparse_set
+            .sparse
+            .iter_occupied_ascending()
+            .map(|(id, index)| (id, index.get() - 1))
+            .collect();
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(id, _)| (id.get(), sparse_set.get_index(*id).unwrap()))
+            .collect();
+        assert_eq!(slots, expected);
+
+        assert_eq!(
+            SparseSetVec::<EntityId, ()>::default()
+                .iter_by_id_asc()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn id_histogram_test() {
+        let mut sparse_set: SparseSetVec<usize, ()> = SparseSet::default();
+        assert_eq!(sparse_set.id_histogram(10), Ok(vec![]));
+        assert_eq!(sparse_set.id_histogram_sparse(10), Ok(vec![]));
+        // 2 clusters with a gap
+        for id in (0..25).chain(60..65).rev() {
+            sparse_set.insert(id, ());
+        }
+        sparse_set.insert(98, ());
+        assert_eq!(
+            sparse_set.id_histogram(10),
+            Ok(vec![10, 10, 5, 0, 0, 0, 5, 0, 0, 1])
+        );
+        assert_eq!(sparse_set.id_histogram(64), Ok(vec![29, 2]));
+        assert_eq!(sparse_set.id_histogram(1000), Ok(vec![31]));
+        assert_eq!(
+            sparse_set.id_histogram_sparse(10),
+            Ok(vec![(0, 10), (1, 10), (2, 5), (6, 5), (9, 1)])
+        );
+
+        // scattered ids only cost their own buckets
+        let mut scattered: SparseSetHashMap<usize, ()> = SparseSet::default();
+        scattered.insert(usize::MAX, ());
+        scattered.insert(1 << 40, ());
+        scattered.insert(3, ());
+        assert_eq!(
+            scattered.id_histogram(1),
+            Err(HistogramError::TooManyBuckets(1 << 24))
+        );
+        assert_eq!(
+            scattered.id_histogram_sparse(1),
+            Ok(vec![(3, 1), (1 << 40, 1), (usize::MAX, 1)])
+        );
+        assert_eq!(
+            scattered.id_histogram_sparse(1 << 20),
+            Ok(vec![(0, 1), (1 << 20, 1), (usize::MAX >> 20, 1)])
+        );
+
+        // integer ids other than usize
+        let mut small: SparseSetHashMap<u32, ()> = SparseSet::default();
+        small.insert(3, ());
+        small.insert(12, ());
+        assert_eq!(small.id_histogram(5), Ok(vec![1, 0, 1]));
+        let mut wide: SparseSet<u64, (), crate::U64VecStorage<u64>> = SparseSet::default();
+        wide.insert(7, ());
+        assert_eq!(wide.id_histogram_sparse(4), Ok(vec![(1, 1)]));
+    }
+
+    #[test]
+    fn reserve_for_ids_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u64> = SparseSet::default();
+        sparse_set.insert(EntityId::new(1).unwrap(), 1);
+        let mut ids: Vec<_> = (2..=1000)
+            .rev()
+            .map(|x| EntityId::new(x).unwrap())
+            .collect();
+        let mut data: Vec<_> = (2..=1000).rev().collect();
+
+        sparse_set.reserve_for_ids(&ids);
+        let data_ptr = sparse_set.data().as_ptr();
+        let slot_capacity = sparse_set.sparse.slot_capacity();
+        assert!(slot_capacity >= 1001);
+        sparse_set.insert_batch(&mut ids, &mut data);
+        assert_eq!(sparse_set.data().as_ptr(), data_ptr);
+        assert_eq!(sparse_set.sparse.slot_capacity(), slot_capacity);
+        assert_eq!(sparse_set.get(EntityId::new(500).unwrap()), Some(&500));
+
+        let mut sparse_set: SparseSetHashMap<EntityId, u64> = SparseSet::default();
+        let ids: Vec<_> = (1..=100)
+            .map(|x| EntityId::new(x * 1000).unwrap())
+            .collect();
+        sparse_set.reserve_for_ids(&ids);
+        assert!(sparse_set.capacity() >= 100);
+        assert!(sparse_set.sparse.capacity() >= 100);
+
+        // the slots are capped by the limits
+        let mut sparse_set: SparseSetVec<EntityId, u64> = SparseSet::default();
+        sparse_set.set_limits(SparseSetLimits {
+            max_sparse_slots: Some(100),
+            ..SparseSetLimits::default()
+        });
+        sparse_set.reserve_for_ids(&[EntityId::new(1 << 60).unwrap()]);
+        assert!(sparse_set.sparse.slot_capacity() < 1 << 20);
+    }
+
+    #[test]
+    fn retain_with_drop_test() {
+        let mut sparse_set: SparseSetVec<EntityId, String> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=10 {
+            sparse_set.insert(id(x), x.to_string());
+        }
+
+        let mut removed = Vec::new();
+        let count = sparse_set.retain_with_drop(
+            |id, _| id.get() % 3 != 0,
+            |id, dat| removed.push((id.get(), dat)),
+        );
+        assert_eq!(count, 3);
+        removed.sort();
+        assert_eq!(
+            removed,
+            vec![
+                (3, "3".to_string()),
+                (6, "6".to_string()),
+                (9, "9".to_string())
+            ]
+        );
+        assert_eq!(sparse_set.len(), 7);
+        for x in 1..=10 {
+            assert_eq!(sparse_set.get(id(x)).is_some(), x % 3 != 0);
+        }
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        assert_eq!(
+            sparse_set.retain_with_drop(|_, _| true, |_, _| unreachable!()),
+            0
+        );
+    }
+
+    #[test]
+    fn max_min_by_key_test() {
+        let mut sparse_set: SparseSetVec<EntityId, i32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        assert_eq!(sparse_set.max_by_key(|_, x| *x), None);
+        assert_eq!(sparse_set.position_min_by_key(|_, x| *x), None);
+
+        sparse_set.insert(id(4), 7);
+        assert_eq!(sparse_set.max_by_key(|_, x| *x), Some((id(4), &7)));
+        assert_eq!(sparse_set.min_by_key(|_, x| *x), Some((id(4), &7)));
+
+        for (x, health) in [(2, 3), (9, 10), (5, 3), (1, 10), (8, 5)] {
+            sparse_set.insert(id(x), health);
+        }
+        // the first extreme wins on ties
+        assert_eq!(sparse_set.max_by_key(|_, x| *x), Some((id(9), &10)));
+        assert_eq!(sparse_set.min_by_key(|_, x| *x), Some((id(2), &3)));
+        assert_eq!(sparse_set.position_max_by_key(|_, x| *x), Some(2));
+        assert_eq!(sparse_set.position_min_by_key(|_, x| *x), Some(1));
+        assert_eq!(sparse_set.max_by_key(|id, _| id.get()), Some((id(9), &10)));
+        assert_eq!(sparse_set.position_min_by_key(|_, _| 0), Some(0));
+    }
+
+    #[test]
+    fn map_test() {
+        let mut sparse_set: SparseSetHashMap<u64, f32> = SparseSet::default();
+        for global in [9000u64, 120, 77_000, 5] {
+            sparse_set.insert(global, global as f32);
+        }
+        let mut locals = std::collections::HashMap::new();
+        let local: SparseSetVec<u16, (u64, f64)> = sparse_set.map(|global, x| {
+            let local = locals.len() as u16;
+            locals.insert(global, local);
+            (local, (global, f64::from(x) / 2.0))
+        });
+        assert_eq!(local.ids(), &[0, 1, 2, 3]);
+        assert_eq!(local.get(locals[&77_000]), Some(&(77_000, 38_500.0)));
+        assert_eq!(local.get(locals[&5]), Some(&(5, 2.5)));
+        assert_eq!(local.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn map_collision_test() {
+        let mut sparse_set: SparseSetVec<u16, u8> = SparseSet::default();
+        sparse_set.insert(2, 2);
+        sparse_set.insert(3, 3);
+        let _: SparseSetVec<u16, u8> = sparse_set.map(|id, x| (id / 2, x));
+    }
+
+    #[test]
+    fn warm_up_test() {
+        let mut sparse_set: SparseSetVec<EntityId, [u64; 4]> = SparseSet::default();
+        sparse_set.warm_up();
+        for x in 1..=1000 {
+            sparse_set.insert(EntityId::new(x * 7).unwrap(), [x as u64; 4]);
+        }
+        sparse_set.reserve_for_ids(&[EntityId::new(100_000).unwrap()]);
+        sparse_set.reserve(100_000);
+        let before = sparse_set.clone();
+        sparse_set.warm_up();
+        assert_eq!(sparse_set.ids(), before.ids());
+        assert_eq!(sparse_set.data(), before.data());
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        assert_eq!(sparse_set.get(EntityId::new(700).unwrap()), Some(&[100; 4]));
+
+        let mut zst: SparseSetVec<EntityId, ()> = SparseSet::default();
+        zst.reserve(10);
+        zst.warm_up();
+    }
+
+    #[test]
+    fn range_by_key_test() {
+        let mut sparse_set: SparseSetVec<EntityId, (u32, char)> = SparseSet::default();
+        let depths = [1, 2, 2, 2, 5, 7, 7, 9];
+        for (x, depth) in depths.iter().enumerate() {
+            let c = char::from(b'a' + x as u8);
+            sparse_set.insert(EntityId::new(20 - x).unwrap(), (*depth, c));
+        }
+        let chars =
+            |iter: Iter<'_, EntityId, (u32, char)>| -> String { iter.map(|(_, x)| x.1).collect() };
+
+        assert_eq!(sparse_set.partition_point_by(|x| x.0 <= 2), 4);
+        assert_eq!(sparse_set.partition_point_by(|x| x.0 < 100), 8);
+        assert_eq!(sparse_set.partition_point_by(|_| false), 0);
+
+        // duplicate keys at both bounds
+        assert_eq!(chars(sparse_set.range_by_key(|x| x.0, 2..=7)), "bcdefg");
+        assert_eq!(chars(sparse_set.range_by_key(|x| x.0, 2..7)), "bcde");
+        assert_eq!(
+            chars(sparse_set.range_by_key(|x| x.0, (Bound::Excluded(2), Bound::Included(7)))),
+            "efg"
+        );
+        assert_eq!(chars(sparse_set.range_by_key(|x| x.0, ..3)), "abcd");
+        assert_eq!(chars(sparse_set.range_by_key(|x| x.0, 8..)), "h");
+        assert_eq!(chars(sparse_set.range_by_key(|x| x.0, 3..5)), "");
+        assert_eq!(
+            chars(sparse_set.range_by_key(|x| x.0, (Bound::Included(7), Bound::Excluded(2)))),
+            ""
+        );
+        let (id, _) = sparse_set.range_by_key(|x| x.0, 5..=5).next().unwrap();
+        assert_eq!(id, EntityId::new(16).unwrap());
+    }
+
+    #[test]
+    fn same_ids_test() {
+        let mut positions: SparseSetVec<EntityId, (f32, f32)> = SparseSet::default();
+        let mut names: SparseSetHashMap<EntityId, String> = SparseSet::default();
+        for x in [4, 9, 1, 16] {
+            positions.insert(EntityId::new(x).unwrap(), (x as f32, 0.0));
+        }
+        for x in [16, 1, 4, 9] {
+            names.insert(EntityId::new(x).unwrap(), x.to_string());
+        }
+        assert!(positions.same_ids(&names));
+        assert!(names.same_ids(&positions));
+
+        names.swap_remove_by_id(EntityId::new(9).unwrap());
+        assert!(!positions.same_ids(&names));
+        names.insert(EntityId::new(10).unwrap(), "10".to_string());
+        assert!(!positions.same_ids(&names));
+        assert!(SparseSetVec::<EntityId, ()>::default()
+            .same_ids(&SparseSetVec::<EntityId, u8>::default()));
+    }
+
+    #[test]
+    fn keys_eq_test() {
+        let from = |ids: &[usize]| -> SparseSetVec<EntityId, usize> {
+            let mut sparse_set = SparseSet::default();
+            for x in ids {
+                sparse_set.insert(EntityId::new(*x).unwrap(), *x);
+            }
+            sparse_set
+        };
+        let a = from(&[3, 1, 4, 5]);
+        // different orders
+        let b = from(&[5, 4, 3, 1]);
+        assert!(a.keys_eq(&b));
+        assert!(!a.dense_ids_eq(&b));
+        assert!(a.dense_ids_eq(&from(&[3, 1, 4, 5])));
+        // subsets
+        let c = from(&[3, 1, 4]);
+        assert!(!a.keys_eq(&c));
+        assert!(!c.keys_eq(&a));
+        assert!(!c.dense_ids_eq(&a));
+        // equal lengths with different ids
+        let d = from(&[3, 1, 4, 6]);
+        assert!(!a.keys_eq(&d));
+        assert!(!a.dense_ids_eq(&d));
+    }
+
+    #[test]
+    fn iter_forms_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, u64> = SparseSet::default();
+        for _ in 0..100 {
+            let id = EntityId::new(rng.gen_range(1..200)).unwrap();
+            sparse_set.insert(id, rng.gen());
+        }
+        let expected: Vec<_> = sparse_set
+            .ids()
+            .iter()
+            .copied()
+            .zip(sparse_set.data().iter().copied())
+            .collect();
+
+        let pairs: Vec<_> = sparse_set.iter().map(|(id, x)| (id, *x)).collect();
+        assert_eq!(pairs, expected);
+        assert_eq!(sparse_set.iter().len(), expected.len());
+        let reversed: Vec<_> = sparse_set.iter().rev().map(|(id, x)| (id, *x)).collect();
+        assert!(reversed.iter().eq(expected.iter().rev()));
+        let borrowed: Vec<_> = (&sparse_set).into_iter().map(|(id, x)| (id, *x)).collect();
+        assert_eq!(borrowed, expected);
+
+        let mut iter_mut = sparse_set.iter_mut();
+        assert_eq!(iter_mut.len(), expected.len());
+        let (last_id, last) = iter_mut.next_back().unwrap();
+        assert_eq!((last_id, *last), *expected.last().unwrap());
+        let pairs: Vec<_> = iter_mut.map(|(id, x)| (id, *x)).collect();
+        assert_eq!(pairs[..], expected[..expected.len() - 1]);
+        for (_, x) in &mut sparse_set {
+            *x = x.wrapping_add(1);
+        }
+
+        let mut into_iter = sparse_set.into_iter();
+        assert_eq!(into_iter.len(), expected.len());
+        let (last_id, last) = into_iter.next_back().unwrap();
+        assert_eq!((last_id, last.wrapping_sub(1)), *expected.last().unwrap());
+        let pairs: Vec<_> = into_iter.map(|(id, x)| (id, x.wrapping_sub(1))).collect();
+        assert_eq!(pairs[..], expected[..expected.len() - 1]);
+    }
+
+    #[test]
+    fn reserve_slots_test() {
+        let mut sparse_set: SparseSetVec<EntityId, [u64; 64]> = SparseSet::default();
+        sparse_set.insert(EntityId::new(1).unwrap(), [1; 64]);
+        let ids: Vec<_> = (10..20).map(|x| EntityId::new(x).unwrap()).collect();
+        unsafe {
+            let slots = sparse_set.reserve_slots(&ids);
+            assert_eq!(slots.len(), 10);
+            for (slot, id) in slots.iter_mut().zip(&ids) {
+                slot.write([id.get() as u64; 64]);
+            }
+            sparse_set.assume_init_slots();
+        }
+        assert_eq!(sparse_set.len(), 11);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        for id in &ids {
+            assert_eq!(sparse_set.get(*id), Some(&[id.get() as u64; 64]));
+        }
+        assert_eq!(sparse_set.get(EntityId::new(1).unwrap()), Some(&[1; 64]));
+        assert_eq!(sparse_set.get_index(EntityId::new(15).unwrap()), Some(6));
+
+        unsafe {
+            assert!(sparse_set.reserve_slots(&[]).is_empty());
+            sparse_set.assume_init_slots();
+        }
+        assert_eq!(sparse_set.len(), 11);
+    }
+
+    fn iter_after_removal<S>()
+    where
+        S: SparseStorage<EntityId = EntityId> + Default,
+    {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSet<EntityId, usize, S> = SparseSet::default();
+        assert_eq!(sparse_set.iter().len(), 0);
+        assert_eq!((&sparse_set).into_iter().next(), None);
+
+        for x in 1..=6 {
+            sparse_set.insert(id(x), x * 10);
+        }
+        sparse_set.swap_remove_by_id(id(2));
+        sparse_set.swap_remove_by_id(id(5));
+        let iter = sparse_set.iter();
+        assert_eq!(iter.size_hint(), (4, Some(4)));
+        let pairs: Vec<_> = iter.map(|(id, x)| (id.get(), *x)).collect();
+        assert_eq!(pairs, vec![(1, 10), (6, 60), (3, 30), (4, 40)]);
+        let mut looped = Vec::new();
+        for (id, x) in &sparse_set {
+            looped.push((id.get(), *x));
+        }
+        assert_eq!(looped, pairs);
+    }
+
+    #[test]
+    fn iter_storages_test() {
+        iter_after_removal::<VecStorage<EntityId>>();
+        iter_after_removal::<HashMap<EntityId, NonZeroUsize>>();
+        iter_after_removal::<BTreeMap<EntityId, NonZeroUsize>>();
+    }
+
+    #[test]
+    fn iter_mut_double_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        for x in [7, 3, 12, 5] {
+            sparse_set.insert(EntityId::new(x).unwrap(), x);
+        }
+        sparse_set.swap_remove_by_id(EntityId::new(3).unwrap());
+
+        let iter = sparse_set.iter_mut();
+        assert_eq!(iter.len(), 3);
+        for (id, x) in iter {
+            assert_eq!(id.get(), *x);
+            *x *= 2;
+        }
+        for (id, x) in sparse_set.iter_mut().rev() {
+            assert_eq!(id.get() * 2, *x);
+        }
+        assert_eq!(sparse_set.data(), &[14, 10, 24]);
+    }
+
+    #[test]
+    fn clear_in_order_test() {
+        let mut sparse_set: SparseSetVec<EntityId, String> = SparseSet::default();
+        for x in [4, 9, 1, 6] {
+            sparse_set.insert(EntityId::new(x).unwrap(), x.to_string());
+        }
+        sparse_set.swap_remove_by_id(EntityId::new(9).unwrap());
+        let capacity = sparse_set.capacity();
+
+        let mut collected = Vec::new();
+        sparse_set.clear_in_order(|id, x| collected.push((id.get(), x)));
+        assert_eq!(
+            collected,
+            vec![
+                (4, "4".to_string()),
+                (6, "6".to_string()),
+                (1, "1".to_string())
+            ]
+        );
+        assert!(sparse_set.is_empty());
+        assert_eq!(sparse_set.capacity(), capacity);
+        for x in [1, 4, 6, 9] {
+            assert!(!sparse_set.contains(EntityId::new(x).unwrap()));
+        }
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
 
-    /// Get the slice of data
-    pub fn data_mut(&mut self) -> &mut [T] {
-        &mut self.data
+        sparse_set.insert(EntityId::new(6).unwrap(), "six".to_string());
+        assert_eq!(sparse_set.get_index(EntityId::new(6).unwrap()), Some(0));
     }
 
-    /// Get the slice of ID , or the dense array
-    /// # Details
-    /// There is NO any `fn ids_mut(&self)` in this lib.  
-    /// Because the mapping relations between the sparse and the dense is ensured by this lib.  
-    /// Leaking the mutable slice of dense is unsafe and will cause some unexpected error
-    pub fn ids(&self) -> &[E] {
-        &self.dense
+    #[test]
+    fn retain_test() {
+        let new_set = || {
+            let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+            for x in 1..=10 {
+                sparse_set.insert(EntityId::new(x).unwrap(), x);
+            }
+            sparse_set
+        };
+
+        let mut sparse_set = new_set();
+        assert_eq!(sparse_set.retain(|_, _| false), 10);
+        assert!(sparse_set.is_empty());
+        assert!(!sparse_set.contains(EntityId::new(1).unwrap()));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        let mut sparse_set = new_set();
+        assert_eq!(sparse_set.retain(|_, _| true), 0);
+        assert_eq!(sparse_set.len(), 10);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        let mut sparse_set = new_set();
+        assert_eq!(sparse_set.retain(|id, x| id.get() == *x && x % 2 == 0), 5);
+        assert_eq!(sparse_set.len(), 5);
+        for x in 1..=10 {
+            let id = EntityId::new(x).unwrap();
+            assert_eq!(sparse_set.get(id), Some(&x).filter(|x| *x % 2 == 0));
+        }
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // the predicate changes data before deciding
+        let mut sparse_set = new_set();
+        let removed = sparse_set.retain_mut(|_, x| {
+            *x *= 10;
+            *x % 20 != 0
+        });
+        assert_eq!(removed, 5);
+        let mut data = sparse_set.data().to_vec();
+        data.sort_unstable();
+        assert_eq!(data, vec![10, 30, 50, 70, 90]);
+        assert_eq!(sparse_set.get(EntityId::new(3).unwrap()), Some(&30));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use std::{collections::BTreeSet, num::NonZeroUsize};
+    #[test]
+    fn debug_name_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u8> = SparseSet::default();
+        assert_eq!(sparse_set.debug_name(), None);
+        assert_eq!(format!("{:?}", sparse_set), "SparseSet {}");
+        sparse_set.insert(EntityId::new(3).unwrap(), 30);
+        sparse_set.insert(EntityId::new(1).unwrap(), 10);
+        assert_eq!(format!("{:?}", sparse_set), "SparseSet {3: 30, 1: 10}");
 
-    use rand::{thread_rng, Rng};
+        sparse_set.set_debug_name("positions");
+        assert_eq!(sparse_set.debug_name(), Some("positions"));
+        assert_eq!(
+            format!("{:?}", sparse_set),
+            "SparseSet \"positions\" of u8 {3: 30, 1: 10}"
+        );
+        assert_eq!(
+            format!("{:#?}", sparse_set),
+            "SparseSet \"positions\" of u8 {\n    3: 30,\n    1: 10,\n}"
+        );
+        sparse_set.clear();
+        sparse_set.set_debug_name(format!("velocities#{}", 2));
+        assert_eq!(sparse_set.debug_name(), Some("velocities#2"));
+        assert_eq!(sparse_set.data_type_name(), "u8");
+        assert_eq!(sparse_set.data_type_id(), std::any::TypeId::of::<u8>());
 
-    use crate::{sparse_storage::VecStorage, SparseSet};
+        // the name is kept by map
+        sparse_set.insert(EntityId::new(1).unwrap(), 1);
+        let mapped: SparseSetVec<EntityId, u16> = sparse_set.map(|id, x| (id, x as u16));
+        assert_eq!(mapped.debug_name(), Some("velocities#2"));
+    }
 
-    type EntityId = NonZeroUsize;
+    #[test]
+    #[should_panic(expected = "sparse set \"positions\" of u8: index_b=5 is out of range")]
+    fn debug_name_panic_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u8> = SparseSet::default();
+        sparse_set.set_debug_name("positions");
+        sparse_set.insert(EntityId::new(1).unwrap(), 1);
+        sparse_set.swap_by_index(0, 5);
+    }
 
     #[test]
-    fn interface_test() {
-        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
+    #[should_panic(expected = "sparse set \"positions\" of u8: the count of entities exceeds 1")]
+    fn debug_name_limit_panic_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u8> = SparseSet::default();
+        sparse_set.set_debug_name("positions");
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: Some(1),
+            max_sparse_slots: None,
+        });
+        sparse_set.insert(EntityId::new(1).unwrap(), 1);
+        sparse_set.insert(EntityId::new(2).unwrap(), 2);
+    }
 
-        assert_eq!(sparse_set.len(), 0);
-        assert!(sparse_set.is_empty());
-        assert!(sparse_set.data().is_empty());
-        assert!(sparse_set.ids().is_empty());
+    #[test]
+    fn gather_by_index_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let mut velocities = Vec::new();
+        for x in [5, 2, 9, 7] {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u32);
+            velocities.push(x * 100);
+        }
+        // keep the external column aligned on removal
+        let index = sparse_set.get_index(EntityId::new(2).unwrap()).unwrap();
+        sparse_set.swap_remove_by_index(index);
+        velocities.swap_remove(index);
 
-        let id = NonZeroUsize::new(124).unwrap();
+        let gathered: Vec<_> = sparse_set
+            .gather_by_index(&velocities)
+            .map(|(id, v)| (id.get(), *v))
+            .collect();
+        assert_eq!(gathered, vec![(5, 500), (7, 700), (9, 900)]);
+        assert_eq!(sparse_set.gather_by_index(&velocities).len(), 3);
+        assert_eq!(
+            sparse_set.gather_by_index(&velocities).next_back(),
+            Some((EntityId::new(9).unwrap(), &900))
+        );
 
-        assert_eq!(sparse_set.swap_remove_by_id(id), None);
-        assert!(!sparse_set.contains(id));
-        assert_eq!(sparse_set.len(), 0);
-        assert!(sparse_set.is_empty());
-        assert!(sparse_set.data().is_empty());
-        assert!(sparse_set.ids().is_empty());
+        let unaligned = std::panic::catch_unwind(|| sparse_set.gather_by_index(&[1, 2]).count());
+        assert!(unaligned.is_err());
+    }
 
-        // insert
-        assert_eq!(sparse_set.insert(id, 'c'), None);
+    #[test]
+    fn into_iter_round_trip_test() {
+        let mut rng = thread_rng();
+        let mut inserted = HashMap::new();
+        let mut sparse_set: SparseSetVec<EntityId, u64> = SparseSet::default();
+        for _ in 0..200 {
+            let id = EntityId::new(rng.gen_range(1..500)).unwrap();
+            let x = rng.gen();
+            sparse_set.insert(id, x);
+            inserted.insert(id, x);
+        }
+        let ids = sparse_set.ids().to_vec();
+        let data = sparse_set.data().to_vec();
 
-        assert_eq!(sparse_set.len(), 1);
-        assert!(!sparse_set.is_empty());
-        assert_eq!(sparse_set.get(id).copied(), Some('c'));
-        assert!(sparse_set.contains(id));
-        assert_eq!(sparse_set.data(), &['c']);
-        assert_eq!(sparse_set.ids(), &[id]);
+        let collected: HashMap<_, _> = sparse_set.clone().into_iter().collect();
+        assert_eq!(collected, inserted);
+        assert_eq!(sparse_set.clone().into_ids(), ids);
+        assert_eq!(sparse_set.into_data(), data);
+    }
 
-        // insert again to change the value
-        assert_eq!(sparse_set.insert(id, 'b'), Some('c'));
+    #[test]
+    fn into_iter_for_loop_test() {
+        let mut sparse_set: SparseSetHashMap<EntityId, String> = SparseSet::default();
+        for x in [8, 3, 5] {
+            sparse_set.insert(EntityId::new(x).unwrap(), x.to_string());
+        }
+        let mut moved = Vec::new();
+        for (id, name) in sparse_set {
+            moved.push((id.get(), name));
+        }
+        assert_eq!(moved.len(), 3);
+        assert_eq!(
+            moved,
+            vec![
+                (8, "8".to_string()),
+                (3, "3".to_string()),
+                (5, "5".to_string())
+            ]
+        );
+    }
 
-        assert_eq!(sparse_set.len(), 1);
-        assert!(!sparse_set.is_empty());
-        assert_eq!(sparse_set.get(id).copied(), Some('b'));
-        assert!(sparse_set.contains(id));
-        assert_eq!(sparse_set.data(), &['b']);
-        assert_eq!(sparse_set.ids(), &[id]);
+    #[test]
+    fn retain_mut_odd_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let mut values = Vec::new();
+        for x in 1..=1000 {
+            let value = rng.gen_range(0..10000);
+            sparse_set.insert(EntityId::new(x).unwrap(), value);
+            values.push(value);
+        }
+        let odd = values.iter().filter(|x| *x % 2 == 1).count();
 
-        // remove this one
-        assert_eq!(sparse_set.swap_remove_by_id(id), Some('b'));
+        // every swapped-in entity is checked too
+        assert_eq!(sparse_set.retain_mut(|_, x| *x % 2 == 0), odd);
+        assert_eq!(sparse_set.len(), 1000 - odd);
+        for (x, value) in (1..=1000).zip(&values) {
+            let id = EntityId::new(x).unwrap();
+            assert_eq!(sparse_set.contains(id), value % 2 == 0);
+            assert_eq!(sparse_set.get(id), Some(value).filter(|x| *x % 2 == 0));
+        }
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
 
-        assert!(!sparse_set.contains(id));
-        assert_eq!(sparse_set.len(), 0);
-        assert!(sparse_set.is_empty());
-        assert!(sparse_set.data().is_empty());
-        assert!(sparse_set.ids().is_empty());
+    #[test]
+    fn replace_all_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in 1..=50 {
+            sparse_set.insert(id(x), x as u32);
+        }
+        let data_ptr = sparse_set.data().as_ptr();
 
-        // remove twice
-        assert_eq!(sparse_set.swap_remove_by_id(id), None);
+        sparse_set.replace_all(vec![(id(7), 70), (id(3), 30), (id(7), 77)]);
+        assert_eq!(sparse_set.len(), 2);
+        assert_eq!(sparse_set.ids(), &[id(7), id(3)]);
+        assert_eq!(sparse_set.get(id(7)), Some(&77));
+        assert!(!sparse_set.contains(id(1)));
+        assert_eq!(sparse_set.data().as_ptr(), data_ptr);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
 
-        assert!(!sparse_set.contains(id));
-        assert_eq!(sparse_set.len(), 0);
+        sparse_set.replace_all(std::iter::empty());
         assert!(sparse_set.is_empty());
-        assert!(sparse_set.data().is_empty());
-        assert!(sparse_set.ids().is_empty());
+        assert!(!sparse_set.contains(id(3)));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
 
-        // generate a lot of ids'
+    #[test]
+    fn from_iter_duplicates_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let pairs = vec![
+            (id(4), 'a'),
+            (id(2), 'b'),
+            (id(4), 'c'),
+            (id(9), 'd'),
+            (id(2), 'e'),
+        ];
+        let sparse_set: SparseSetVec<EntityId, char> = pairs.into_iter().collect();
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.ids(), &[id(4), id(2), id(9)]);
+        assert_eq!(sparse_set.data(), &['c', 'e', 'd']);
+        assert_eq!(sparse_set.get(id(2)), Some(&'e'));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        let mut sparse_set = sparse_set;
+        sparse_set.extend((10..20).map(|x| (id(x), 'x')));
+        assert_eq!(sparse_set.len(), 13);
+        assert!(sparse_set.capacity() >= 13);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn collect_edge_cases_test() {
+        let empty: SparseSetHashMap<EntityId, u8> = std::iter::empty().collect();
+        assert!(empty.is_empty());
+        assert_eq!(empty.check_invariants(), Ok(()));
+
+        let id = EntityId::new(42).unwrap();
+        let single: SparseSetVec<EntityId, u8> = std::iter::once((id, 7)).collect();
+        assert_eq!(single.len(), 1);
+        assert_eq!(single.get(id), Some(&7));
+
+        let last_wins: SparseSetBTreeMap<EntityId, u8> = (0..5).map(|x| (id, x)).collect();
+        assert_eq!(last_wins.len(), 1);
+        assert_eq!(last_wins.get(id), Some(&4));
+    }
+
+    /// Find the value of `key` in pairs sorted by key
+    fn lookup_sorted<P>(pairs: &P, key: usize) -> Option<&char>
+    where
+        P: PairSlice<EntityId, char> + ?Sized,
+    {
+        let index = pairs.binary_search_by(|id, _| id.get().cmp(&key)).ok()?;
+        pairs.get(index).map(|(_, value)| value)
+    }
+
+    #[test]
+    fn entries_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, char> = SparseSet::default();
+        for (x, c) in [(2, 'a'), (5, 'b'), (9, 'c')] {
+            sparse_set.insert(id(x), c);
+        }
+
+        let entries = sparse_set.entries();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries.get(1), Some((id(5), &'b')));
+        assert_eq!(entries.get(3), None);
+        assert_eq!(entries.iter().count(), 3);
+        assert_eq!(entries.binary_search_by(|x, _| x.cmp(&id(9))), Ok(2));
+        assert_eq!(entries.binary_search_by(|x, _| x.cmp(&id(3))), Err(1));
+
+        // a generic function accepts both the view and a real slice
+        let pairs = entries.to_vec();
+        assert_eq!(pairs, vec![(id(2), 'a'), (id(5), 'b'), (id(9), 'c')]);
+        for key in 0..12 {
+            assert_eq!(
+                lookup_sorted(&entries, key),
+                lookup_sorted(pairs.as_slice(), key)
+            );
+        }
+        assert_eq!(lookup_sorted(&entries, 5), Some(&'b'));
+        assert!(PairSlice::is_empty(&[] as &[(EntityId, char)]));
+    }
+
+    #[test]
+    fn get_by_index_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in [3, 8, 1] {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u32);
+        }
+        assert_eq!(sparse_set.get_by_index(1), Some(&8));
+        assert_eq!(sparse_set.get_by_index(3), None);
+
+        *sparse_set.get_mut_by_index(2).unwrap() += 10;
+        assert!(sparse_set.get_mut_by_index(3).is_none());
+        let id = sparse_set.get_id(2).unwrap();
+        assert_eq!(sparse_set.get(id), Some(&11));
+    }
+
+    #[test]
+    fn count_present_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in (2..=20).step_by(2) {
+            sparse_set.insert(id(x), x as u32);
+        }
+
+        assert_eq!(sparse_set.count_present(std::iter::empty()), 0);
+        assert_eq!(sparse_set.count_present((1..=10).map(id)), 5);
+        assert_eq!(sparse_set.count_present((21..=100).map(id)), 0);
+        assert_eq!(sparse_set.count_present(sparse_set.ids().to_vec()), 10);
+        assert_eq!(sparse_set.count_present([id(4), id(4), id(5)]), 2);
+    }
+
+    #[test]
+    fn subset_test() {
+        let mut a: SparseSetVec<EntityId, char> = SparseSet::default();
+        let mut b: SparseSetHashMap<EntityId, u32> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+        for x in 1..=10 {
+            a.insert(id(x), 'a');
+        }
+
+        // empty
+        assert!(a.is_superset(&b));
+        assert!(b.is_subset(&a));
+
+        // strict subset
+        for x in 3..=7 {
+            b.insert(id(x), x as u32);
+        }
+        assert!(a.is_superset(&b));
+        assert!(b.is_subset(&a));
+        assert!(!a.is_subset(&b));
+        assert!(!b.is_superset(&a));
+
+        // equal
+        for x in 1..=10 {
+            b.insert(id(x), x as u32);
+        }
+        assert!(a.is_superset(&b));
+        assert!(a.is_subset(&b));
+
+        // disjoint
+        let mut c: SparseSetVec<EntityId, ()> = SparseSet::default();
+        for x in 11..=13 {
+            c.insert(id(x), ());
+        }
+        assert!(!a.is_superset(&c));
+        assert!(!c.is_subset(&a));
+    }
+
+    #[test]
+    fn get_disjoint_mut_by_index_test() {
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in 1..=10 {
+            sparse_set.insert(EntityId::new(x).unwrap(), x as u32);
+        }
+
+        assert!(sparse_set.get_disjoint_mut_by_index([1, 3, 1]).is_none());
+        assert!(sparse_set.get_disjoint_mut_by_index([0, 10]).is_none());
+        assert!(sparse_set.get_disjoint_mut_by_index::<0>([]).is_some());
+
+        let [a, b, c, d] = sparse_set.get_disjoint_mut_by_index([9, 0, 4, 5]).unwrap();
+        std::mem::swap(a, b);
+        *c += 100;
+        *d += 100;
+        assert_eq!(sparse_set.data(), &[10, 2, 3, 4, 105, 106, 7, 8, 9, 1]);
+    }
+
+    #[test]
+    fn raw_sparse_index_test() {
+        let mut sparse_set: SparseSetHashMap<u32, char> = SparseSet::default();
+        for (x, c) in (0..10).zip('a'..) {
+            sparse_set.insert(x * 3, c);
+        }
+        sparse_set.swap_remove_by_id(6);
+
+        for x in 0..30 {
+            assert_eq!(
+                sparse_set.raw_sparse_index(x).map(NonZeroUsize::get),
+                sparse_set.get_index(x).map(|index| index + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn try_index_test() {
+        let mut sparse_set: SparseSetVec<EntityId, char> = SparseSet::default();
+        for (x, c) in (1..=3).zip('a'..) {
+            sparse_set.insert(EntityId::new(x).unwrap(), c);
+        }
+        let error = |index| IndexOutOfRange { index, len: 3 };
+
+        assert_eq!(sparse_set.try_swap_by_index(0, 3), Err(error(3)));
+        assert_eq!(sparse_set.try_swap_by_index(5, 0), Err(error(5)));
+        assert_eq!(sparse_set.data(), &['a', 'b', 'c']);
+        assert_eq!(sparse_set.try_swap_by_index(0, 2), Ok(()));
+        assert_eq!(sparse_set.data(), &['c', 'b', 'a']);
+
+        assert_eq!(sparse_set.try_get_id(3), Err(error(3)));
+        assert_eq!(sparse_set.try_get_id(1), Ok(EntityId::new(2).unwrap()));
+
+        assert!(sparse_set.try_split_data_at_mut(4).is_err());
+        assert!(sparse_set.try_split_data_at_mut(3).is_ok());
+        assert!(sparse_set.try_split_at_mut(4).is_err());
+
+        assert_eq!(sparse_set.try_swap_remove_by_index(3), Err(error(3)));
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.try_swap_remove_by_index(0), Ok('c'));
+        assert_eq!(sparse_set.len(), 2);
+    }
+
+    #[test]
+    fn insert_append_hint_test() {
+        fn check<S>(mut sparse_set: SparseSet<EntityId, usize, S>)
+        where
+            S: SparseStorage<EntityId = EntityId>,
+        {
+            let mut rng = thread_rng();
+            let mut model = BTreeMap::new();
+            let mut next = 1;
+            for round in 0..10_000 {
+                // mostly increasing, sometimes random
+                let id = if rng.gen_bool(0.8) {
+                    next += rng.gen_range(1..4);
+                    next
+                } else {
+                    rng.gen_range(1..next + 10)
+                };
+                let id = EntityId::new(id).unwrap();
+                assert_eq!(
+                    sparse_set.insert_append_hint(id, round),
+                    model.insert(id, round)
+                );
+            }
+            assert_eq!(sparse_set.len(), model.len());
+            assert_eq!(sparse_set.check_invariants(), Ok(()));
+            for (id, x) in &model {
+                assert_eq!(sparse_set.get(*id), Some(x));
+            }
+        }
+
+        check(SparseSetVec::default());
+        check(SparseSetBTreeMap::default());
+        check(SparseSetHashMap::default());
+    }
+
+    #[test]
+    fn to_id_set_test() {
         let mut rng = thread_rng();
-        let count = 100000;
-        // generate unique id
-        let ids = std::iter::from_fn(move || {
-            Some((rng.gen_range(1000..100000), rng.gen_range('a'..='z')))
-        })
-        .map(|(x, c)| (NonZeroUsize::new(x).unwrap(), c))
-        .take(count);
+        let mut sparse_set: SparseSetHashMap<EntityId, u64> = SparseSet::default();
+        for _ in 0..1000 {
+            sparse_set.insert(EntityId::new(rng.gen_range(1..5000)).unwrap(), rng.gen());
+        }
 
-        for (id, c) in ids {
-            sparse_set.insert(id, c);
-            assert!(sparse_set.contains(id));
-            assert_eq!(sparse_set.get(id).copied(), Some(c));
+        let id_set = sparse_set.to_id_set();
+        assert_eq!(id_set.ids(), sparse_set.ids());
+        for x in 1..5000 {
+            let id = EntityId::new(x).unwrap();
+            assert_eq!(id_set.contains(id), sparse_set.contains(id));
+            assert_eq!(id_set.get_index(id), sparse_set.get_index(id));
         }
     }
 
     #[test]
-    fn batch_test() {
-        let mut rng = rand::thread_rng();
-        let mut sparse_set: SparseSet<EntityId, char, VecStorage<EntityId>> = SparseSet::default();
-        let mut set = BTreeSet::new();
+    fn iter_sorted_by_key_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, i32> = SparseSet::default();
+        for _ in 0..1000 {
+            let id = EntityId::new(rng.gen_range(1..2000)).unwrap();
+            sparse_set.insert(id, rng.gen_range(-100..100));
+        }
+        let ids = sparse_set.ids().to_vec();
 
-        let mut ids = Vec::new();
-        let mut data = Vec::new();
+        let mut expected: Vec<_> = sparse_set.iter().collect();
+        expected.sort_by_key(|(_, x)| -**x);
+        let sorted: Vec<_> = sparse_set.iter_sorted_by_key(|x| -*x).collect();
+        assert_eq!(sorted, expected);
 
-        let count = 100_000;
-        for _ in 0..count {
-            'gen_data: loop {
-                let id = rng.gen_range(1..100_000_000);
-                if !set.contains(&id) {
-                    set.insert(id);
-                    let id = EntityId::new(id).unwrap();
-                    let d = rng.gen_range('a'..='z');
+        // untouched
+        assert_eq!(sparse_set.ids(), ids.as_slice());
+    }
 
-                    ids.push(id);
-                    data.push(d);
-                    break 'gen_data;
-                }
+    #[test]
+    fn iter_with_optional_test() {
+        let mut positions: SparseSetVec<EntityId, u32> = SparseSet::default();
+        let mut velocities: SparseSetHashMap<EntityId, i32> = SparseSet::default();
+        for x in 1..=10 {
+            positions.insert(EntityId::new(x).unwrap(), x as u32);
+            if x % 3 == 0 {
+                velocities.insert(EntityId::new(x).unwrap(), -(x as i32));
             }
         }
+        velocities.insert(EntityId::new(100).unwrap(), 100);
 
-        let mut ids_in = ids.clone();
-        let mut data_in = data.clone();
-        sparse_set.insert_batch(&mut ids_in, &mut data_in);
+        let result: Vec<_> = positions.iter_with_optional(&velocities).collect();
+        assert_eq!(result.len(), 10);
+        for (id, position, velocity) in result {
+            assert_eq!(*position as usize, id.get());
+            if id.get() % 3 == 0 {
+                assert_eq!(velocity, Some(&-(id.get() as i32)));
+            } else {
+                assert_eq!(velocity, None);
+            }
+        }
+    }
 
-        assert_eq!(data.len(), sparse_set.len());
-        assert_eq!(&data, sparse_set.data());
+    #[test]
+    fn limits_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        assert_eq!(sparse_set.limits(), SparseSetLimits::default());
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: Some(3),
+            max_sparse_slots: Some(100),
+        });
 
-        for (id, data) in ids.iter().zip(data.iter()) {
-            let ch = sparse_set.get(id.clone());
-            assert!(ch.is_some());
-            assert_eq!(data.clone(), ch.copied().unwrap());
+        for x in 1..=3 {
+            assert_eq!(sparse_set.try_insert(id(x), x), Ok(None));
+        }
+        assert_eq!(
+            sparse_set.try_insert(id(4), 4),
+            Err(LimitExceeded::MaxLen(3))
+        );
+        // replacing doesn't grow
+        assert_eq!(sparse_set.try_insert(id(2), 20), Ok(Some(2)));
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // removing frees headroom
+        sparse_set.swap_remove_by_id(id(1));
+        assert_eq!(
+            sparse_set.try_insert(id(100), 100),
+            Err(LimitExceeded::MaxSparseSlots(100))
+        );
+        assert_eq!(sparse_set.try_insert(id(99), 99), Ok(None));
+
+        sparse_set.swap_remove_by_id(id(99));
+        sparse_set.swap_remove_by_id(id(3));
+        let mut ids = vec![id(50), id(60), id(70)];
+        let mut data = vec![50, 60, 70];
+        assert_eq!(
+            sparse_set.try_insert_batch(&mut ids, &mut data),
+            Err(LimitExceeded::MaxLen(3))
+        );
+        assert_eq!(ids.len(), 3);
+        assert_eq!(sparse_set.len(), 1);
+        ids.pop();
+        data.pop();
+        assert_eq!(sparse_set.try_insert_batch(&mut ids, &mut data), Ok(()));
+        assert_eq!(sparse_set.len(), 3);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        let (sparse, _, _) = sparse_set.into_raw_parts();
+        assert!(sparse.slots_after(id(1)).unwrap() <= 100);
+    }
+
+    #[test]
+    fn try_extend_limits_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: Some(3),
+            max_sparse_slots: None,
+        });
+        sparse_set.insert(id(1), 1);
+
+        // a huge size hint doesn't reserve past max_len
+        let mut pairs = (2..1_000_000).map(|x| (id(x), x));
+        assert_eq!(
+            sparse_set.try_extend(pairs.by_ref()),
+            Err(LimitExceeded::MaxLen(3))
+        );
+        assert!(sparse_set.capacity() < 100);
+        assert_eq!(sparse_set.ids(), &[id(1), id(2), id(3)]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        // the pairs after the failing one are not consumed
+        assert_eq!(pairs.next(), Some((id(5), 5)));
+
+        // replacing doesn't grow
+        assert_eq!(sparse_set.try_extend([(id(2), 20), (id(3), 30)]), Ok(()));
+        assert_eq!(sparse_set.data(), &[1, 20, 30]);
+    }
+
+    #[test]
+    #[should_panic(expected = "the count of entities exceeds 1")]
+    fn extend_limits_panic_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: Some(1),
+            max_sparse_slots: None,
+        });
+        sparse_set.extend((1..10).map(|x| (EntityId::new(x).unwrap(), x)));
+    }
+
+    #[test]
+    #[should_panic]
+    fn limits_panic_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        sparse_set.set_limits(SparseSetLimits {
+            max_len: Some(0),
+            max_sparse_slots: None,
+        });
+        sparse_set.insert(EntityId::new(1).unwrap(), 1);
+    }
+
+    #[test]
+    fn would_be_index_after_sort_by_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        for x in 1..=500 {
+            sparse_set.insert(EntityId::new(x).unwrap(), rng.gen_range(0..50));
+        }
+
+        let mut sorted: Vec<_> = sparse_set.iter().collect();
+        sorted.sort_by_key(|(_, x)| **x);
+        for (index, (id, _)) in sorted.iter().enumerate() {
+            assert_eq!(
+                sparse_set.would_be_index_after_sort_by(*id, |x| *x),
+                Some(index)
+            );
+        }
+        assert_eq!(
+            sparse_set.would_be_index_after_sort_by(EntityId::new(501).unwrap(), |x| *x),
+            None
+        );
+    }
+
+    #[test]
+    fn extend_from_slices_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, char> = SparseSet::default();
+        sparse_set.insert(id(1), 'a');
+        sparse_set.insert(id(2), 'b');
+
+        sparse_set.extend_from_slices(&[id(2), id(3), id(4), id(3)], &['x', 'y', 'z', 'w']);
+
+        assert_eq!(sparse_set.len(), 4);
+        assert_eq!(sparse_set.ids(), &[id(1), id(2), id(3), id(4)]);
+        assert_eq!(sparse_set.data(), &['a', 'x', 'w', 'z']);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    #[should_panic]
+    fn extend_from_slices_mismatch_test() {
+        let mut sparse_set: SparseSetVec<EntityId, char> = SparseSet::default();
+        sparse_set.extend_from_slices(&[EntityId::new(1).unwrap()], &[]);
+    }
+
+    #[test]
+    fn get_or_try_insert_with_test() {
+        let id = |x| EntityId::new(x).unwrap();
+        let mut sparse_set: SparseSetVec<EntityId, u32> = SparseSet::default();
+        sparse_set.insert(id(1), 1);
+
+        // hit doesn't call the factory
+        let dat = sparse_set.get_or_try_insert_with(id(1), || -> Result<u32, ()> {
+            panic!("factory called on hit")
+        });
+        *dat.unwrap() += 10;
+        assert_eq!(sparse_set.get(id(1)), Some(&11));
+
+        // error inserts nothing
+        let result = sparse_set.get_or_try_insert_with(id(2), || Err("failed"));
+        assert_eq!(result, Err("failed"));
+        assert!(!sparse_set.contains(id(2)));
+        assert_eq!(sparse_set.len(), 1);
+
+        // ok inserts
+        let dat = sparse_set.get_or_try_insert_with(id(2), || Ok::<_, ()>(2));
+        assert_eq!(dat, Ok(&mut 2));
+        assert_eq!(sparse_set.get(id(2)), Some(&2));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn sort_by_id_order_test() {
+        let mut sparse_set: SparseSetHashMap<u32, u32> = SparseSet::default();
+        for x in 0..10 {
+            sparse_set.insert(x, x * 10);
         }
+
+        // partial, with ids not in sparse set
+        sparse_set.sort_by_id_order(&[7, 100, 2, 5]).unwrap();
+        assert_eq!(sparse_set.ids(), &[7, 2, 5, 0, 1, 3, 4, 6, 8, 9]);
+        assert_eq!(sparse_set.data(), &[70, 20, 50, 0, 10, 30, 40, 60, 80, 90]);
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // complete
+        let order: Vec<u32> = (0..10).rev().collect();
+        sparse_set.sort_by_id_order(&order).unwrap();
+        assert_eq!(sparse_set.ids(), order.as_slice());
+        assert!(sparse_set.iter().all(|(id, x)| *x == id * 10));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+
+        // duplicated
+        assert_eq!(
+            sparse_set.sort_by_id_order(&[1, 2, 1]),
+            Err(OrderError::DuplicateId(1))
+        );
+        assert_eq!(sparse_set.ids(), order.as_slice());
+        assert_eq!(sparse_set.sort_by_id_order(&[100, 100]), Ok(()));
+    }
+
+    #[test]
+    fn union_with_test() {
+        let mut a: SparseSet<EntityId, u32, VecStorage<EntityId>> = SparseSet::default();
+        let mut b: SparseSet<EntityId, u32, VecStorage<EntityId>> = SparseSet::default();
+        let id = |x| EntityId::new(x).unwrap();
+
+        a.insert(id(1), 1);
+        a.insert(id(2), 2);
+        b.insert(id(3), 30);
+        b.insert(id(2), 20);
+
+        a.union_with(&b, |_, x, y| *x += *y);
+
+        assert_eq!(a.ids(), &[id(1), id(2), id(3)]);
+        assert_eq!(a.data(), &[1, 22, 30]);
     }
 }
@@ -0,0 +1,56 @@
+use crate::{LimitExceeded, SparseSet, SparseStorage};
+
+/// The limits of a `SparseSet`
+/// # Details
+/// `None` means unlimited, which is the default
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SparseSetLimits {
+    /// The max count of entities
+    pub max_len: Option<usize>,
+    /// The max count of slots in the sparse storage,
+    /// only checked for storages with slots like `VecStorage`
+    pub max_sparse_slots: Option<usize>,
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Set the limits of sparse set
+    /// # Details
+    /// The entities already in sparse set are kept even if they exceed the limits.  
+    /// `try_insert` and `try_insert_batch` return `Err` when growing past the limits,
+    /// `insert` and `insert_batch` panic.  
+    /// Removing entities frees headroom
+    pub fn set_limits(&mut self, limits: SparseSetLimits) {
+        self.limits = limits;
+    }
+
+    /// Get the limits of sparse set
+    pub fn limits(&self) -> SparseSetLimits {
+        self.limits
+    }
+
+    /// Check if `additional` new entities with `ids` can be added
+    pub(crate) fn check_limits(&self, additional: usize, ids: &[E]) -> Result<(), LimitExceeded> {
+        if !ids.iter().all(|id| self.sparse.is_addressable(*id)) {
+            return Err(LimitExceeded::AddressSpace);
+        }
+        if let Some(max_len) = self.limits.max_len {
+            if self.len() + additional > max_len {
+                return Err(LimitExceeded::MaxLen(max_len));
+            }
+        }
+        if let Some(max_sparse_slots) = self.limits.max_sparse_slots {
+            let slots = ids
+                .iter()
+                .filter_map(|id| self.sparse.slots_after(*id))
+                .max();
+            if slots.is_some_and(|slots| slots > max_sparse_slots) {
+                return Err(LimitExceeded::MaxSparseSlots(max_sparse_slots));
+            }
+        }
+        Ok(())
+    }
+}
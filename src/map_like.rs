@@ -0,0 +1,147 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::{BuildHasher, Hash},
+};
+
+use crate::{SparseSet, SparseStorage};
+
+/// The common interface of maps from id to data
+/// # Details
+/// Write generic code against it to swap a `HashMap` and a `SparseSet`
+pub trait MapLike<E, T> {
+    /// Get the reference of data by given `id`
+    fn get(&self, id: E) -> Option<&T>;
+
+    /// Insert the `dat` with `id`
+    /// # Returns
+    /// Return the old data if it has this id
+    fn insert(&mut self, id: E, dat: T) -> Option<T>;
+
+    /// Remove the data by given `id`
+    /// # Details
+    /// `SparseSet` removes by swapping, so the dense order is changed
+    fn remove(&mut self, id: E) -> Option<T>;
+
+    /// Check if it has `id`
+    fn contains(&self, id: E) -> bool;
+
+    /// Get the count of ids
+    fn len(&self) -> usize;
+
+    /// Check if it is empty
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<E, T, S> MapLike<E, T> for SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    fn get(&self, id: E) -> Option<&T> {
+        SparseSet::get(self, id)
+    }
+
+    fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        SparseSet::insert(self, id, dat)
+    }
+
+    fn remove(&mut self, id: E) -> Option<T> {
+        self.swap_remove_by_id(id)
+    }
+
+    fn contains(&self, id: E) -> bool {
+        SparseSet::contains(self, id)
+    }
+
+    fn len(&self) -> usize {
+        SparseSet::len(self)
+    }
+}
+
+impl<E, T, H> MapLike<E, T> for HashMap<E, T, H>
+where
+    E: Hash + Eq,
+    H: BuildHasher,
+{
+    fn get(&self, id: E) -> Option<&T> {
+        HashMap::get(self, &id)
+    }
+
+    fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        HashMap::insert(self, id, dat)
+    }
+
+    fn remove(&mut self, id: E) -> Option<T> {
+        HashMap::remove(self, &id)
+    }
+
+    fn contains(&self, id: E) -> bool {
+        self.contains_key(&id)
+    }
+
+    fn len(&self) -> usize {
+        HashMap::len(self)
+    }
+}
+
+impl<E, T> MapLike<E, T> for BTreeMap<E, T>
+where
+    E: Ord,
+{
+    fn get(&self, id: E) -> Option<&T> {
+        BTreeMap::get(self, &id)
+    }
+
+    fn insert(&mut self, id: E, dat: T) -> Option<T> {
+        BTreeMap::insert(self, id, dat)
+    }
+
+    fn remove(&mut self, id: E) -> Option<T> {
+        BTreeMap::remove(self, &id)
+    }
+
+    fn contains(&self, id: E) -> bool {
+        self.contains_key(&id)
+    }
+
+    fn len(&self) -> usize {
+        BTreeMap::len(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+
+    use crate::{MapLike, SparseSet, SparseSetVec};
+
+    fn churn<M>(map: &mut M) -> Vec<Option<u32>>
+    where
+        M: MapLike<usize, u32>,
+    {
+        for id in 0..100 {
+            map.insert(id, id as u32);
+        }
+        for id in (0..100).step_by(3) {
+            map.remove(id);
+        }
+        assert_eq!(map.insert(1, 10), Some(1));
+        assert!(!map.contains(3));
+        assert!(map.contains(4));
+        assert_eq!(map.len(), 66);
+        assert!(!map.is_empty());
+        (0..120).map(|id| map.get(id).copied()).collect()
+    }
+
+    #[test]
+    fn map_like_test() {
+        let mut sparse_set: SparseSetVec<usize, u32> = SparseSet::default();
+        let mut hash_map = HashMap::new();
+        let mut btree_map = BTreeMap::new();
+        let expected = churn(&mut hash_map);
+        assert_eq!(churn(&mut sparse_set), expected);
+        assert_eq!(churn(&mut btree_map), expected);
+    }
+}
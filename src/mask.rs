@@ -0,0 +1,85 @@
+use fixedbitset::FixedBitSet;
+
+use crate::{SparseSet, SparseStorage};
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + Into<usize>,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Iterate `(id, &mut data)` pairs whose id bit is set in `mask`
+    /// # Details
+    /// Ids out of the range of `mask` count as unset
+    pub fn iter_mut_masked<'a>(
+        &'a mut self,
+        mask: &'a FixedBitSet,
+    ) -> impl Iterator<Item = (E, &'a mut T)> + 'a {
+        self.dense
+            .iter()
+            .zip(self.data.iter_mut())
+            .filter(move |(id, _)| mask.contains((**id).into()))
+            .map(|(id, dat)| (*id, dat))
+    }
+
+    /// Remove all entities whose id bit in `mask` is not `keep_if_set`
+    /// # Details
+    /// Ids out of the range of `mask` count as unset
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain_by_mask(&mut self, mask: &FixedBitSet, keep_if_set: bool) -> usize {
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let id = unsafe { *self.dense.get_unchecked(index) };
+            if mask.contains(id.into()) != keep_if_set {
+                self.swap_remove_by_index(index);
+                count += 1;
+            }
+        }
+        count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use fixedbitset::FixedBitSet;
+    use rand::{thread_rng, Rng};
+
+    use crate::SparseSetVec;
+
+    #[test]
+    fn mask_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<usize, u32> = SparseSetVec::default();
+        for _ in 0..1000 {
+            sparse_set.insert(rng.gen_range(0..2000), 0);
+        }
+        // ids >= 1500 are out of the mask
+        let mut mask = FixedBitSet::with_capacity(1500);
+        for _ in 0..500 {
+            mask.insert(rng.gen_range(0..1500));
+        }
+        let is_member = |id: usize| id < 1500 && mask[id];
+
+        let mut masked = sparse_set.clone();
+        for (_, x) in masked.iter_mut_masked(&mask) {
+            *x += 1;
+        }
+        for (id, x) in masked.iter() {
+            assert_eq!(*x, is_member(id) as u32);
+        }
+
+        let mut kept = sparse_set.clone();
+        let removed = kept.retain_by_mask(&mask, true);
+        let expected = sparse_set.ids().iter().filter(|id| is_member(**id)).count();
+        assert_eq!(kept.len(), expected);
+        assert_eq!(removed, sparse_set.len() - expected);
+        assert!(kept.ids().iter().all(|id| is_member(*id)));
+
+        let mut dropped = sparse_set.clone();
+        dropped.retain_by_mask(&mask, false);
+        assert_eq!(dropped.len(), sparse_set.len() - expected);
+        assert!(dropped.ids().iter().all(|id| !is_member(*id)));
+    }
+}
@@ -0,0 +1,301 @@
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A value of `SparseMultiSet` linked with the other values of its id
+#[derive(Debug, Clone)]
+struct Node<E, T> {
+    owner: E,
+    value: T,
+    prev: Option<usize>,
+    next: Option<usize>,
+}
+
+/// The linked list of values of an id
+#[derive(Debug, Clone, Copy)]
+struct Group {
+    head: usize,
+    tail: usize,
+    len: usize,
+}
+
+/// A sparse set mapping an id to many values
+/// # Details
+/// All values are stored in one dense Vec of nodes,
+/// the values of an id are linked in push order
+/// and the sparse side maps the id to the head and tail of its list.
+///
+/// Removing a value swap-removes its node, then relinks the node moved into the hole,
+/// so the nodes are always compact and every removal is O(1) after the node is found.
+/// The values of an id are not contiguous in memory
+#[derive(Debug, Clone)]
+pub struct SparseMultiSet<E, T, S = VecStorage<E>> {
+    groups: SparseSet<E, Group, S>,
+    nodes: Vec<Node<E, T>>,
+}
+
+/// The iterator of values of an id in `SparseMultiSet`
+#[derive(Debug, Clone)]
+pub struct MultiValues<'a, E, T> {
+    nodes: &'a [Node<E, T>],
+    next: Option<usize>,
+    remaining: usize,
+}
+
+impl<'a, E, T> Iterator for MultiValues<'a, E, T> {
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = &self.nodes[self.next?];
+        self.next = node.next;
+        self.remaining -= 1;
+        Some(&node.value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, E, T> ExactSizeIterator for MultiValues<'a, E, T> {}
+
+impl<E, T, S> Default for SparseMultiSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E> + Default,
+{
+    fn default() -> Self {
+        SparseMultiSet {
+            groups: SparseSet::default(),
+            nodes: Vec::new(),
+        }
+    }
+}
+
+impl<E, T, S> SparseMultiSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Append the `value` to the values of `id`
+    pub fn push(&mut self, id: E, value: T) {
+        let index = self.nodes.len();
+        let prev = match self.groups.get_mut(id) {
+            Some(group) => {
+                let tail = group.tail;
+                group.tail = index;
+                group.len += 1;
+                Some(tail)
+            }
+            None => {
+                self.groups.insert(
+                    id,
+                    Group {
+                        head: index,
+                        tail: index,
+                        len: 1,
+                    },
+                );
+                None
+            }
+        };
+        if let Some(prev) = prev {
+            self.nodes[prev].next = Some(index);
+        }
+        self.nodes.push(Node {
+            owner: id,
+            value,
+            prev,
+            next: None,
+        });
+    }
+
+    /// Iterate the values of `id` in push order
+    /// # Details
+    /// It is empty if the set doesn't contain `id`
+    pub fn values(&self, id: E) -> MultiValues<'_, E, T> {
+        let group = self.groups.get(id);
+        MultiValues {
+            nodes: &self.nodes,
+            next: group.map(|group| group.head),
+            remaining: group.map_or(0, |group| group.len),
+        }
+    }
+
+    /// Get the count of values of `id`
+    pub fn count(&self, id: E) -> usize {
+        self.groups.get(id).map_or(0, |group| group.len)
+    }
+
+    /// Check if `id` has any value
+    pub fn contains(&self, id: E) -> bool {
+        self.groups.contains(id)
+    }
+
+    /// Remove the `index`-th value of `id` in push order
+    /// # Details
+    /// It walks the values of `id`, so it is O(`index`)
+    /// # Returns
+    /// Return None if `id` has no more than `index` values
+    pub fn remove_one(&mut self, id: E, index: usize) -> Option<T> {
+        let group = self.groups.get(id)?;
+        if index >= group.len {
+            return None;
+        }
+        let mut node = group.head;
+        for _ in 0..index {
+            node = self.nodes[node].next?;
+        }
+        Some(self.remove_node(node))
+    }
+
+    /// Remove all values of `id`
+    /// # Returns
+    /// Return the values in push order, it is empty if `id` has no value
+    pub fn remove_all(&mut self, id: E) -> Vec<T> {
+        let mut values = Vec::with_capacity(self.count(id));
+        // the head is read again each time because a removal may move it
+        while let Some(group) = self.groups.get(id) {
+            let head = group.head;
+            values.push(self.remove_node(head));
+        }
+        values
+    }
+
+    /// Unlink the node, then swap-remove it and relink the node moved into its place
+    fn remove_node(&mut self, index: usize) -> T {
+        let (owner, prev, next) = {
+            let node = &self.nodes[index];
+            (node.owner, node.prev, node.next)
+        };
+        let group = self
+            .groups
+            .get_mut(owner)
+            .expect("the owner of a node has a group");
+        group.len -= 1;
+        if group.len == 0 {
+            self.groups.swap_remove_by_id(owner);
+        } else {
+            match prev {
+                Some(prev) => self.nodes[prev].next = next,
+                None => group.head = next.expect("a non-empty group has a next node"),
+            }
+            match next {
+                Some(next) => self.nodes[next].prev = prev,
+                None => group.tail = prev.expect("a non-empty group has a prev node"),
+            }
+        }
+
+        let node = self.nodes.swap_remove(index);
+        if index < self.nodes.len() {
+            // the last node is moved to index
+            let (owner, prev, next) = {
+                let node = &self.nodes[index];
+                (node.owner, node.prev, node.next)
+            };
+            let group = self
+                .groups
+                .get_mut(owner)
+                .expect("the owner of a node has a group");
+            match prev {
+                Some(prev) => self.nodes[prev].next = Some(index),
+                None => group.head = index,
+            }
+            match next {
+                Some(next) => self.nodes[next].prev = Some(index),
+                None => group.tail = index,
+            }
+        }
+        node.value
+    }
+
+    /// Iterate `(id, values)` grouped by id
+    pub fn iter(&self) -> impl Iterator<Item = (E, MultiValues<'_, E, T>)> {
+        self.groups.iter().map(move |(id, group)| {
+            (
+                id,
+                MultiValues {
+                    nodes: &self.nodes,
+                    next: Some(group.head),
+                    remaining: group.len,
+                },
+            )
+        })
+    }
+
+    /// Get the slice of ids having values
+    pub fn ids(&self) -> &[E] {
+        self.groups.ids()
+    }
+
+    /// Get the count of ids having values
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    /// Get the count of values of all ids
+    pub fn len_values(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Check if there is no value
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Remove all values
+    pub fn clear(&mut self) {
+        self.groups.clear();
+        self.nodes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::SparseMultiSet;
+
+    fn values(set: &SparseMultiSet<usize, u32>, id: usize) -> Vec<u32> {
+        set.values(id).copied().collect()
+    }
+
+    #[test]
+    fn multi_set_test() {
+        let mut effects: SparseMultiSet<usize, u32> = SparseMultiSet::default();
+        for value in 0..12 {
+            effects.push([3, 8, 5][value as usize % 3], value);
+        }
+        assert_eq!(effects.len(), 3);
+        assert_eq!(effects.len_values(), 12);
+        assert_eq!(values(&effects, 3), vec![0, 3, 6, 9]);
+        assert_eq!(values(&effects, 8), vec![1, 4, 7, 10]);
+        assert_eq!(effects.values(8).len(), 4);
+        assert_eq!(values(&effects, 100), vec![]);
+
+        // head, middle and tail
+        assert_eq!(effects.remove_one(8, 0), Some(1));
+        assert_eq!(effects.remove_one(8, 1), Some(7));
+        assert_eq!(effects.remove_one(8, 1), Some(10));
+        assert_eq!(effects.remove_one(8, 1), None);
+        assert_eq!(effects.remove_one(100, 0), None);
+        assert_eq!(values(&effects, 8), vec![4]);
+        assert_eq!(values(&effects, 3), vec![0, 3, 6, 9]);
+        assert_eq!(values(&effects, 5), vec![2, 5, 8, 11]);
+
+        effects.push(8, 12);
+        assert_eq!(effects.remove_all(3), vec![0, 3, 6, 9]);
+        assert!(!effects.contains(3));
+        assert_eq!(effects.remove_all(3), vec![]);
+        assert_eq!(effects.remove_one(8, 0), Some(4));
+        assert_eq!(effects.remove_one(8, 0), Some(12));
+        assert!(!effects.contains(8));
+
+        effects.push(3, 13);
+        let grouped: Vec<(usize, Vec<u32>)> = effects
+            .iter()
+            .map(|(id, values)| (id, values.copied().collect()))
+            .collect();
+        assert_eq!(grouped, vec![(5, vec![2, 5, 8, 11]), (3, vec![13])]);
+        assert_eq!(effects.len_values(), 5);
+
+        effects.clear();
+        assert!(effects.is_empty());
+    }
+}
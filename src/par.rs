@@ -0,0 +1,109 @@
+use rayon::prelude::*;
+
+use crate::{first_new_index, SparseSet, SparseStorage};
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + Send + Sync,
+    T: Send + Sync,
+    S: SparseStorage<EntityId = E> + Sync,
+{
+    /// Merge all entities of `other` into this sparse set in parallel
+    /// # Details
+    /// The result is identical to `union_with`, except the call order of `resolver`:
+    /// * Entities only in `other` are cloned and appended in the dense order of `other`
+    /// * Entities in both sets call `resolver(id, &mut self_data, &other_data)`
+    ///   in an unspecified order and possibly on several threads at once
+    /// # Panics
+    /// Panic if it grows past the limits set by `set_limits`
+    pub fn par_union_with<S2, F>(&mut self, other: &SparseSet<E, T, S2>, resolver: F)
+    where
+        T: Clone,
+        S2: SparseStorage<EntityId = E> + Sync,
+        F: Fn(E, &mut T, &T) + Sync,
+    {
+        // classify the entities of other, only reads self
+        let classified: Vec<(usize, Option<usize>)> = other
+            .dense
+            .par_iter()
+            .enumerate()
+            .map(|(other_index, id)| (other_index, self.get_index(*id)))
+            .collect();
+
+        // collisions: map the index in self to the index in other
+        // every entity in other maps to a unique index in self,
+        // so each data in self is touched at most once
+        let mut collisions = vec![None; self.len()];
+        let mut fresh_ids = Vec::new();
+        let mut fresh_data = Vec::new();
+        for (other_index, self_index) in classified {
+            if let Some(self_index) = self_index {
+                collisions[self_index] = Some(other_index);
+            } else {
+                // Safety
+                // other_index comes from enumerate of other.dense
+                unsafe {
+                    fresh_ids.push(*other.dense.get_unchecked(other_index));
+                    fresh_data.push(other.data.get_unchecked(other_index).clone());
+                }
+            }
+        }
+
+        if let Err(error) = self.check_limits(fresh_ids.len(), &fresh_ids) {
+            panic!("{}{}", self.name_prefix(), error);
+        }
+
+        self.dense
+            .par_iter()
+            .zip(self.data.par_iter_mut())
+            .zip(collisions.par_iter())
+            .for_each(|((id, data), other_index)| {
+                if let Some(other_index) = other_index {
+                    // Safety
+                    // other_index comes from enumerate of other.dense
+                    let other_data = unsafe { other.data.get_unchecked(*other_index) };
+                    resolver(*id, data, other_data);
+                }
+            });
+
+        let start_index = first_new_index(self.len(), fresh_ids.len());
+        self.sparse.set_indices(&fresh_ids, start_index);
+        self.dense.append(&mut fresh_ids);
+        self.data.append(&mut fresh_data);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::SparseSetVec;
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn par_union_with_test() {
+        let mut rng = thread_rng();
+        let mut a: SparseSetVec<EntityId, u32> = SparseSetVec::default();
+        let mut b: SparseSetVec<EntityId, u32> = SparseSetVec::default();
+        for _ in 0..10_000 {
+            let id = EntityId::new(rng.gen_range(1..20_000)).unwrap();
+            a.insert(id, rng.gen_range(0..1000));
+            let id = EntityId::new(rng.gen_range(1..20_000)).unwrap();
+            b.insert(id, rng.gen_range(0..1000));
+        }
+
+        let mut sequential = a.clone();
+        sequential.union_with(&b, |_, x, y| *x += *y);
+        let mut parallel = a.clone();
+        parallel.par_union_with(&b, |_, x, y| *x += *y);
+
+        assert_eq!(sequential.ids(), parallel.ids());
+        assert_eq!(sequential.data(), parallel.data());
+        for (id, data) in parallel.ids().iter().zip(parallel.data().iter()) {
+            assert_eq!(parallel.get(*id), Some(data));
+        }
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{IndexOutOfRange, IterMut, SparseSet, SparseStorage};
+
+/// A part of the dense arrays of `SparseSet` with mutable data
+/// # Details
+/// It can only change the data, never the ids or the layout,
+/// so disjoint partitions can be sent to different threads
+#[derive(Debug)]
+pub struct SparsePartitionMut<'a, E, T> {
+    ids: &'a [E],
+    data: &'a mut [T],
+}
+
+impl<'a, E, T> SparsePartitionMut<'a, E, T>
+where
+    E: Copy,
+{
+    /// Get the count of entities in partition
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check partition is empty
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Get the slice of ID in partition
+    pub fn ids(&self) -> &[E] {
+        self.ids
+    }
+
+    /// Get the slice of data in partition
+    pub fn data(&self) -> &[T] {
+        self.data
+    }
+
+    /// Get the mutable slice of data in partition
+    pub fn data_mut(&mut self) -> &mut [T] {
+        self.data
+    }
+
+    /// Iterate `(id, &mut data)` pairs in partition
+    pub fn iter_mut(&mut self) -> IterMut<'_, E, T> {
+        IterMut {
+            ids: self.ids.iter(),
+            data: self.data.iter_mut(),
+        }
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Split sparse set into 2 partitions at dense index `mid`
+    /// # Panics
+    /// Panic if `mid > self.len()`
+    pub fn split_at_mut(
+        &mut self,
+        mid: usize,
+    ) -> (SparsePartitionMut<'_, E, T>, SparsePartitionMut<'_, E, T>) {
+        let ((ids_a, data_a), (ids_b, data_b)) = self.split_data_at_mut(mid);
+        (
+            SparsePartitionMut {
+                ids: ids_a,
+                data: data_a,
+            },
+            SparsePartitionMut {
+                ids: ids_b,
+                data: data_b,
+            },
+        )
+    }
+
+    /// Split sparse set into `n` partitions with near-equal length
+    /// # Details
+    /// The first `len % n` partitions have 1 more entity than the others.
+    /// Empty partitions are skipped, so there are fewer than `n` partitions if `len < n`
+    /// # Panics
+    /// Panic if `n` is 0
+    pub fn partitions_mut(
+        &mut self,
+        n: usize,
+    ) -> impl ExactSizeIterator<Item = SparsePartitionMut<'_, E, T>> {
+        if n == 0 {
+            panic!("n must not be 0");
+        }
+        let len = self.len();
+        let (base, extra) = (len / n, len % n);
+        let mut ids = self.dense.as_slice();
+        let mut data = self.data.as_mut_slice();
+        (0..n.min(len)).map(move |part| {
+            let part_len = base + usize::from(part < extra);
+            let (part_ids, rest_ids) = ids.split_at(part_len);
+            let (part_data, rest_data) = std::mem::take(&mut data).split_at_mut(part_len);
+            ids = rest_ids;
+            data = rest_data;
+            SparsePartitionMut {
+                ids: part_ids,
+                data: part_data,
+            }
+        })
+    }
+
+    /// Split sparse set into 2 partitions at dense index `mid`
+    /// # Returns
+    /// Return Err if `mid > self.len()`
+    #[allow(clippy::type_complexity)]
+    pub fn try_split_at_mut(
+        &mut self,
+        mid: usize,
+    ) -> Result<(SparsePartitionMut<'_, E, T>, SparsePartitionMut<'_, E, T>), IndexOutOfRange> {
+        if mid > self.len() {
+            return Err(IndexOutOfRange {
+                index: mid,
+                len: self.len(),
+            });
+        }
+        Ok(self.split_at_mut(mid))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use crate::SparseSetVec;
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn split_at_mut_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSetVec::default();
+        for x in (1..=100).rev() {
+            sparse_set.insert(EntityId::new(x).unwrap(), x);
+        }
+
+        let (mut left, mut right) = sparse_set.split_at_mut(40);
+        assert_eq!(left.len(), 40);
+        assert_eq!(right.len(), 60);
+        std::thread::scope(|scope| {
+            scope.spawn(|| {
+                for (id, x) in left.iter_mut() {
+                    *x += id.get();
+                }
+            });
+            scope.spawn(|| {
+                for x in right.data_mut() {
+                    *x = 0;
+                }
+            });
+        });
+
+        for x in 1..=100 {
+            let expected = if x > 60 { 2 * x } else { 0 };
+            assert_eq!(sparse_set.get(EntityId::new(x).unwrap()), Some(&expected));
+        }
+
+        let (empty, all) = sparse_set.split_at_mut(0);
+        assert!(empty.is_empty());
+        assert_eq!(all.len(), 100);
+    }
+
+    #[test]
+    fn partitions_mut_test() {
+        for len in [0, 1, 7, 10, 100] {
+            for n in [1, 2, 3, 10, 150] {
+                let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSetVec::default();
+                for x in 1..=len {
+                    sparse_set.insert(EntityId::new(x).unwrap(), 0);
+                }
+
+                let mut partitions: Vec<_> = sparse_set.partitions_mut(n).collect();
+                assert_eq!(partitions.len(), n.min(len));
+                let lens: Vec<_> = partitions.iter().map(|part| part.len()).collect();
+                assert!(lens.iter().all(|part_len| *part_len > 0));
+                assert_eq!(lens.iter().sum::<usize>(), len);
+                assert!(lens.windows(2).all(|w| w[0] == w[1] || w[0] == w[1] + 1));
+
+                std::thread::scope(|scope| {
+                    for (index, part) in partitions.iter_mut().enumerate() {
+                        scope.spawn(move || {
+                            for (_, x) in part.iter_mut() {
+                                *x += index + 1;
+                            }
+                        });
+                    }
+                });
+
+                // every entity is visited by exactly one partition, in dense order
+                let visited: Vec<_> = sparse_set.data().to_vec();
+                assert!(visited.iter().all(|x| *x > 0));
+                assert!(visited.windows(2).all(|w| w[0] <= w[1]));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn split_at_mut_out_of_range_test() {
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSetVec::default();
+        sparse_set.split_at_mut(1);
+    }
+}
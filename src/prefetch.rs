@@ -0,0 +1,54 @@
+/// Hint the CPU to load the cache line of `ptr` for reading
+/// # Details
+/// It never dereferences `ptr`, so any address is fine.
+/// It does nothing on targets without a prefetch instruction
+#[inline(always)]
+pub(crate) fn prefetch_read<P>(ptr: *const P) {
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        #[cfg(target_arch = "x86")]
+        use std::arch::x86::{_mm_prefetch, _MM_HINT_T0};
+        #[cfg(target_arch = "x86_64")]
+        use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+
+        // Safety
+        // prefetch is only a hint, it never faults
+        unsafe { _mm_prefetch::<_MM_HINT_T0>(ptr as *const i8) };
+    }
+    #[cfg(target_arch = "aarch64")]
+    {
+        // Safety
+        // prfm is only a hint, it never faults
+        unsafe {
+            std::arch::asm!(
+                "prfm pldl1keep, [{ptr}]",
+                ptr = in(reg) ptr,
+                options(nostack, preserves_flags, readonly)
+            );
+        }
+    }
+    #[cfg(not(any(target_arch = "x86", target_arch = "x86_64", target_arch = "aarch64")))]
+    {
+        let _ = ptr;
+    }
+}
+
+/// The stride of `touch_spare_capacity`, the smallest common page size
+const PAGE_SIZE: usize = 4096;
+
+/// Write a byte into every page of the spare capacity of `vec`,
+/// so the OS maps the pages before they are used
+/// # Details
+/// The pages of the initialized elements are mapped when they were written,
+/// only the spare capacity may be unmapped
+pub(crate) fn touch_spare_capacity<T>(vec: &mut Vec<T>) {
+    let spare = vec.spare_capacity_mut();
+    let bytes = std::mem::size_of_val(spare);
+    let ptr = spare.as_mut_ptr() as *mut u8;
+    for offset in (0..bytes).step_by(PAGE_SIZE) {
+        // Safety
+        // The offset is in the spare capacity, which is uninitialized memory owned by vec.
+        // Writing bytes into it is fine, it stays uninitialized for vec
+        unsafe { std::ptr::write_volatile(ptr.add(offset), 0) };
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    collections::HashMap,
+};
+
+use crate::{SparseSet, SparseStorage, VecStorage};
+
+/// A sparse set with its data type erased
+/// # Details
+/// It is implemented for every `SparseSet` with `'static` types,
+/// use `as_any` to downcast back to the concrete set
+pub trait DynSparseSet<E>: Any {
+    /// Get the count of entities
+    fn len(&self) -> usize;
+
+    /// Check if there is no entity
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if it has id
+    fn contains(&self, id: E) -> bool;
+
+    /// Remove the entity and drop its data
+    /// # Returns
+    /// Return false if it doesn't have id
+    fn remove_entity(&mut self, id: E) -> bool;
+
+    /// Remove all entities
+    fn clear(&mut self);
+
+    /// Get the slice of ids
+    fn ids(&self) -> &[E];
+
+    /// Cast to `&dyn Any` for downcasting
+    fn as_any(&self) -> &dyn Any;
+
+    /// Cast to `&mut dyn Any` for downcasting
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<E, T, S> DynSparseSet<E> for SparseSet<E, T, S>
+where
+    E: Copy + 'static,
+    T: 'static,
+    S: SparseStorage<EntityId = E> + 'static,
+{
+    fn len(&self) -> usize {
+        SparseSet::len(self)
+    }
+
+    fn contains(&self, id: E) -> bool {
+        SparseSet::contains(self, id)
+    }
+
+    fn remove_entity(&mut self, id: E) -> bool {
+        self.swap_remove_by_id(id).is_some()
+    }
+
+    fn clear(&mut self) {
+        SparseSet::clear(self)
+    }
+
+    fn ids(&self) -> &[E] {
+        SparseSet::ids(self)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Sparse sets keyed by their data type, like the component storages of an ECS
+/// # Details
+/// Every registered set uses the sparse storage `S`
+pub struct SparseSetRegistry<E, S = VecStorage<E>> {
+    /// The sets in registration order with their type names
+    sets: Vec<(&'static str, Box<dyn DynSparseSet<E>>)>,
+    /// Map the `TypeId` of data to the position in `sets`
+    positions: HashMap<TypeId, usize>,
+    _marker: std::marker::PhantomData<S>,
+}
+
+impl<E, S> Default for SparseSetRegistry<E, S> {
+    fn default() -> Self {
+        SparseSetRegistry {
+            sets: Vec::new(),
+            positions: HashMap::new(),
+            _marker: std::marker::PhantomData,
+        }
+    }
+}
+
+impl<E, S> std::fmt::Debug for SparseSetRegistry<E, S>
+where
+    E: 'static,
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_map()
+            .entries(self.sets.iter().map(|(name, set)| (name, set.len())))
+            .finish()
+    }
+}
+
+impl<E, S> SparseSetRegistry<E, S>
+where
+    E: Copy + 'static,
+    S: SparseStorage<EntityId = E> + Default + 'static,
+{
+    /// Register the sparse set of `T`
+    /// # Details
+    /// It does nothing if `T` is registered
+    /// # Returns
+    /// Return the sparse set of `T`
+    pub fn register<T>(&mut self) -> &mut SparseSet<E, T, S>
+    where
+        T: 'static,
+    {
+        let sets = &mut self.sets;
+        let position = *self.positions.entry(TypeId::of::<T>()).or_insert_with(|| {
+            sets.push((type_name::<T>(), Box::new(SparseSet::<E, T, S>::default())));
+            sets.len() - 1
+        });
+        self.sets[position]
+            .1
+            .as_any_mut()
+            .downcast_mut()
+            .expect("the set is registered by its type id")
+    }
+
+    /// Check if `T` is registered
+    pub fn is_registered<T>(&self) -> bool
+    where
+        T: 'static,
+    {
+        self.positions.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Get the sparse set of `T`
+    /// # Returns
+    /// Return None if `T` is not registered
+    pub fn get<T>(&self) -> Option<&SparseSet<E, T, S>>
+    where
+        T: 'static,
+    {
+        let position = *self.positions.get(&TypeId::of::<T>())?;
+        self.sets[position].1.as_any().downcast_ref()
+    }
+
+    /// Get the MUTABLE sparse set of `T`
+    /// # Returns
+    /// Return None if `T` is not registered
+    pub fn get_mut<T>(&mut self) -> Option<&mut SparseSet<E, T, S>>
+    where
+        T: 'static,
+    {
+        let position = *self.positions.get(&TypeId::of::<T>())?;
+        self.sets[position].1.as_any_mut().downcast_mut()
+    }
+
+    /// Remove the entity from every registered set
+    /// # Returns
+    /// Return the count of sets that had the entity
+    pub fn remove_entity(&mut self, id: E) -> usize {
+        self.sets
+            .iter_mut()
+            .map(|(_, set)| set.remove_entity(id))
+            .filter(|removed| *removed)
+            .count()
+    }
+
+    /// Clear every registered set
+    pub fn clear_all(&mut self) {
+        for (_, set) in &mut self.sets {
+            set.clear();
+        }
+    }
+
+    /// Get the count of registered sets
+    pub fn len(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// Check if nothing is registered
+    pub fn is_empty(&self) -> bool {
+        self.sets.is_empty()
+    }
+
+    /// Iterate the type names of registered data in registration order
+    pub fn type_names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.sets.iter().map(|(name, _)| *name)
+    }
+
+    /// Iterate `(type name, erased set)` in registration order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &dyn DynSparseSet<E>)> + '_ {
+        self.sets.iter().map(|(name, set)| (*name, set.as_ref()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SparseSetRegistry, SparseSetVec};
+
+    #[derive(Debug, PartialEq)]
+    struct Position(f32, f32);
+    #[derive(Debug, PartialEq)]
+    struct Health(u32);
+    #[derive(Debug, PartialEq)]
+    struct Player;
+
+    #[test]
+    fn registry_test() {
+        let mut registry: SparseSetRegistry<usize> = SparseSetRegistry::default();
+        assert!(registry.get::<Health>().is_none());
+
+        let positions = registry.register::<Position>();
+        for id in 0..10 {
+            positions.insert(id, Position(id as f32, 0.0));
+        }
+        let health = registry.register::<Health>();
+        for id in 5..15 {
+            health.insert(id, Health(100));
+        }
+        registry.register::<Player>().insert(7, Player);
+        // registering again keeps the set
+        assert_eq!(registry.register::<Player>().len(), 1);
+        assert_eq!(registry.len(), 3);
+        assert!(registry.is_registered::<Health>());
+        assert!(!registry.is_registered::<u8>());
+
+        // despawn
+        assert_eq!(registry.remove_entity(7), 3);
+        assert_eq!(registry.remove_entity(12), 1);
+        assert_eq!(registry.remove_entity(100), 0);
+
+        let positions: &SparseSetVec<usize, Position> = registry.get().unwrap();
+        assert_eq!(positions.len(), 9);
+        assert_eq!(positions.get(3), Some(&Position(3.0, 0.0)));
+        assert!(!positions.contains(7));
+        let health = registry.get_mut::<Health>().unwrap();
+        assert_eq!(health.len(), 8);
+        health.get_mut(5).unwrap().0 -= 10;
+        assert_eq!(registry.get::<Health>().unwrap().get(5), Some(&Health(90)));
+        assert!(registry.get::<Player>().unwrap().is_empty());
+
+        let names: Vec<_> = registry.type_names().collect();
+        assert_eq!(names.len(), 3);
+        assert!(names[0].ends_with("Position"));
+        assert!(names[2].ends_with("Player"));
+        let lens: Vec<_> = registry.iter().map(|(_, set)| set.len()).collect();
+        assert_eq!(lens, vec![9, 8, 0]);
+
+        registry.clear_all();
+        assert!(registry.iter().all(|(_, set)| set.is_empty()));
+        assert_eq!(registry.len(), 3);
+    }
+}
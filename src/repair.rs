@@ -0,0 +1,171 @@
+use std::{borrow::Cow, fmt::Display, num::NonZeroUsize};
+
+use crate::{NamePrefix, SparseSet, SparseStorage};
+
+/// The broken invariant found by `SparseSet::check_invariants`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvariantError {
+    /// The dense array and the data array have different length
+    LengthMismatch { dense_len: usize, data_len: usize },
+    /// The id at `index` and the id at `other_index` are the same
+    DuplicateId { index: usize, other_index: usize },
+    /// The sparse storage maps the id at `index` to `stored`, not to `index + 1`
+    IndexMismatch {
+        index: usize,
+        stored: Option<NonZeroUsize>,
+    },
+}
+
+impl Display for InvariantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvariantError::LengthMismatch {
+                dense_len,
+                data_len,
+            } => write!(f, "dense.len()={} != data.len()={}", dense_len, data_len),
+            InvariantError::DuplicateId { index, other_index } => {
+                write!(f, "id at index {} is duplicated at {}", index, other_index)
+            }
+            InvariantError::IndexMismatch { index, stored } => write!(
+                f,
+                "id at index {} is mapped to {:?} in sparse",
+                index, stored
+            ),
+        }
+    }
+}
+
+impl std::error::Error for InvariantError {}
+
+/// The error of `SparseSet::check_invariants`
+/// # Details
+/// It names the sparse set by `set_debug_name` and the type of data,
+/// so the broken one can be found when many sets are checked
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidSparseSet {
+    /// The debug name of the sparse set
+    pub name: Option<Cow<'static, str>>,
+    /// The name of the data type, like `SparseSet::data_type_name`
+    pub data_type: &'static str,
+    /// The broken invariant
+    pub error: InvariantError,
+}
+
+impl Display for InvalidSparseSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", NamePrefix(self.name.as_deref(), self.data_type), self.error)
+    }
+}
+
+impl std::error::Error for InvalidSparseSet {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+/// What `SparseSet::repair` did
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RepairReport {
+    /// The count of ids or data dropped to make the lengths equal
+    pub truncated: usize,
+    /// The count of duplicated ids dropped, the first one is kept
+    pub duplicates_removed: usize,
+    /// The count of ids whose index in sparse was wrong
+    pub remapped: usize,
+    /// The invariant still broken after repairing.
+    /// It means the sparse storage itself is broken
+    pub unrecoverable: Option<InvariantError>,
+}
+
+impl RepairReport {
+    /// Check if `repair` found nothing to do
+    pub fn is_clean(&self) -> bool {
+        *self == RepairReport::default()
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + PartialEq,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Check the mapping between the sparse and the dense
+    /// # Details
+    /// Stale entries in sparse (ids not in dense) cannot be found by `SparseStorage`.
+    /// Use `repair` to drop them.
+    /// The error is named by `set_debug_name`
+    pub fn check_invariants(&self) -> Result<(), InvalidSparseSet> {
+        match self.find_broken_invariant() {
+            Some(error) => Err(InvalidSparseSet {
+                name: self.debug_name.clone(),
+                data_type: self.data_type_name(),
+                error,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    /// Find the first broken invariant
+    fn find_broken_invariant(&self) -> Option<InvariantError> {
+        if self.dense.len() != self.data.len() {
+            return Some(InvariantError::LengthMismatch {
+                dense_len: self.dense.len(),
+                data_len: self.data.len(),
+            });
+        }
+        for (index, id) in self.dense.iter().enumerate() {
+            let stored = self.sparse.get_index(*id);
+            if stored.map(|x| x.get() - 1) == Some(index) {
+                continue;
+            }
+            let other_index = stored
+                .map(|x| x.get() - 1)
+                .filter(|other_index| self.dense.get(*other_index) == Some(id));
+            return Some(match other_index {
+                Some(other_index) => InvariantError::DuplicateId { index, other_index },
+                None => InvariantError::IndexMismatch { index, stored },
+            });
+        }
+        None
+    }
+
+    /// Fix the sparse set by the dense array and report what was done
+    /// # Details
+    /// * The longer one of dense and data is truncated
+    /// * Only the first one of duplicated ids is kept
+    /// * The sparse storage is rebuilt from dense, so stale entries are dropped
+    pub fn repair(&mut self) -> RepairReport {
+        let mut report = RepairReport::default();
+
+        let len = self.dense.len().min(self.data.len());
+        report.truncated = self.dense.len() + self.data.len() - 2 * len;
+        self.dense.truncate(len);
+        self.data.truncate(len);
+
+        report.remapped = self
+            .dense
+            .iter()
+            .enumerate()
+            .filter(|(index, id)| self.sparse.get_index(**id).map(|x| x.get() - 1) != Some(*index))
+            .count();
+
+        self.sparse.clear();
+        self.reorder_version = self.reorder_version.wrapping_add(1);
+        let dense = std::mem::take(&mut self.dense);
+        let data = std::mem::take(&mut self.data);
+        for (id, dat) in dense.into_iter().zip(data) {
+            if self.sparse.get_index(id).is_some() {
+                report.duplicates_removed += 1;
+                continue;
+            }
+            self.sparse
+                .set_index(id, NonZeroUsize::new(self.dense.len() + 1));
+            self.dense.push(id);
+            self.data.push(dat);
+        }
+        report.remapped -= report.duplicates_removed;
+
+        report.unrecoverable = self.find_broken_invariant();
+        report
+    }
+}
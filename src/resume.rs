@@ -0,0 +1,171 @@
+use std::iter::FusedIterator;
+
+use crate::{SparseSet, SparseStorage};
+
+/// The position of a pass of `SparseSet::iter_resumable`
+/// # Details
+/// `ResumeCursor::default()` starts a new pass
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ResumeCursor {
+    /// The entities before it are not visited yet, None before the pass starts
+    position: Option<usize>,
+    reorder_version: u64,
+}
+
+impl ResumeCursor {
+    /// Check if the pass visited every entity
+    pub fn is_done(&self) -> bool {
+        self.position == Some(0)
+    }
+}
+
+/// Iterator over `(id, &data)` pairs from the back of the dense array,
+/// which can be stopped and resumed later
+/// # Details
+/// Created by `SparseSet::iter_resumable`
+#[derive(Debug)]
+pub struct ResumableIter<'a, E, T, S> {
+    set: &'a SparseSet<E, T, S>,
+    position: usize,
+}
+
+impl<'a, E, T, S> ResumableIter<'a, E, T, S> {
+    /// Get the cursor to resume the pass after the pairs yielded so far
+    pub fn cursor(&self) -> ResumeCursor {
+        ResumeCursor {
+            position: Some(self.position),
+            reorder_version: self.set.reorder_version,
+        }
+    }
+}
+
+impl<'a, E, T, S> Iterator for ResumableIter<'a, E, T, S>
+where
+    E: Copy,
+{
+    type Item = (E, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.position = self.position.checked_sub(1)?;
+        Some((self.set.dense[self.position], &self.set.data[self.position]))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.position, Some(self.position))
+    }
+}
+
+impl<'a, E, T, S> ExactSizeIterator for ResumableIter<'a, E, T, S> where E: Copy {}
+
+impl<'a, E, T, S> FusedIterator for ResumableIter<'a, E, T, S> where E: Copy {}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Iterate the rest of the pass at `cursor`, for processing a set across several frames
+    /// # Details
+    /// A pass walks the dense array from the back.
+    /// Take some pairs, then keep `ResumableIter::cursor` to resume the pass later.
+    /// Between 2 calls the sparse set may be changed:
+    /// * Every entity in sparse set for the whole pass is visited at least once
+    /// * An entity moved by a swap-removal may be visited again
+    /// * An entity inserted during the pass may not be visited
+    /// * If entities are reordered (by swapping, sorting, rotating, shuffling or `repair`),
+    ///   the pass starts again from the back, so entities may be visited again
+    ///
+    /// It never panics or yields removed data
+    pub fn iter_resumable(&self, cursor: ResumeCursor) -> ResumableIter<'_, E, T, S> {
+        let position = match cursor.position {
+            Some(position) if cursor.reorder_version == self.reorder_version => {
+                position.min(self.len())
+            }
+            _ => self.len(),
+        };
+        ResumableIter {
+            set: self,
+            position,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{ResumeCursor, SparseSet, SparseSetVec};
+
+    #[test]
+    fn time_sliced_test() {
+        let mut rng = thread_rng();
+        for _ in 0..100 {
+            let mut sparse_set: SparseSetVec<usize, usize> = SparseSet::default();
+            for id in 0..200 {
+                sparse_set.insert(id, id);
+            }
+            let mut removed = HashSet::new();
+            let mut visited = HashSet::new();
+            let mut cursor = ResumeCursor::default();
+            while !cursor.is_done() {
+                let mut iter = sparse_set.iter_resumable(cursor);
+                for (id, x) in iter.by_ref().take(7) {
+                    assert_eq!(id, *x);
+                    assert!(!removed.contains(&id));
+                    visited.insert(id);
+                }
+                cursor = iter.cursor();
+
+                // interleaved removals and insertions
+                for _ in 0..3 {
+                    let id = rng.gen_range(0..300);
+                    if sparse_set.swap_remove_by_id(id).is_some() {
+                        removed.insert(id);
+                    }
+                }
+                let id = rng.gen_range(200..400);
+                if !removed.contains(&id) {
+                    sparse_set.insert(id, id);
+                }
+            }
+            for id in 0..200 {
+                assert!(removed.contains(&id) || visited.contains(&id));
+            }
+            assert_eq!(sparse_set.iter_resumable(cursor).count(), 0);
+        }
+    }
+
+    #[test]
+    fn reorder_restart_test() {
+        let mut sparse_set: SparseSetVec<usize, ()> = SparseSet::default();
+        for id in 0..10 {
+            sparse_set.insert(id, ());
+        }
+        let mut iter = sparse_set.iter_resumable(ResumeCursor::default());
+        let first: Vec<_> = iter.by_ref().take(4).map(|(id, _)| id).collect();
+        assert_eq!(first, vec![9, 8, 7, 6]);
+        let cursor = iter.cursor();
+
+        // resumed without reordering
+        assert_eq!(sparse_set.iter_resumable(cursor).len(), 6);
+        // removing an unvisited entity moves a visited one, which is visited again
+        sparse_set.swap_remove_by_id(2);
+        let rest: Vec<_> = sparse_set
+            .iter_resumable(cursor)
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(rest, vec![5, 4, 3, 9, 1, 0]);
+
+        // reordering restarts the pass
+        sparse_set.rotate_left(3);
+        assert_eq!(sparse_set.iter_resumable(cursor).len(), 9);
+        let mut iter = sparse_set.iter_resumable(cursor);
+        iter.nth(1);
+        let cursor = iter.cursor();
+        assert_eq!(sparse_set.iter_resumable(cursor).len(), 7);
+        sparse_set.swap_by_index(0, 8);
+        assert_eq!(sparse_set.iter_resumable(cursor).len(), 9);
+    }
+}
@@ -0,0 +1,153 @@
+use std::convert::TryFrom;
+
+use roaring::RoaringBitmap;
+
+use crate::{IdOverflow, SparseSet, SparseStorage};
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + Into<usize>,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Collect the ids into a `RoaringBitmap`
+    /// # Details
+    /// Ids converted to the same `usize` are collected once
+    /// # Returns
+    /// Return Err if any id doesn't fit in `u32`
+    pub fn to_roaring(&self) -> Result<RoaringBitmap, IdOverflow> {
+        let mut ids = self
+            .dense
+            .iter()
+            .map(|id| {
+                let id: usize = (*id).into();
+                u32::try_from(id).map_err(|_| IdOverflow(id))
+            })
+            .collect::<Result<Vec<u32>, IdOverflow>>()?;
+        ids.sort_unstable();
+        // different ids may convert to the same usize
+        ids.dedup();
+        Ok(RoaringBitmap::from_sorted_iter(ids).expect("ids are strictly increasing"))
+    }
+
+    /// Remove all entities whose id is not in `bitmap`
+    /// # Details
+    /// Ids that don't fit in `u32` are never in `bitmap`, so they are removed
+    /// # Returns
+    /// Return the count of removed entities
+    pub fn retain_in_roaring(&mut self, bitmap: &RoaringBitmap) -> usize {
+        let mut count = 0;
+        for index in (0..self.len()).rev() {
+            // Safety
+            // index is less than self.len()
+            let id: usize = unsafe { *self.dense.get_unchecked(index) }.into();
+            if !u32::try_from(id).is_ok_and(|id| bitmap.contains(id)) {
+                self.swap_remove_by_index(index);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    /// Iterate `(id, &data)` pairs whose id is in `bitmap`
+    /// # Details
+    /// It walks the smaller one of the sparse set and `bitmap`,
+    /// so the order is unspecified
+    pub fn iter_in_roaring<'a>(
+        &'a self,
+        bitmap: &'a RoaringBitmap,
+    ) -> impl Iterator<Item = (E, &'a T)> + 'a
+    where
+        E: TryFrom<usize>,
+    {
+        let (set_side, bitmap_side) = if bitmap.len() < self.len() as u64 {
+            let bitmap_side = bitmap.iter().filter_map(move |id| {
+                let id = E::try_from(id as usize).ok()?;
+                Some((id, self.get(id)?))
+            });
+            (None, Some(bitmap_side))
+        } else {
+            let set_side = self.iter().filter(move |(id, _)| {
+                u32::try_from((*id).into()).is_ok_and(|id| bitmap.contains(id))
+            });
+            (Some(set_side), None)
+        };
+        set_side
+            .into_iter()
+            .flatten()
+            .chain(bitmap_side.into_iter().flatten())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{thread_rng, Rng};
+    use roaring::RoaringBitmap;
+
+    use crate::{IdOverflow, SparseSet, SparseSetHashMap, SparseSetVec};
+
+    /// An id whose conversion to usize ignores the tag
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    struct TaggedId(usize, bool);
+
+    impl From<TaggedId> for usize {
+        fn from(id: TaggedId) -> usize {
+            id.0
+        }
+    }
+
+    #[test]
+    fn roaring_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<usize, u32> = SparseSetVec::default();
+        let mut model = vec![false; 5000];
+        for _ in 0..1000 {
+            let id = rng.gen_range(0..5000);
+            sparse_set.insert(id, id as u32);
+            model[id] = true;
+        }
+
+        let bitmap = sparse_set.to_roaring().unwrap();
+        assert_eq!(bitmap.len(), sparse_set.len() as u64);
+        for (id, present) in model.iter().enumerate() {
+            assert_eq!(bitmap.contains(id as u32), *present);
+        }
+
+        // walk both sides
+        for size in [10, 4000] {
+            let mut query = RoaringBitmap::new();
+            for _ in 0..size {
+                query.insert(rng.gen_range(0..5000));
+            }
+            let mut result: Vec<_> = sparse_set.iter_in_roaring(&query).collect();
+            result.sort_unstable();
+            let expected: Vec<_> = (0..5000)
+                .filter(|id| model[*id] && query.contains(*id as u32))
+                .map(|id| (id, sparse_set.get(id).unwrap()))
+                .collect();
+            assert_eq!(result, expected);
+
+            let mut retained = sparse_set.clone();
+            let removed = retained.retain_in_roaring(&query);
+            assert_eq!(retained.len(), expected.len());
+            assert_eq!(removed, sparse_set.len() - expected.len());
+            assert!(retained.ids().iter().all(|id| query.contains(*id as u32)));
+        }
+
+        let mut large: SparseSetHashMap<usize, ()> = SparseSetHashMap::default();
+        large.insert(1, ());
+        large.insert(1 << 40, ());
+        assert_eq!(large.to_roaring(), Err(IdOverflow(1 << 40)));
+        assert_eq!(large.retain_in_roaring(&RoaringBitmap::full()), 1);
+        assert!(large.contains(1));
+    }
+
+    #[test]
+    fn to_roaring_repeated_test() {
+        let mut sparse_set: SparseSetHashMap<TaggedId, ()> = SparseSet::default();
+        sparse_set.insert(TaggedId(5, false), ());
+        sparse_set.insert(TaggedId(3, false), ());
+        sparse_set.insert(TaggedId(5, true), ());
+        let bitmap = sparse_set.to_roaring().unwrap();
+        assert_eq!(bitmap.iter().collect::<Vec<_>>(), vec![3, 5]);
+    }
+}
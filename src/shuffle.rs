@@ -0,0 +1,66 @@
+use rand::{seq::SliceRandom, Rng};
+
+use crate::{SparseSet, SparseStorage};
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Shuffle the dense order randomly
+    /// # Details
+    /// It shuffles a permutation by Fisher-Yates then applies it in one pass,
+    /// so each sparse index is written only once
+    pub fn shuffle<R>(&mut self, rng: &mut R)
+    where
+        R: Rng + ?Sized,
+    {
+        let mut permutation: Vec<usize> = (0..self.len()).collect();
+        permutation.shuffle(rng);
+        self.apply_permutation(&permutation);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroUsize;
+
+    use rand::{rngs::StdRng, SeedableRng};
+
+    use crate::{SparseSet, SparseSetHashMap, SparseSetVec};
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn shuffle_test() {
+        let mut rng = StdRng::seed_from_u64(0x5eed);
+        let mut sparse_set: SparseSetVec<EntityId, usize> = SparseSet::default();
+        for x in 1..=1000 {
+            sparse_set.insert(EntityId::new(x).unwrap(), x * 10);
+        }
+        let before = sparse_set.ids().to_vec();
+
+        sparse_set.shuffle(&mut rng);
+        assert_eq!(sparse_set.len(), 1000);
+        assert_ne!(sparse_set.ids(), before.as_slice());
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+        for (id, x) in sparse_set.iter() {
+            assert_eq!(*x, id.get() * 10);
+        }
+        let mut ids = sparse_set.ids().to_vec();
+        ids.sort();
+        assert_eq!(ids, before);
+
+        // same seed, same order
+        let mut other: SparseSetHashMap<EntityId, usize> = SparseSet::default();
+        for x in 1..=1000 {
+            other.insert(EntityId::new(x).unwrap(), x * 10);
+        }
+        other.shuffle(&mut StdRng::seed_from_u64(0x5eed));
+        assert_eq!(other.ids(), sparse_set.ids());
+
+        let mut empty: SparseSetVec<EntityId, usize> = SparseSet::default();
+        empty.shuffle(&mut rng);
+        assert!(empty.is_empty());
+    }
+}
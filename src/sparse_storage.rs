@@ -1,7 +1,13 @@
 use std::collections::{HashMap, BTreeMap};
+use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
 use std::hash::Hash;
+use std::iter::Map;
 use std::marker::PhantomData;
 use std::num::NonZeroUsize;
+use std::ops::RangeBounds;
+
+use crate::prefetch::{prefetch_read, touch_spare_capacity};
 
 /// A trait that can represent the storage of the Sparse Data
 /// # Remark
@@ -23,16 +29,24 @@ pub trait SparseStorage {
     /// # Remarks
     /// * The index must be continuous and start from `start_index`
     fn set_indices(&mut self,entity_ids: &[Self::EntityId], start_index: NonZeroUsize) {
-        let mut index = start_index.get();
-        for id in entity_ids {
+        for (index, id) in (start_index.get()..).zip(entity_ids.iter()) {
             self.set_index(*id, NonZeroUsize::new(index));
-            index += 1;
         }
     }
 
     /// Clear itself
     fn clear(&mut self);
 
+    /// Unmap a batch of entity ids
+    /// # Details
+    /// It is `set_index(id, None)` for every id by default,
+    /// storages can override it to remove many ids at once
+    fn clear_indices(&mut self, entity_ids: &[Self::EntityId]) {
+        for id in entity_ids {
+            self.set_index(*id, None);
+        }
+    }
+
     /// swap 2 entitis
     fn swap(&mut self,entity_id_1: Self::EntityId,entity_id_2: Self::EntityId) {
         let index_1 = self.get_index(entity_id_1);
@@ -41,6 +55,66 @@ pub trait SparseStorage {
         self.set_index(entity_id_1, index_2);
         self.set_index(entity_id_2, index_1);
     }
+
+    /// Check if `entity_id` is greater than every mapped entity id in O(1)
+    /// # Details
+    /// Return false if the storage can't tell cheaply,
+    /// it is always correct but disables the fast path of `SparseSet::insert_append_hint`
+    fn is_after_last(&self, _entity_id: Self::EntityId) -> bool {
+        false
+    }
+
+    /// Get the count of slots after mapping `entity_id`
+    /// # Returns
+    /// Return None if the storage is not made of slots
+    fn slots_after(&self, _entity_id: Self::EntityId) -> Option<usize> {
+        None
+    }
+
+    /// Reserve space for about `hint` entity ids
+    /// # Details
+    /// It is only a hint and does nothing by default.  
+    /// Storages addressed by integer ids treat `hint` as the count of slots
+    fn reserve(&mut self, _hint: usize) {}
+
+    /// Get the first slot that is not mapped to any index
+    /// # Returns
+    /// Return None if the storage can't answer.  
+    /// Storages addressed by integer ids return the smallest vacant id no less than `start`
+    fn first_vacant_slot(&self, _start: usize) -> Option<usize> {
+        None
+    }
+
+    /// Collect the entity ids in `range` if the storage keeps them in order
+    /// # Returns
+    /// Return None if the storage can't enumerate ids by range.  
+    /// `SparseSet` scans the dense array instead
+    fn range_ids<R>(&self, _range: R) -> Option<Vec<Self::EntityId>>
+    where
+        R: RangeBounds<Self::EntityId>,
+    {
+        None
+    }
+
+    /// Hint the CPU to load the memory where `entity_id` is mapped
+    /// # Details
+    /// It is only a hint and does nothing by default
+    fn prefetch(&self, _entity_id: Self::EntityId) {}
+
+    /// Fault in the memory reserved by the storage ahead of a latency-sensitive phase
+    /// # Details
+    /// It never changes the mapping and does nothing by default
+    fn warm_up(&mut self) {}
+
+    /// Check if `entity_id` can be mapped by the storage now
+    /// # Details
+    /// It is true by default.
+    /// `SparseSet` checks it before inserting a new id,
+    /// so an id out of range or a slot held by another id
+    /// is reported as `LimitExceeded::AddressSpace`
+    fn is_addressable(&self, _entity_id: Self::EntityId) -> bool {
+        true
+    }
 }
 
 impl<E> SparseStorage for HashMap<E,NonZeroUsize> 
@@ -62,6 +136,10 @@ where E : Hash + Eq + Copy{
     fn clear(&mut self){
         self.clear();
     }
+
+    fn reserve(&mut self, hint: usize) {
+        self.reserve(hint.saturating_sub(self.len()));
+    }
 }
 
 
@@ -84,24 +162,163 @@ where E : Ord + Copy{
     fn clear(&mut self){
         self.clear();
     }
+
+    fn is_after_last(&self, entity_id: Self::EntityId) -> bool {
+        self.keys().next_back().is_none_or(|last| *last < entity_id)
+    }
+
+    fn range_ids<R>(&self, range: R) -> Option<Vec<Self::EntityId>>
+    where
+        R: RangeBounds<Self::EntityId>,
+    {
+        Some(self.range(range).map(|(id, _)| *id).collect())
+    }
 }
+/// A sparse storage that keeps the entity ids in order
+/// # Remark
+/// `count_in_range` counts through `ids_in_range` by default.  
+/// Storages with order statistics can override it to answer in O(log n)
+pub trait OrderedSparseStorage: SparseStorage {
+    /// the iterator of entity ids in a range
+    type RangeIter<'a>: Iterator<Item = Self::EntityId>
+    where
+        Self: 'a;
+
+    /// Iterate the entity ids in `range` in ascending order
+    fn ids_in_range<R>(&self, range: R) -> Self::RangeIter<'_>
+    where
+        R: RangeBounds<Self::EntityId>;
+
+    /// Count the entity ids in `range`
+    fn count_in_range<R>(&self, range: R) -> usize
+    where
+        R: RangeBounds<Self::EntityId>,
+    {
+        self.ids_in_range(range).count()
+    }
+}
+
+impl<E> OrderedSparseStorage for BTreeMap<E,NonZeroUsize>
+where E : Ord + Copy{
+    type RangeIter<'a> = Map<
+        std::collections::btree_map::Range<'a, E, NonZeroUsize>,
+        fn((&'a E, &'a NonZeroUsize)) -> E,
+    >
+    where
+        Self: 'a;
+
+    fn ids_in_range<R>(&self, range: R) -> Self::RangeIter<'_>
+    where
+        R: RangeBounds<Self::EntityId>,
+    {
+        self.range(range).map(|(id, _)| *id)
+    }
+}
+
 /// To make the Vec `Rank Up` and avoid the warning  
 /// `VecWrapeer :: T -> U -> VecWrapper`
-#[derive(Debug,Clone)]
-pub struct VecWrapper<T,E>(Vec<T>,PhantomData<E>);
+#[derive(Clone)]
+pub struct VecWrapper<T,E>(
+    Vec<T>,
+    /// All slots in `1..it` are occupied, it is the first vacant slot after slot 0
+    /// # Details
+    /// Slot 0 is left out because ids like `NonZeroUsize` never address it
+    usize,
+    PhantomData<E>,
+);
 
 impl<T,E> Default for VecWrapper<T,E> {
     fn default() -> Self {
-        Self(Default::default(), Default::default())
+        Self(Default::default(), 1, Default::default())
+    }
+}
+
+impl<E> VecWrapper<Option<NonZeroUsize>,E> {
+    /// Get the first slot after slot 0 mapped to nothing in O(1)
+    /// # Details
+    /// It is the smallest free id for ids like `NonZeroUsize`.  
+    /// It may be equal to the count of slots if all slots are occupied
+    pub fn first_vacant_hint(&self) -> usize {
+        self.1
+    }
+
+    /// Get the count of slots can be held without reallocating
+    pub fn slot_capacity(&self) -> usize {
+        self.0.capacity()
+    }
+
+    /// Iterate `(id, index)` of occupied slots in ascending order of id
+    /// # Details
+    /// The index is start from 1 like `get_index`.
+    /// Slots are checked in chunks, so runs of vacant slots are skipped quickly
+    pub fn iter_occupied_ascending(&self) -> impl Iterator<Item = (usize, NonZeroUsize)> + '_ {
+        self.0
+            .chunks(OCCUPIED_CHUNK)
+            .enumerate()
+            .filter(|(_, chunk)| chunk.iter().any(Option::is_some))
+            .flat_map(|(chunk_index, chunk)| {
+                let first = chunk_index * OCCUPIED_CHUNK;
+                chunk
+                    .iter()
+                    .enumerate()
+                    .filter_map(move |(offset, index)| Some((first + offset, (*index)?)))
+            })
+    }
+
+    /// Resize to `len` slots, the capacity grows at least twice
+    fn grow(&mut self, len: usize) {
+        let capacity = len.max(2 * self.0.len());
+        self.0.reserve(capacity - self.0.len());
+        self.0.resize(len, None);
     }
 }
 
 pub type VecStorage<E> = VecWrapper<Option<NonZeroUsize>,E>;
 
+/// The count of slots checked at once by `iter_occupied_ascending`
+const OCCUPIED_CHUNK: usize = 16;
+
+/// The count of entries printed by `{:?}`, `{:#?}` prints all of them
+const DEBUG_ENTRIES: usize = 8;
+
+/// Print a summary instead of every slot
+/// # Details
+/// Only the occupied slots are printed as `slot: index`,
+/// vacant slots never appear
+impl<E> Debug for VecWrapper<Option<NonZeroUsize>,E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        struct Entries<'a>(&'a [Option<NonZeroUsize>], usize);
+
+        impl Debug for Entries<'_> {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                let mut occupied = self.0
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(slot, index)| Some((slot, index.as_ref()?.get() - 1)));
+                let mut map = f.debug_map();
+                map.entries(occupied.by_ref().take(self.1));
+                if occupied.next().is_some() {
+                    map.finish_non_exhaustive()
+                } else {
+                    map.finish()
+                }
+            }
+        }
+
+        let limit = if f.alternate() { usize::MAX } else { DEBUG_ENTRIES };
+        f.debug_struct("VecStorage")
+            .field("len", &self.0.iter().filter(|index| index.is_some()).count())
+            .field("slots", &self.0.len())
+            .field("entries", &Entries(&self.0, limit))
+            .finish()
+    }
+}
+
 impl<E> SparseStorage for VecWrapper<Option<NonZeroUsize>,E>
 where E : Into<usize> + Copy {
     type EntityId = E;
 
+    #[inline]
     fn get_index(&self, entity_id: Self::EntityId) -> Option<NonZeroUsize> {
         let entity_id : usize = entity_id.into();
         if entity_id < self.0.len() {
@@ -113,16 +330,528 @@ where E : Into<usize> + Copy {
         }
     }
 
+    #[inline]
     fn set_index(&mut self, entity_id: Self::EntityId, index : Option<NonZeroUsize>) {
         let entity_id : usize = entity_id.into();
         if entity_id >= self.0.len() {
-            self.0.resize(entity_id + 1, None);
+            self.grow(entity_id + 1);
         }
         *unsafe { self.0.get_unchecked_mut(entity_id) } = index;
+
+        if index.is_none() {
+            if entity_id != 0 {
+                self.1 = self.1.min(entity_id);
+            }
+        } else if entity_id == self.1 {
+            while let Some(Some(_)) = self.0.get(self.1) {
+                self.1 += 1;
+            }
+        }
+    }
+
+    fn set_indices(&mut self, entity_ids: &[Self::EntityId], start_index: NonZeroUsize) {
+        // fill the slots on demand to write each slot only once,
+        // the capacity of Vec grows geometrically by itself
+        for (index, id) in (start_index.get()..).zip(entity_ids.iter()) {
+            let entity_id : usize = (*id).into();
+            if entity_id < self.0.len() {
+                *unsafe { self.0.get_unchecked_mut(entity_id) } = NonZeroUsize::new(index);
+            } else {
+                self.0.resize(entity_id, None);
+                self.0.push(NonZeroUsize::new(index));
+            }
+        }
+        while let Some(Some(_)) = self.0.get(self.1) {
+            self.1 += 1;
+        }
     }
 
     fn clear(&mut self) {
         self.0.clear();
+        self.1 = 1;
+    }
+
+    fn clear_indices(&mut self, entity_ids: &[Self::EntityId]) {
+        let mut first_vacant = self.1;
+        for id in entity_ids {
+            let entity_id : usize = (*id).into();
+            if let Some(slot) = self.0.get_mut(entity_id) {
+                *slot = None;
+                if entity_id != 0 {
+                    first_vacant = first_vacant.min(entity_id);
+                }
+            }
+        }
+        // the first vacant slot is updated once for the batch
+        self.1 = first_vacant;
+    }
+
+    fn reserve(&mut self, hint: usize) {
+        self.0.reserve(hint.saturating_sub(self.0.len()));
+    }
+
+    fn slots_after(&self, entity_id: Self::EntityId) -> Option<usize> {
+        Some(self.0.len().max(entity_id.into().saturating_add(1)))
+    }
+
+    fn is_after_last(&self, entity_id: Self::EntityId) -> bool {
+        // all slots after the end are vacant
+        entity_id.into() >= self.0.len()
+    }
+
+    fn prefetch(&self, entity_id: Self::EntityId) {
+        prefetch_read(self.0.as_ptr().wrapping_add(entity_id.into()));
+    }
+
+    fn warm_up(&mut self) {
+        touch_spare_capacity(&mut self.0);
+    }
+
+    fn first_vacant_slot(&self, start: usize) -> Option<usize> {
+        if start == 0 && !matches!(self.0.first(), Some(Some(_))) {
+            return Some(0);
+        }
+        let start = start.max(1);
+        if start <= self.1 {
+            return Some(self.1);
+        }
+        let slot = self.0[start.min(self.0.len())..]
+            .iter()
+            .position(Option::is_none)
+            .map_or(self.0.len().max(start), |offset| start + offset);
+        Some(slot)
+    }
+}
+
+
+
+/// A slot storage like `VecStorage` for 64-bit ids like `NonZeroU64`
+/// # Details
+/// The slot of an id is converted from `u64` with a checked conversion,
+/// so it works on targets whose `usize` is narrower than 64 bits.
+/// # Oversized ids
+/// On a 32-bit target an id greater than `usize::MAX` has no slot:
+/// * `SparseSet::try_insert` and the other fallible insertions return `LimitExceeded::AddressSpace`,
+///   the panicking ones panic with that error before the storage is touched
+/// * `get`, `contains` and removals treat it as absent
+///
+/// Use `HashMap` or `BTreeMap` as the storage if ids are spread over the whole 64-bit range
+#[derive(Debug, Clone)]
+pub struct U64VecStorage<E> {
+    slots: VecStorage<usize>,
+    _marker: PhantomData<E>,
+}
+
+impl<E> Default for U64VecStorage<E> {
+    fn default() -> Self {
+        U64VecStorage {
+            slots: VecStorage::default(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E> U64VecStorage<E>
+where
+    E: Into<u64> + Copy,
+{
+    /// Get the slot of `entity_id`, None if it is out of the address space
+    fn slot(entity_id: E) -> Option<usize> {
+        usize::try_from(entity_id.into()).ok()
+    }
+
+    /// Get the slot of `entity_id` which is going to be mapped
+    /// # Panics
+    /// Panic if it is out of the address space
+    fn slot_to_map(entity_id: E) -> usize {
+        Self::slot(entity_id).unwrap_or_else(|| {
+            panic!("id={} is out of the address space", entity_id.into())
+        })
+    }
+}
+
+impl<E> SparseStorage for U64VecStorage<E>
+where
+    E: Into<u64> + Copy,
+{
+    type EntityId = E;
+
+    fn get_index(&self, entity_id: Self::EntityId) -> Option<NonZeroUsize> {
+        self.slots.get_index(Self::slot(entity_id)?)
+    }
+
+    fn set_index(&mut self, entity_id: Self::EntityId, index: Option<NonZeroUsize>) {
+        match Self::slot(entity_id) {
+            Some(slot) => self.slots.set_index(slot, index),
+            // an oversized id is never mapped
+            None if index.is_none() => {}
+            None => {
+                Self::slot_to_map(entity_id);
+            }
+        }
+    }
+
+    fn set_indices(&mut self, entity_ids: &[Self::EntityId], start_index: NonZeroUsize) {
+        let slots: Vec<usize> = entity_ids.iter().map(|id| Self::slot_to_map(*id)).collect();
+        self.slots.set_indices(&slots, start_index);
+    }
+
+    fn clear(&mut self) {
+        self.slots.clear();
+    }
+
+    fn clear_indices(&mut self, entity_ids: &[Self::EntityId]) {
+        // an oversized id is never mapped
+        let slots: Vec<usize> = entity_ids.iter().filter_map(|id| Self::slot(*id)).collect();
+        self.slots.clear_indices(&slots);
+    }
+
+    fn reserve(&mut self, hint: usize) {
+        self.slots.reserve(hint);
+    }
+
+    fn slots_after(&self, entity_id: Self::EntityId) -> Option<usize> {
+        // an oversized id needs more slots than any limit
+        Some(Self::slot(entity_id).map_or(usize::MAX, |slot| {
+            self.slots.slots_after(slot).unwrap_or(usize::MAX)
+        }))
+    }
+
+    fn is_after_last(&self, entity_id: Self::EntityId) -> bool {
+        Self::slot(entity_id).is_none_or(|slot| self.slots.is_after_last(slot))
     }
+
+    fn first_vacant_slot(&self, start: usize) -> Option<usize> {
+        self.slots.first_vacant_slot(start)
+    }
+
+    fn prefetch(&self, entity_id: Self::EntityId) {
+        if let Some(slot) = Self::slot(entity_id) {
+            self.slots.prefetch(slot);
+        }
+    }
+
+    fn warm_up(&mut self) {
+        self.slots.warm_up();
+    }
+
+    fn is_addressable(&self, entity_id: Self::EntityId) -> bool {
+        Self::slot(entity_id).is_some()
+    }
+}
+
+/// The slots of `SmallVecStorage`
+#[derive(Debug, Clone)]
+enum SmallSlots<const INLINE: usize> {
+    Inline([Option<NonZeroUsize>; INLINE]),
+    Heap(Vec<Option<NonZeroUsize>>),
+}
+
+/// A slot storage like `VecStorage` that keeps the first `INLINE` slots inline
+/// # Details
+/// Ids less than `INLINE` are stored in an array without allocating.
+/// The first id not less than `INLINE` spills all slots to the heap,
+/// and they stay there until the storage is dropped, even after `clear`
+#[derive(Debug, Clone)]
+pub struct SmallVecStorage<E, const INLINE: usize> {
+    slots: SmallSlots<INLINE>,
+    _marker: PhantomData<E>,
 }
 
+impl<E, const INLINE: usize> Default for SmallVecStorage<E, INLINE> {
+    fn default() -> Self {
+        SmallVecStorage {
+            slots: SmallSlots::Inline([None; INLINE]),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<E, const INLINE: usize> SmallVecStorage<E, INLINE> {
+    /// Check if the slots are still inline
+    pub fn is_inline(&self) -> bool {
+        matches!(self.slots, SmallSlots::Inline(_))
+    }
+
+    fn as_slice(&self) -> &[Option<NonZeroUsize>] {
+        match &self.slots {
+            SmallSlots::Inline(slots) => slots,
+            SmallSlots::Heap(slots) => slots,
+        }
+    }
+
+    /// Get the MUTABLE slot, spilling to the heap if `slot` is not less than `INLINE`
+    fn slot_mut(&mut self, slot: usize) -> &mut Option<NonZeroUsize> {
+        if let SmallSlots::Inline(slots) = &self.slots {
+            if slot >= INLINE {
+                self.slots = SmallSlots::Heap(slots.to_vec());
+            }
+        }
+        match &mut self.slots {
+            SmallSlots::Inline(slots) => &mut slots[slot],
+            SmallSlots::Heap(slots) => {
+                if slot >= slots.len() {
+                    slots.resize(slot + 1, None);
+                }
+                &mut slots[slot]
+            }
+        }
+    }
+}
+
+impl<E, const INLINE: usize> SparseStorage for SmallVecStorage<E, INLINE>
+where
+    E: Into<usize> + Copy,
+{
+    type EntityId = E;
+
+    fn get_index(&self, entity_id: Self::EntityId) -> Option<NonZeroUsize> {
+        self.as_slice().get(entity_id.into()).copied().flatten()
+    }
+
+    fn set_index(&mut self, entity_id: Self::EntityId, index: Option<NonZeroUsize>) {
+        let slot = entity_id.into();
+        if index.is_none() && slot >= self.as_slice().len() {
+            return;
+        }
+        *self.slot_mut(slot) = index;
+        if let SmallSlots::Heap(slots) = &mut self.slots {
+            // keep the last heap slot occupied, each slot is popped at most once per push
+            while let Some(None) = slots.last() {
+                slots.pop();
+            }
+        }
+    }
+
+    fn clear(&mut self) {
+        match &mut self.slots {
+            SmallSlots::Inline(slots) => *slots = [None; INLINE],
+            SmallSlots::Heap(slots) => slots.clear(),
+        }
+    }
+
+    fn slots_after(&self, entity_id: Self::EntityId) -> Option<usize> {
+        Some(self.as_slice().len().max(entity_id.into().saturating_add(1)))
+    }
+
+    fn is_after_last(&self, entity_id: Self::EntityId) -> bool {
+        let slot: usize = entity_id.into();
+        match &self.slots {
+            // at most `INLINE` slots are checked
+            SmallSlots::Inline(slots) => slots
+                .get(slot..)
+                .is_none_or(|rest| rest.iter().all(Option::is_none)),
+            // the last heap slot is always occupied
+            SmallSlots::Heap(slots) => slot >= slots.len(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::num::{NonZeroU64, NonZeroUsize};
+
+    use crate::{SmallVecStorage, SparseSet, SparseStorage, U64VecStorage, VecStorage};
+
+    #[test]
+    fn debug_test() {
+        let mut storage: VecStorage<usize> = VecStorage::default();
+        assert_eq!(
+            format!("{:?}", storage),
+            "VecStorage { len: 0, slots: 0, entries: {} }"
+        );
+
+        storage.set_index(3, NonZeroUsize::new(1));
+        storage.set_index(1000, NonZeroUsize::new(2));
+        assert_eq!(
+            format!("{:?}", storage),
+            "VecStorage { len: 2, slots: 1001, entries: {3: 0, 1000: 1} }"
+        );
+
+        for id in 0..20 {
+            storage.set_index(id * 10, NonZeroUsize::new(id + 3));
+        }
+        let compact = format!("{:?}", storage);
+        assert_eq!(
+            compact,
+            "VecStorage { len: 22, slots: 1001, entries: \
+             {0: 2, 3: 0, 10: 3, 20: 4, 30: 5, 40: 6, 50: 7, 60: 8, ..} }"
+        );
+        let full = format!("{:#?}", storage);
+        assert!(!full.contains(".."));
+        assert!(!full.contains("None"));
+        assert!(full.contains("1000: 1"));
+        assert!(full.contains("190: 21"));
+        assert_eq!(full.lines().count(), 22 + 6);
+    }
+
+    #[test]
+    fn growth_test() {
+        let mut storage: VecStorage<usize> = VecStorage::default();
+        storage.set_index(5, NonZeroUsize::new(1));
+        storage.set_index(2, NonZeroUsize::new(2));
+        storage.set_index(100, NonZeroUsize::new(3));
+        assert!(storage.slot_capacity() >= 101);
+
+        let ids: Vec<usize> = (1000..2000).step_by(2).collect();
+        storage.set_indices(&ids, NonZeroUsize::new(4).unwrap());
+        assert!(storage.slot_capacity() >= 1999);
+
+        assert_eq!(storage.get_index(5), NonZeroUsize::new(1));
+        assert_eq!(storage.get_index(2), NonZeroUsize::new(2));
+        assert_eq!(storage.get_index(100), NonZeroUsize::new(3));
+        for (index, id) in ids.iter().enumerate() {
+            assert_eq!(storage.get_index(*id), NonZeroUsize::new(index + 4));
+            assert_eq!(storage.get_index(*id + 1), None);
+        }
+        for id in (0..1000).filter(|id| ![2, 5, 100].contains(id)) {
+            assert_eq!(storage.get_index(id), None);
+        }
+        assert_eq!(storage.get_index(5000), None);
+    }
+
+    #[test]
+    fn small_vec_test() {
+        let mut storage: SmallVecStorage<usize, 8> = SmallVecStorage::default();
+        for id in 0..8 {
+            storage.set_index(id, NonZeroUsize::new(id + 1));
+        }
+        storage.set_index(100, None);
+        assert!(storage.is_inline());
+        assert_eq!(storage.slots_after(3), Some(8));
+        for id in 0..8 {
+            assert_eq!(storage.get_index(id), NonZeroUsize::new(id + 1));
+        }
+        assert_eq!(storage.get_index(8), None);
+
+        storage.set_index(20, NonZeroUsize::new(9));
+        assert!(!storage.is_inline());
+        assert_eq!(storage.slots_after(3), Some(21));
+        for id in 0..8 {
+            assert_eq!(storage.get_index(id), NonZeroUsize::new(id + 1));
+        }
+        assert_eq!(storage.get_index(20), NonZeroUsize::new(9));
+        assert_eq!(storage.get_index(19), None);
+
+        // removing the last slots trims the heap
+        assert!(!storage.is_after_last(20));
+        assert!(storage.is_after_last(21));
+        storage.set_index(12, NonZeroUsize::new(10));
+        storage.set_index(20, None);
+        assert_eq!(storage.slots_after(3), Some(13));
+        assert!(!storage.is_after_last(12));
+        assert!(storage.is_after_last(13));
+        storage.set_index(12, None);
+        assert_eq!(storage.slots_after(3), Some(8));
+        assert!(!storage.is_after_last(7));
+        assert!(storage.is_after_last(8));
+        storage.set_index(7, None);
+        assert!(storage.is_after_last(7));
+        assert!(!storage.is_after_last(6));
+
+        storage.clear();
+        assert_eq!(storage.get_index(3), None);
+        assert_eq!(storage.get_index(20), None);
+    }
+
+    fn u64_ids_test<S>()
+    where
+        S: SparseStorage<EntityId = NonZeroU64> + Default,
+    {
+        let id = |x| NonZeroU64::new(x).unwrap();
+        let mut sparse_set: SparseSet<NonZeroU64, u64, S> = SparseSet::default();
+        for x in (1..100).step_by(7) {
+            assert_eq!(sparse_set.try_insert(id(x), x), Ok(None));
+        }
+        assert_eq!(sparse_set.get(id(50)), Some(&50));
+        assert_eq!(sparse_set.get(id(51)), None);
+        assert_eq!(sparse_set.swap_remove_by_id(id(1)), Some(1));
+        assert_eq!(sparse_set.get_index(id(99)), Some(0));
+        assert_eq!(sparse_set.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn u64_ids_storages_test() {
+        u64_ids_test::<U64VecStorage<NonZeroU64>>();
+        u64_ids_test::<HashMap<NonZeroU64, NonZeroUsize>>();
+        u64_ids_test::<BTreeMap<NonZeroU64, NonZeroUsize>>();
+
+        // ids past 32 bits in the map storages on every target
+        let mut sparse_set: SparseSet<NonZeroU64, (), HashMap<_, _>> = SparseSet::default();
+        let large = NonZeroU64::new(u64::MAX).unwrap();
+        sparse_set.insert(large, ());
+        assert!(sparse_set.contains(large));
+        assert_eq!(sparse_set.ids(), &[large]);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "32")]
+    fn u64_vec_oversized_id_test() {
+        use crate::LimitExceeded;
+
+        let large = NonZeroU64::new(1 << 40).unwrap();
+        let mut sparse_set: SparseSet<NonZeroU64, u32, U64VecStorage<_>> = SparseSet::default();
+        assert_eq!(
+            sparse_set.try_insert(large, 1),
+            Err(LimitExceeded::AddressSpace)
+        );
+        let mut ids = vec![NonZeroU64::new(1).unwrap(), large];
+        let mut data = vec![1, 2];
+        assert_eq!(
+            sparse_set.try_insert_batch(&mut ids, &mut data),
+            Err(LimitExceeded::AddressSpace)
+        );
+        assert!(sparse_set.is_empty());
+        assert!(!sparse_set.contains(large));
+        assert_eq!(sparse_set.swap_remove_by_id(large), None);
+        // the oversized id is skipped
+        sparse_set.reserve_for_ids(&[NonZeroU64::new(1).unwrap(), large]);
+        assert!(sparse_set.capacity() >= 2);
+    }
+
+    #[test]
+    fn u64_vec_addressable_test() {
+        let storage: U64VecStorage<u64> = U64VecStorage::default();
+        assert!(storage.is_addressable(u32::MAX as u64));
+        assert_eq!(
+            storage.is_addressable(u64::MAX),
+            usize::BITS >= u64::BITS
+        );
+    }
+
+    fn clear_indices_matches<S>(mut bulk: S)
+    where
+        S: SparseStorage<EntityId = usize> + Clone,
+    {
+        for id in 0..100 {
+            bulk.set_index(id * 3, NonZeroUsize::new(id + 1));
+        }
+        let mut single = bulk.clone();
+        let ids: Vec<usize> = (0..300).filter(|id| id % 7 < 3).collect();
+        bulk.clear_indices(&ids);
+        for id in &ids {
+            single.set_index(*id, None);
+        }
+        for id in 0..400 {
+            assert_eq!(bulk.get_index(id), single.get_index(id));
+        }
+        assert_eq!(bulk.first_vacant_slot(0), single.first_vacant_slot(0));
+        assert_eq!(bulk.first_vacant_slot(10), single.first_vacant_slot(10));
+    }
+
+    #[test]
+    fn clear_indices_test() {
+        clear_indices_matches(VecStorage::<usize>::default());
+        clear_indices_matches(HashMap::<usize, NonZeroUsize>::default());
+        clear_indices_matches(SmallVecStorage::<usize, 16>::default());
+
+        // the first vacant slot moves back to the smallest cleared id
+        let mut storage: VecStorage<usize> = VecStorage::default();
+        for id in 0..10 {
+            storage.set_index(id, NonZeroUsize::new(id + 1));
+        }
+        storage.clear_indices(&[8, 4, 1000, 6]);
+        assert_eq!(storage.first_vacant_hint(), 4);
+    }
+}
@@ -0,0 +1,585 @@
+use std::{
+    convert::TryFrom,
+    fmt::Display,
+    io::{self, Read, Write},
+    num::{NonZeroU16, NonZeroU32, NonZeroU64, NonZeroU8, NonZeroUsize},
+};
+
+use crate::{LimitExceeded, SparseSet, SparseSetLimits, SparseStorage};
+
+/// The magic bytes at the start of a stream
+pub(crate) const STREAM_MAGIC: [u8; 4] = *b"XSPS";
+/// The format version written by `write_to`
+pub(crate) const STREAM_VERSION: u16 = 1;
+/// The count of bytes of the header written by `write_to`
+/// # Details
+/// It is the magic, format version, encodings of ids and data and count of entities
+pub const STREAM_HEADER_LEN: usize = 4 + 2 + 3 + 3 + 8;
+
+/// The encoding of ids or data in a stream
+/// # Details
+/// Only `FixedLe` is supported for now,
+/// the header stores the tag so other encodings (like serde) can be added later
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEncoding {
+    /// Fixed width little endian bytes, see `FixedCodec`
+    FixedLe { width: u16 },
+}
+
+impl StreamEncoding {
+    const FIXED_LE: u8 = 0;
+
+    fn fixed<C: FixedCodec>() -> Self {
+        StreamEncoding::FixedLe {
+            width: C::WIDTH as u16,
+        }
+    }
+
+    fn to_bytes(self) -> [u8; 3] {
+        match self {
+            StreamEncoding::FixedLe { width } => {
+                let [a, b] = width.to_le_bytes();
+                [Self::FIXED_LE, a, b]
+            }
+        }
+    }
+
+    fn from_bytes(bytes: [u8; 3]) -> Result<Self, ReadError> {
+        match bytes[0] {
+            Self::FIXED_LE => Ok(StreamEncoding::FixedLe {
+                width: u16::from_le_bytes([bytes[1], bytes[2]]),
+            }),
+            tag => Err(ReadError::UnsupportedEncoding(tag)),
+        }
+    }
+}
+
+/// A value with a fixed width plain bytes representation
+/// # Details
+/// `usize` and `NonZeroUsize` are always 8 bytes, so streams are portable
+pub trait FixedCodec: Sized {
+    /// The count of bytes of one value
+    const WIDTH: usize;
+
+    /// Write the bytes into `bytes`, whose length is `WIDTH`
+    fn encode(&self, bytes: &mut [u8]);
+
+    /// Read a value from `bytes`, whose length is `WIDTH`
+    /// # Returns
+    /// Return None if the bytes are not a valid value
+    fn decode(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_fixed_codec {
+    ($($ty:ty),*) => {
+        $(
+            impl FixedCodec for $ty {
+                const WIDTH: usize = std::mem::size_of::<$ty>();
+
+                fn encode(&self, bytes: &mut [u8]) {
+                    bytes.copy_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    Some(<$ty>::from_le_bytes(TryFrom::try_from(bytes).ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+macro_rules! impl_fixed_codec_non_zero {
+    ($($ty:ty => $inner:ty),*) => {
+        $(
+            impl FixedCodec for $ty {
+                const WIDTH: usize = <$inner as FixedCodec>::WIDTH;
+
+                fn encode(&self, bytes: &mut [u8]) {
+                    self.get().encode(bytes)
+                }
+
+                fn decode(bytes: &[u8]) -> Option<Self> {
+                    <$ty>::new(<$inner>::decode(bytes)?)
+                }
+            }
+        )*
+    };
+}
+
+impl_fixed_codec_non_zero!(
+    NonZeroU8 => u8,
+    NonZeroU16 => u16,
+    NonZeroU32 => u32,
+    NonZeroU64 => u64
+);
+
+impl FixedCodec for usize {
+    const WIDTH: usize = 8;
+
+    fn encode(&self, bytes: &mut [u8]) {
+        (*self as u64).encode(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        usize::try_from(u64::decode(bytes)?).ok()
+    }
+}
+
+impl FixedCodec for NonZeroUsize {
+    const WIDTH: usize = 8;
+
+    fn encode(&self, bytes: &mut [u8]) {
+        self.get().encode(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        NonZeroUsize::new(usize::decode(bytes)?)
+    }
+}
+
+impl FixedCodec for bool {
+    const WIDTH: usize = 1;
+
+    fn encode(&self, bytes: &mut [u8]) {
+        bytes[0] = *self as u8;
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        match bytes[0] {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl FixedCodec for () {
+    const WIDTH: usize = 0;
+
+    fn encode(&self, _: &mut [u8]) {}
+
+    fn decode(_: &[u8]) -> Option<Self> {
+        Some(())
+    }
+}
+
+/// The error of `SparseSet::read_from`
+#[derive(Debug)]
+pub enum ReadError {
+    /// The reader failed
+    Io(io::Error),
+    /// The stream ends before the header or the last entity
+    Truncated,
+    /// The stream doesn't start with the magic bytes `XSPS`
+    BadMagic([u8; 4]),
+    /// The stream is written by a newer or unknown format version
+    UnsupportedVersion(u16),
+    /// The encoding tag is unknown
+    UnsupportedEncoding(u8),
+    /// The encoding of ids or data in the stream is not the one of `E` or `T`
+    EncodingMismatch {
+        expected: StreamEncoding,
+        found: StreamEncoding,
+    },
+    /// The id of the entity at `index` is not a valid `E`
+    InvalidId { index: u64 },
+    /// The data of the entity at `index` is not a valid `T`
+    InvalidData { index: u64 },
+    /// The id of the entity at `index` appears before
+    DuplicateId { index: u64 },
+    /// The entity at `index` can't be added within the limits or the address space of the storage
+    LimitExceeded { index: u64, error: LimitExceeded },
+}
+
+impl Display for ReadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReadError::Io(error) => write!(f, "io error: {}", error),
+            ReadError::Truncated => write!(f, "the stream is truncated"),
+            ReadError::BadMagic(magic) => write!(f, "bad magic {:?}", magic),
+            ReadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            ReadError::UnsupportedEncoding(tag) => write!(f, "unsupported encoding tag {}", tag),
+            ReadError::EncodingMismatch { expected, found } => {
+                write!(f, "expected encoding {:?}, found {:?}", expected, found)
+            }
+            ReadError::InvalidId { index } => write!(f, "invalid id at index {}", index),
+            ReadError::InvalidData { index } => write!(f, "invalid data at index {}", index),
+            ReadError::DuplicateId { index } => write!(f, "duplicated id at index {}", index),
+            ReadError::LimitExceeded { index, error } => {
+                write!(f, "entity at index {} can't be added: {}", index, error)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ReadError::Io(error) => Some(error),
+            ReadError::LimitExceeded { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(error: io::Error) -> Self {
+        if error.kind() == io::ErrorKind::UnexpectedEof {
+            ReadError::Truncated
+        } else {
+            ReadError::Io(error)
+        }
+    }
+}
+
+/// The count of entities reserved before reading them,
+/// so a broken count can't allocate a huge buffer
+const READ_RESERVE_LIMIT: usize = 1 << 16;
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + FixedCodec,
+    S: SparseStorage<EntityId = E>,
+{
+    /// The count of bytes each entity costs besides its data, which is the encoded id
+    pub const STREAM_ENTRY_OVERHEAD: usize = E::WIDTH;
+
+    /// Estimate the count of bytes of the sparse set serialized entity by entity
+    /// # Details
+    /// It is `STREAM_HEADER_LEN` plus `STREAM_ENTRY_OVERHEAD + per_value(&data)` of every entity,
+    /// so it is exact for `write_to` if `per_value` returns `T::WIDTH`
+    pub fn serialized_size_hint<F>(&self, per_value: F) -> usize
+    where
+        F: Fn(&T) -> usize,
+    {
+        STREAM_HEADER_LEN
+            + Self::STREAM_ENTRY_OVERHEAD * self.len()
+            + self.data.iter().map(per_value).sum::<usize>()
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy + FixedCodec,
+    T: FixedCodec,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the exact count of bytes written by `write_to`
+    pub fn raw_size(&self) -> usize {
+        STREAM_HEADER_LEN + (E::WIDTH + T::WIDTH) * self.len()
+    }
+
+    /// Write the sparse set into `writer` entity by entity
+    /// # Details
+    /// The stream is a header
+    /// (magic, format version, encodings of ids and data, count of entities)
+    /// followed by `(id, data)` pairs in dense order
+    /// # Returns
+    /// Return the count of bytes written
+    pub fn write_to<W>(&self, mut writer: W) -> io::Result<u64>
+    where
+        W: Write,
+    {
+        writer.write_all(&STREAM_MAGIC)?;
+        writer.write_all(&STREAM_VERSION.to_le_bytes())?;
+        writer.write_all(&StreamEncoding::fixed::<E>().to_bytes())?;
+        writer.write_all(&StreamEncoding::fixed::<T>().to_bytes())?;
+        writer.write_all(&(self.len() as u64).to_le_bytes())?;
+
+        let mut buffer = vec![0; E::WIDTH + T::WIDTH];
+        for (id, dat) in self.dense.iter().zip(self.data.iter()) {
+            id.encode(&mut buffer[..E::WIDTH]);
+            dat.encode(&mut buffer[E::WIDTH..]);
+            writer.write_all(&buffer)?;
+        }
+
+        Ok((STREAM_HEADER_LEN + buffer.len() * self.len()) as u64)
+    }
+
+    /// Read a sparse set written by `write_to` from `reader`
+    /// # Details
+    /// The entities keep the dense order in the stream.
+    /// Any sparse set written by `write_to` can be read back, however large its ids are.  
+    /// Storages made of slots like `VecStorage` allocate a slot for every id up to the greatest one,
+    /// so use `read_from_with_limits` with `max_sparse_slots` to read untrusted streams
+    pub fn read_from<R>(reader: R) -> Result<Self, ReadError>
+    where
+        R: Read,
+        S: Default,
+    {
+        Self::read_from_with_limits(reader, SparseSetLimits::default())
+    }
+
+    /// Read a sparse set written by `write_to` from `reader` within `limits`
+    /// # Details
+    /// Every id is checked like `try_insert` before it is added,
+    /// so an id out of `limits` or out of the address space of the storage
+    /// is reported as `ReadError::LimitExceeded`.
+    /// The sparse set read keeps `limits`
+    pub fn read_from_with_limits<R>(
+        mut reader: R,
+        limits: SparseSetLimits,
+    ) -> Result<Self, ReadError>
+    where
+        R: Read,
+        S: Default,
+    {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != STREAM_MAGIC {
+            return Err(ReadError::BadMagic(magic));
+        }
+        let mut version = [0; 2];
+        reader.read_exact(&mut version)?;
+        let version = u16::from_le_bytes(version);
+        if version == 0 || version > STREAM_VERSION {
+            return Err(ReadError::UnsupportedVersion(version));
+        }
+        read_encoding::<_, E>(&mut reader)?;
+        read_encoding::<_, T>(&mut reader)?;
+        let mut count = [0; 8];
+        reader.read_exact(&mut count)?;
+        let count = u64::from_le_bytes(count);
+
+        let mut sparse_set = SparseSet::default();
+        sparse_set.set_limits(limits);
+        sparse_set.reserve(
+            usize::try_from(count)
+                .map_or(READ_RESERVE_LIMIT, |count| count.min(READ_RESERVE_LIMIT)),
+        );
+        let mut buffer = vec![0; E::WIDTH + T::WIDTH];
+        for index in 0..count {
+            reader.read_exact(&mut buffer)?;
+            let id = E::decode(&buffer[..E::WIDTH]).ok_or(ReadError::InvalidId { index })?;
+            let dat = T::decode(&buffer[E::WIDTH..]).ok_or(ReadError::InvalidData { index })?;
+            if sparse_set.contains(id) {
+                return Err(ReadError::DuplicateId { index });
+            }
+            sparse_set
+                .check_limits(1, &[id])
+                .map_err(|error| ReadError::LimitExceeded { index, error })?;
+            sparse_set.push_unchecked(id, dat);
+        }
+        Ok(sparse_set)
+    }
+}
+
+fn read_encoding<R, C>(reader: &mut R) -> Result<(), ReadError>
+where
+    R: Read,
+    C: FixedCodec,
+{
+    let mut bytes = [0; 3];
+    reader.read_exact(&mut bytes)?;
+    let found = StreamEncoding::from_bytes(bytes)?;
+    let expected = StreamEncoding::fixed::<C>();
+    if found != expected {
+        return Err(ReadError::EncodingMismatch { expected, found });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Cursor, num::NonZeroUsize};
+
+    use rand::{thread_rng, Rng};
+
+    use crate::{
+        stream::STREAM_VERSION,
+        LimitExceeded, ReadError, SparseSet, SparseSetHashMap, SparseSetLimits, SparseSetVec,
+        StreamEncoding, STREAM_HEADER_LEN,
+    };
+
+    type EntityId = NonZeroUsize;
+
+    #[test]
+    fn round_trip_test() {
+        let mut rng = thread_rng();
+        let mut sparse_set: SparseSetVec<EntityId, f64> = SparseSet::default();
+        for _ in 0..1000 {
+            let id = EntityId::new(rng.gen_range(1..5000)).unwrap();
+            sparse_set.insert(id, rng.gen());
+        }
+
+        let mut buffer = Vec::new();
+        let written = sparse_set.write_to(&mut buffer).unwrap();
+        assert_eq!(written, buffer.len() as u64);
+        assert_eq!(written, 20 + 16 * sparse_set.len() as u64);
+
+        let read: SparseSetVec<EntityId, f64> = SparseSet::read_from(Cursor::new(&buffer)).unwrap();
+        assert_eq!(read.ids(), sparse_set.ids());
+        assert_eq!(read.data(), sparse_set.data());
+        assert_eq!(read.check_invariants(), Ok(()));
+
+        // another storage
+        let read: SparseSetHashMap<EntityId, f64> =
+            SparseSet::read_from(Cursor::new(&buffer)).unwrap();
+        assert_eq!(read.ids(), sparse_set.ids());
+
+        // empty
+        let empty: SparseSetVec<u16, ()> = SparseSet::default();
+        let mut buffer = Vec::new();
+        assert_eq!(empty.write_to(&mut buffer).unwrap(), 20);
+        let read: SparseSetVec<u16, ()> = SparseSet::read_from(buffer.as_slice()).unwrap();
+        assert!(read.is_empty());
+    }
+
+    #[test]
+    fn size_test() {
+        let mut sparse_set: SparseSetVec<u16, u32> = SparseSet::default();
+        assert_eq!(sparse_set.raw_size(), 20);
+        for id in 0..100 {
+            sparse_set.insert(id * 3, id as u32);
+        }
+        let mut buffer = Vec::new();
+        sparse_set.write_to(&mut buffer).unwrap();
+        assert_eq!(sparse_set.raw_size(), buffer.len());
+        assert_eq!(sparse_set.serialized_size_hint(|_| 4), buffer.len());
+        assert_eq!(SparseSetVec::<u16, u32>::STREAM_ENTRY_OVERHEAD, 2);
+
+        let mut names: SparseSetVec<u16, String> = SparseSet::default();
+        names.insert(1, "abc".to_string());
+        names.insert(7, "de".to_string());
+        assert_eq!(
+            names.serialized_size_hint(|name| 8 + name.len()),
+            STREAM_HEADER_LEN + 2 * 2 + 8 * 2 + 5
+        );
+    }
+
+    #[test]
+    fn read_error_test() {
+        let mut sparse_set: SparseSetVec<u16, u32> = SparseSet::default();
+        for x in 0..10 {
+            sparse_set.insert(x * 3, x as u32);
+        }
+        let mut buffer = Vec::new();
+        sparse_set.write_to(&mut buffer).unwrap();
+
+        let read = |bytes: &[u8]| SparseSetVec::<u16, u32>::read_from(bytes);
+        for len in [0, 3, 10, 19, 20, 25, buffer.len() - 1] {
+            assert!(matches!(read(&buffer[..len]), Err(ReadError::Truncated)));
+        }
+
+        let mut bad = buffer.clone();
+        bad[0] = b'Y';
+        assert!(matches!(read(&bad), Err(ReadError::BadMagic(_))));
+
+        let mut future = buffer.clone();
+        future[4..6].copy_from_slice(&(STREAM_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read(&future),
+            Err(ReadError::UnsupportedVersion(version)) if version == STREAM_VERSION + 1
+        ));
+        let mut never_written = buffer.clone();
+        never_written[4..6].copy_from_slice(&0u16.to_le_bytes());
+        assert!(matches!(
+            read(&never_written),
+            Err(ReadError::UnsupportedVersion(0))
+        ));
+
+        let mut unknown = buffer.clone();
+        unknown[6] = 7;
+        assert!(matches!(
+            read(&unknown),
+            Err(ReadError::UnsupportedEncoding(7))
+        ));
+
+        assert!(matches!(
+            SparseSetVec::<u8, u32>::read_from(buffer.as_slice()),
+            Err(ReadError::EncodingMismatch {
+                expected: StreamEncoding::FixedLe { width: 1 },
+                found: StreamEncoding::FixedLe { width: 2 },
+            })
+        ));
+
+        // the second entity has the id of the first one
+        let mut duplicated = buffer.clone();
+        duplicated.copy_within(20..22, 26);
+        assert!(matches!(
+            read(&duplicated),
+            Err(ReadError::DuplicateId { index: 1 })
+        ));
+
+        let zero: SparseSetVec<u16, bool> = SparseSet::default();
+        let mut buffer = Vec::new();
+        zero.write_to(&mut buffer).unwrap();
+        buffer[12..20].copy_from_slice(&1u64.to_le_bytes());
+        buffer.extend_from_slice(&[1, 0, 2]);
+        assert!(matches!(
+            SparseSetVec::<u16, bool>::read_from(buffer.as_slice()),
+            Err(ReadError::InvalidData { index: 0 })
+        ));
+    }
+
+    #[test]
+    fn read_limits_test() {
+        let mut sparse_set: SparseSetVec<usize, u8> = SparseSet::default();
+        for x in 0..10 {
+            sparse_set.insert(x * 3, x as u8);
+        }
+        let mut buffer = Vec::new();
+        sparse_set.write_to(&mut buffer).unwrap();
+
+        // a broken id is rejected before the slots are allocated
+        let mut huge = buffer.clone();
+        huge[20 + 9 * 2..20 + 9 * 2 + 8].copy_from_slice(&(1usize << 60).to_le_bytes());
+        let limits = SparseSetLimits {
+            max_sparse_slots: Some(1 << 20),
+            ..SparseSetLimits::default()
+        };
+        assert!(matches!(
+            SparseSetVec::<usize, u8>::read_from_with_limits(huge.as_slice(), limits),
+            Err(ReadError::LimitExceeded {
+                index: 2,
+                error: LimitExceeded::MaxSparseSlots(1_048_576),
+            })
+        ));
+        // the map storages have no slots
+        let read = SparseSetHashMap::<usize, u8>::read_from(huge.as_slice()).unwrap();
+        assert!(read.contains(1 << 60));
+
+        let limits = SparseSetLimits {
+            max_len: Some(4),
+            ..SparseSetLimits::default()
+        };
+        assert!(matches!(
+            SparseSetVec::<usize, u8>::read_from_with_limits(buffer.as_slice(), limits),
+            Err(ReadError::LimitExceeded {
+                index: 4,
+                error: LimitExceeded::MaxLen(4),
+            })
+        ));
+        let limits = SparseSetLimits {
+            max_len: Some(10),
+            ..SparseSetLimits::default()
+        };
+        let read =
+            SparseSetVec::<usize, u8>::read_from_with_limits(buffer.as_slice(), limits).unwrap();
+        assert_eq!(read.limits(), limits);
+        assert_eq!(read.ids(), sparse_set.ids());
+        assert_eq!(
+            SparseSetVec::<usize, u8>::read_from(buffer.as_slice())
+                .unwrap()
+                .limits(),
+            SparseSetLimits::default()
+        );
+    }
+
+    #[test]
+    fn read_large_id_test() {
+        // a valid set always round-trips, even with ids past 2^24
+        let mut sparse_set: SparseSetVec<usize, u8> = SparseSet::default();
+        sparse_set.insert(3, 1);
+        sparse_set.insert((1 << 24) + 7, 2);
+        let mut buffer = Vec::new();
+        sparse_set.write_to(&mut buffer).unwrap();
+        let read = SparseSetVec::<usize, u8>::read_from(buffer.as_slice()).unwrap();
+        assert_eq!(read.ids(), sparse_set.ids());
+        assert_eq!(read.data(), sparse_set.data());
+    }
+}
@@ -0,0 +1,172 @@
+use std::ops::{Deref, DerefMut};
+
+use crate::{SparseSet, SparseStorage};
+
+/// Data with the tick it was changed last time
+/// # Details
+/// The tick is any increasing counter of the caller, like the frame number.
+/// Store `Tracked<T>` in a sparse set and use `get_mut_tracked` or `iter_mut_tracked`
+/// to write through `Mut`, which only updates the tick when the data is written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tracked<T> {
+    value: T,
+    changed_tick: u32,
+}
+
+impl<T> Tracked<T> {
+    /// Create tracked data changed at `tick`
+    pub fn new(value: T, tick: u32) -> Self {
+        Tracked {
+            value,
+            changed_tick: tick,
+        }
+    }
+
+    /// Get the reference of data
+    pub fn get(&self) -> &T {
+        &self.value
+    }
+
+    /// Get the tick it was changed last time
+    pub fn changed_tick(&self) -> u32 {
+        self.changed_tick
+    }
+
+    /// Check if it was changed at or after `tick`
+    pub fn is_changed_since(&self, tick: u32) -> bool {
+        self.changed_tick >= tick
+    }
+
+    /// Borrow it mutably, writes are recorded at `tick`
+    /// # Details
+    /// Use it to get `Mut` from other iterators yielding `&mut Tracked<T>`
+    pub fn as_mut(&mut self, tick: u32) -> Mut<'_, T> {
+        Mut {
+            value: &mut self.value,
+            changed_tick: &mut self.changed_tick,
+            tick,
+        }
+    }
+
+    /// Take the data out
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+/// A MUTABLE reference recording the change tick on write
+/// # Details
+/// `Deref` doesn't touch the tick, the first `DerefMut` records it
+#[derive(Debug)]
+pub struct Mut<'a, T> {
+    value: &'a mut T,
+    changed_tick: &'a mut u32,
+    tick: u32,
+}
+
+impl<'a, T> Mut<'a, T> {
+    /// Get the MUTABLE reference without recording the change
+    pub fn bypass_change_detection(&mut self) -> &mut T {
+        self.value
+    }
+
+    /// Check if it has been written through this guard
+    /// or was changed at the tick of this guard before
+    pub fn is_changed(&self) -> bool {
+        *self.changed_tick == self.tick
+    }
+}
+
+impl<'a, T> Deref for Mut<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &Self::Target {
+        self.value
+    }
+}
+
+impl<'a, T> DerefMut for Mut<'a, T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        if *self.changed_tick != self.tick {
+            *self.changed_tick = self.tick;
+        }
+        self.value
+    }
+}
+
+impl<E, T, S> SparseSet<E, Tracked<T>, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the MUTABLE reference of data by given `id`, writes are recorded at `tick`
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn get_mut_tracked(&mut self, id: E, tick: u32) -> Option<Mut<'_, T>> {
+        Some(self.get_mut(id)?.as_mut(tick))
+    }
+
+    /// Iterate `(id, Mut<data>)` pairs in dense order, writes are recorded at `tick`
+    pub fn iter_mut_tracked(&mut self, tick: u32) -> impl Iterator<Item = (E, Mut<'_, T>)> {
+        self.dense
+            .iter()
+            .zip(self.data.iter_mut())
+            .map(move |(id, dat)| (*id, dat.as_mut(tick)))
+    }
+
+    /// Iterate `(id, &data)` pairs changed at or after `tick` in dense order
+    pub fn iter_changed_since(&self, tick: u32) -> impl Iterator<Item = (E, &T)> {
+        self.iter()
+            .filter(move |(_, dat)| dat.is_changed_since(tick))
+            .map(|(id, dat)| (id, dat.get()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{SparseSet, SparseSetVec, Tracked};
+
+    #[test]
+    fn tracked_test() {
+        let mut sparse_set: SparseSetVec<usize, Tracked<u32>> = SparseSet::default();
+        for id in 0..10 {
+            sparse_set.insert(id, Tracked::new(id as u32, 0));
+        }
+
+        // read only
+        let guard = sparse_set.get_mut_tracked(3, 1).unwrap();
+        assert_eq!(*guard, 3);
+        assert!(!guard.is_changed());
+        assert!(!sparse_set.get(3).unwrap().is_changed_since(1));
+
+        // bypass
+        let mut guard = sparse_set.get_mut_tracked(3, 1).unwrap();
+        *guard.bypass_change_detection() = 30;
+        assert_eq!(sparse_set.get(3).unwrap().changed_tick(), 0);
+
+        // write
+        let mut guard = sparse_set.get_mut_tracked(4, 1).unwrap();
+        *guard += 1;
+        assert!(guard.is_changed());
+        assert_eq!(sparse_set.get(4).unwrap().changed_tick(), 1);
+        assert!(sparse_set.get_mut_tracked(100, 1).is_none());
+
+        // read-modify-maybe-write
+        for (_, mut x) in sparse_set.iter_mut_tracked(2) {
+            if *x % 2 == 0 {
+                *x += 100;
+            }
+        }
+        let changed: Vec<_> = sparse_set.iter_changed_since(2).map(|(id, _)| id).collect();
+        assert_eq!(changed, vec![0, 2, 3, 6, 8]);
+        let changed: Vec<_> = sparse_set.iter_changed_since(1).map(|(id, _)| id).collect();
+        assert_eq!(changed, vec![0, 2, 3, 4, 6, 8]);
+
+        // from other iterators of &mut Tracked<T>
+        let ((_, left), _) = sparse_set.split_data_at_mut(2);
+        for x in left {
+            let _ = *x.as_mut(3);
+        }
+        assert_eq!(sparse_set.iter_changed_since(3).count(), 0);
+    }
+}
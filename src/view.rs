@@ -0,0 +1,310 @@
+use std::cmp::Ordering;
+
+use crate::{Iter, SparseSet, SparseStorage};
+
+/// A read-only view of `SparseSet`
+/// # Details
+/// It only borrows the sparse set immutably and can be copied freely,
+/// so many readers can share it without any way to mutate the sparse set
+#[derive(Debug)]
+pub struct SparseSetView<'a, E, T, S> {
+    sparse: &'a S,
+    dense: &'a [E],
+    data: &'a [T],
+}
+
+impl<'a, E, T, S> Clone for SparseSetView<'a, E, T, S> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E, T, S> Copy for SparseSetView<'a, E, T, S> {}
+
+/// The dense arrays of `SparseSet` as aligned columns
+/// # Details
+/// `data[i]` is the data of `ids[i]` for every `i`,
+/// so serialization and FFI code can't pair them wrongly
+#[derive(Debug)]
+pub struct Columns<'a, E, T> {
+    /// The ids in dense order
+    pub ids: &'a [E],
+    /// The data in dense order
+    pub data: &'a [T],
+}
+
+impl<'a, E, T> Clone for Columns<'a, E, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E, T> Copy for Columns<'a, E, T> {}
+
+impl<'a, E, T> Columns<'a, E, T> {
+    /// Get the count of rows
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if there is no row
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+}
+
+/// A read-only sequence of `(key, &value)` pairs, like `&[(K, V)]`
+/// # Details
+/// Generic code written against it accepts both a slice of pairs
+/// and the `Entries` of a sparse set, which stores the keys and values apart
+pub trait PairSlice<K, V> {
+    /// Get the count of pairs
+    fn len(&self) -> usize;
+
+    /// Get the pair at `index`
+    fn get(&self, index: usize) -> Option<(K, &V)>;
+
+    /// Check if there is no pair
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Binary search the pairs sorted by `f` like `slice::binary_search_by`
+    fn binary_search_by<F>(&self, mut f: F) -> Result<usize, usize>
+    where
+        F: FnMut(K, &V) -> Ordering,
+    {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let (key, value) = self.get(mid).expect("mid is less than len");
+            match f(key, value) {
+                Ordering::Less => low = mid + 1,
+                Ordering::Greater => high = mid,
+                Ordering::Equal => return Ok(mid),
+            }
+        }
+        Err(low)
+    }
+}
+
+impl<K, V> PairSlice<K, V> for [(K, V)]
+where
+    K: Copy,
+{
+    fn len(&self) -> usize {
+        <[(K, V)]>::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<(K, &V)> {
+        <[(K, V)]>::get(self, index).map(|(key, value)| (*key, value))
+    }
+}
+
+/// The dense arrays of `SparseSet` as a sequence of `(id, &data)` pairs
+/// # Details
+/// The ids and data are stored apart, so it can't be a real `&[(E, T)]`.
+/// It implements `PairSlice` instead.
+/// `Index` is not implemented because it must return a reference,
+/// use `get` for the pair or `data()[index]` for the data
+#[derive(Debug)]
+pub struct Entries<'a, E, T> {
+    ids: &'a [E],
+    data: &'a [T],
+}
+
+impl<'a, E, T> Clone for Entries<'a, E, T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, E, T> Copy for Entries<'a, E, T> {}
+
+impl<'a, E, T> Entries<'a, E, T>
+where
+    E: Copy,
+{
+    /// Get the count of pairs
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Check if there is no pair
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Get the pair at dense `index`
+    pub fn get(&self, index: usize) -> Option<(E, &'a T)> {
+        Some((*self.ids.get(index)?, self.data.get(index)?))
+    }
+
+    /// Iterate the pairs in dense order
+    pub fn iter(&self) -> Iter<'a, E, T> {
+        Iter {
+            ids: self.ids.iter(),
+            data: self.data.iter(),
+        }
+    }
+
+    /// Get the data in dense order
+    pub fn data(&self) -> &'a [T] {
+        self.data
+    }
+
+    /// Binary search the pairs sorted by `f` like `slice::binary_search_by`
+    pub fn binary_search_by<F>(&self, f: F) -> Result<usize, usize>
+    where
+        F: FnMut(E, &T) -> Ordering,
+    {
+        PairSlice::binary_search_by(self, f)
+    }
+
+    /// Copy the pairs into a Vec, for APIs that need a real slice
+    pub fn to_vec(&self) -> Vec<(E, T)>
+    where
+        T: Clone,
+    {
+        self.iter().map(|(id, dat)| (id, dat.clone())).collect()
+    }
+}
+
+impl<'a, E, T> PairSlice<E, T> for Entries<'a, E, T>
+where
+    E: Copy,
+{
+    fn len(&self) -> usize {
+        Entries::len(self)
+    }
+
+    fn get(&self, index: usize) -> Option<(E, &T)> {
+        Entries::get(self, index)
+    }
+}
+
+impl<'a, E, T> From<Columns<'a, E, T>> for Entries<'a, E, T> {
+    fn from(columns: Columns<'a, E, T>) -> Self {
+        Entries {
+            ids: columns.ids,
+            data: columns.data,
+        }
+    }
+}
+
+impl<'a, E, T> IntoIterator for Entries<'a, E, T>
+where
+    E: Copy,
+{
+    type Item = (E, &'a T);
+    type IntoIter = Iter<'a, E, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<E, T, S> SparseSet<E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the ids and data as aligned columns
+    /// # Examples
+    /// ```
+    /// use xsparseset::SparseSetVec;
+    ///
+    /// let mut sparse_set: SparseSetVec<u16, f32> = SparseSetVec::default();
+    /// sparse_set.insert(7, 1.5);
+    /// sparse_set.insert(3, -2.0);
+    ///
+    /// let columns = sparse_set.columns();
+    /// let mut bytes = Vec::new();
+    /// bytes.extend((columns.len() as u32).to_le_bytes());
+    /// for id in columns.ids {
+    ///     bytes.extend(id.to_le_bytes());
+    /// }
+    /// for dat in columns.data {
+    ///     bytes.extend(dat.to_le_bytes());
+    /// }
+    /// assert_eq!(bytes.len(), 4 + 2 * 2 + 2 * 4);
+    /// assert_eq!(&bytes[4..6], &7u16.to_le_bytes());
+    /// assert_eq!(&bytes[12..16], &(-2.0f32).to_le_bytes());
+    /// ```
+    pub fn columns(&self) -> Columns<'_, E, T> {
+        Columns {
+            ids: &self.dense,
+            data: &self.data,
+        }
+    }
+
+    /// Get the ids and data as a sequence of `(id, &data)` pairs
+    pub fn entries(&self) -> Entries<'_, E, T> {
+        self.columns().into()
+    }
+
+    /// Get a read-only view of sparse set
+    pub fn view(&self) -> SparseSetView<'_, E, T, S> {
+        SparseSetView {
+            sparse: &self.sparse,
+            dense: &self.dense,
+            data: &self.data,
+        }
+    }
+}
+
+impl<'a, E, T, S> SparseSetView<'a, E, T, S>
+where
+    E: Copy,
+    S: SparseStorage<EntityId = E>,
+{
+    /// Get the count of entities in sparse set
+    pub fn len(&self) -> usize {
+        self.dense.len()
+    }
+
+    /// Check sparse set is empty
+    pub fn is_empty(&self) -> bool {
+        self.dense.is_empty()
+    }
+
+    /// Check if the sparse set has id
+    pub fn contains(&self, id: E) -> bool {
+        self.sparse.get_index(id).is_some()
+    }
+
+    /// Get the reference of data by given `id`
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn get(&self, id: E) -> Option<&'a T> {
+        let index = self.sparse.get_index(id)?.get() - 1;
+        // Safety
+        // The index stored in sparse is always in range
+        unsafe { Some(self.data.get_unchecked(index)) }
+    }
+
+    /// Get the index of the entity was given by `id` in sparse set
+    /// # Returns
+    /// Return None if sparse set doesn't contain this `id`
+    pub fn get_index(&self, id: E) -> Option<usize> {
+        self.sparse.get_index(id).map(|x| x.get() - 1)
+    }
+
+    /// Get the slice of data
+    pub fn data(&self) -> &'a [T] {
+        self.data
+    }
+
+    /// Get the slice of ID , or the dense array
+    pub fn ids(&self) -> &'a [E] {
+        self.dense
+    }
+
+    /// Iterate `(id, &data)` pairs in dense order
+    pub fn iter(&self) -> Iter<'a, E, T> {
+        Iter {
+            ids: self.dense.iter(),
+            data: self.data.iter(),
+        }
+    }
+}